@@ -0,0 +1,30 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use obj::raw::{parse_mtl, parse_obj};
+use obj::{load_obj_with, LoadOptions, Obj, Position, Triangulation};
+
+fn parse(c: &mut Criterion) {
+    let sponza = std::fs::read("tests/fixtures/sponza.obj").unwrap();
+    let untitled = std::fs::read("tests/fixtures/untitled.mtl").unwrap();
+
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(20);
+
+    group.throughput(Throughput::Bytes(sponza.len() as u64));
+    group.bench_function("parse_obj sponza", |b| {
+        b.iter(|| parse_obj(&sponza[..]).unwrap())
+    });
+    group.bench_function("load_obj sponza", |b| {
+        let options = LoadOptions::new().triangulate(Triangulation::Fan);
+        b.iter(|| -> Obj<Position> { load_obj_with(&sponza[..], &options).unwrap() })
+    });
+
+    group.throughput(Throughput::Bytes(untitled.len() as u64));
+    group.bench_function("parse_mtl untitled", |b| {
+        b.iter(|| parse_mtl(&untitled[..]).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -5,6 +5,10 @@ use std::error::Error;
 use std::fmt;
 use std::io;
 use std::num::{ParseFloatError, ParseIntError};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// A type for results generated by `load_obj` and `load_mtl` where the `Err` type is hard-wired to
 /// `ObjError`
@@ -19,29 +23,109 @@ pub enum ObjError {
     /// IO error has been occurred during opening the `obj` file.
     Io(io::Error),
     /// Tried to parse integer frome the `obj` file, but failed.
-    ParseInt(ParseIntError),
+    ParseInt(ParseIntError, Location),
     /// Tried to parse floating point number frome the `obj` file, but failed.
-    ParseFloat(ParseFloatError),
+    ParseFloat(ParseFloatError, Location),
     /// `LoadError` has been occurred during parseing the `obj` file.
     Load(LoadError),
 }
 
-macro_rules! implmnt {
-    ($name:ident, $error:path) => {
-        impl From<$error> for ObjError {
-            fn from(err: $error) -> Self {
-                ObjError::$name(err)
+impl ObjError {
+    /// Returns where in the input the error has been occurred, if it is known.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            ObjError::Io(_) => None,
+            ObjError::ParseInt(_, ref location) => Some(location),
+            ObjError::ParseFloat(_, ref location) => Some(location),
+            ObjError::Load(ref e) => Some(e.location()),
+        }
+    }
+
+    fn location_mut(&mut self) -> Option<&mut Location> {
+        match self {
+            ObjError::Io(_) => None,
+            ObjError::ParseInt(_, ref mut location) => Some(location),
+            ObjError::ParseFloat(_, ref mut location) => Some(location),
+            ObjError::Load(ref mut e) => Some(&mut e.location),
+        }
+    }
+
+    /// Records the line where the error has been occurred, unless it is already known.
+    pub(crate) fn at_line(mut self, line: usize) -> Self {
+        if let Some(location) = self.location_mut() {
+            location.line.get_or_insert(line);
+        }
+        self
+    }
+
+    /// Records the statement where the error has been occurred, unless it is already known.
+    pub(crate) fn in_statement(mut self, statement: &str) -> Self {
+        if let Some(location) = self.location_mut() {
+            if location.statement.is_none() {
+                location.statement = Some(statement.to_string());
             }
         }
-    };
+        self
+    }
+
+    /// Converts the error into a `Warning`, or gives the error back if the parser can't recover
+    /// from it by skipping the statement where it has been occurred.
+    pub(crate) fn into_warning(self) -> Result<Warning, ObjError> {
+        let (kind, reason, location) = match self {
+            ObjError::Io(_) => return Err(self),
+            ObjError::Load(LoadError {
+                kind: LoadErrorKind::BackslashAtEOF,
+                ..
+            }) => return Err(self),
+            ObjError::ParseInt(e, location) => (None, e.to_string(), location),
+            ObjError::ParseFloat(e, location) => (None, e.to_string(), location),
+            ObjError::Load(e) => (
+                Some(e.kind),
+                format!("{}: {}", e.kind.description(), e.message),
+                e.location,
+            ),
+        };
+
+        Ok(Warning {
+            file: location.file,
+            line: location.line.unwrap_or(0),
+            statement: location.statement.unwrap_or_default(),
+            token: location.token,
+            kind,
+            reason,
+        })
+    }
+
+    /// Records the file where the error has been occurred, e.g. a material library which an OBJ
+    /// file refers to. IO errors get the path in their messages instead.
+    pub(crate) fn in_file(mut self, path: &Path) -> Self {
+        if let ObjError::Io(e) = self {
+            let message = format!("{}: {}", path.display(), e);
+            return ObjError::Io(io::Error::new(e.kind(), message));
+        }
+        if let Some(location) = self.location_mut() {
+            location.file.get_or_insert_with(|| path.to_owned());
+        }
+        self
+    }
+
+    /// Records the token which caused the error, unless it is already known.
+    pub(crate) fn with_token(mut self, token: &str) -> Self {
+        if let Some(location) = self.location_mut() {
+            if location.token.is_none() {
+                location.token = Some(token.to_string());
+            }
+        }
+        self
+    }
 }
 
 impl fmt::Display for ObjError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ObjError::Io(ref e) => e.fmt(f),
-            ObjError::ParseInt(ref e) => e.fmt(f),
-            ObjError::ParseFloat(ref e) => e.fmt(f),
+            ObjError::ParseInt(ref e, ref location) => write!(f, "{}{}", e, location),
+            ObjError::ParseFloat(ref e, ref location) => write!(f, "{}{}", e, location),
             ObjError::Load(ref e) => e.fmt(f),
         }
     }
@@ -51,23 +135,86 @@ impl Error for ObjError {
     fn cause(&self) -> Option<&dyn Error> {
         match *self {
             ObjError::Io(ref err) => Some(err),
-            ObjError::ParseInt(ref err) => Some(err),
-            ObjError::ParseFloat(ref err) => Some(err),
+            ObjError::ParseInt(ref err, _) => Some(err),
+            ObjError::ParseFloat(ref err, _) => Some(err),
             ObjError::Load(ref err) => Some(err),
         }
     }
 }
 
-implmnt!(Io, io::Error);
-implmnt!(ParseInt, ParseIntError);
-implmnt!(ParseFloat, ParseFloatError);
-implmnt!(Load, LoadError);
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+impl From<ParseIntError> for ObjError {
+    fn from(err: ParseIntError) -> Self {
+        ObjError::ParseInt(err, Location::default())
+    }
+}
+
+impl From<ParseFloatError> for ObjError {
+    fn from(err: ParseFloatError) -> Self {
+        ObjError::ParseFloat(err, Location::default())
+    }
+}
+
+impl From<LoadError> for ObjError {
+    fn from(err: LoadError) -> Self {
+        ObjError::Load(err)
+    }
+}
+
+/// Where in the input an error has been occurred.
+///
+/// Each field is `None` if the corresponding information is not known, e.g. for errors which are
+/// not generated by the parser.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Location {
+    /// Path of the file where the error has been occurred, if it's not the file being parsed,
+    /// e.g. a material library of `load_obj_with_materials`.
+    pub file: Option<PathBuf>,
+    /// Line number (1-based) where the erroneous statement begins.
+    pub line: Option<usize>,
+    /// Keyword of the erroneous statement, such as `v` or `newmtl`.
+    pub statement: Option<String>,
+    /// The token which caused the error.
+    pub token: Option<String>,
+}
+
+impl fmt::Display for Location {
+    /// Writes the location as a parenthesized suffix, or nothing if the location is unknown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(ref file) = self.file {
+            parts.push(format!("file `{}`", file.display()));
+        }
+        if let Some(line) = self.line {
+            parts.push(format!("line {}", line));
+        }
+        if let Some(ref statement) = self.statement {
+            parts.push(format!("statement `{}`", statement));
+        }
+        if let Some(ref token) = self.token {
+            parts.push(format!("token `{}`", token));
+        }
+
+        if parts.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " ({})", parts.join(", "))
+        }
+    }
+}
 
 /// The error type for parse operations of the `Obj` struct.
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct LoadError {
     kind: LoadErrorKind,
     message: &'static str,
+    location: Location,
 }
 
 impl LoadError {
@@ -75,13 +222,21 @@ impl LoadError {
     pub fn kind(&self) -> &LoadErrorKind {
         &self.kind
     }
+
+    /// Outputs where in the input loading an OBJ failed.
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
 }
 
 /// Enum to store the various types of errors that can cause loading an OBJ to fail.
 #[derive(Copy, PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum LoadErrorKind {
     /// Met unexpected statement.
     UnexpectedStatement,
+    /// Met a statement which is valid but not supported yet.
+    UnsupportedStatement,
     /// Received wrong number of arguments.
     WrongNumberOfArguments,
     /// Received unexpected type of arguments.
@@ -96,23 +251,30 @@ pub enum LoadErrorKind {
     BackslashAtEOF,
     /// Group number exceeded limitation.
     TooBigGroupNumber,
+    /// The model has more vertices than the index type can refer to.
+    IndexOverflow,
 }
 
 impl LoadError {
     /// Creates a new custom error from a specified kind and message.
     pub fn new(kind: LoadErrorKind, message: &'static str) -> Self {
-        LoadError { kind, message }
+        LoadError {
+            kind,
+            message,
+            location: Location::default(),
+        }
     }
 }
 
 impl Error for LoadError {}
 
-impl fmt::Display for LoadError {
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl LoadErrorKind {
+    fn description(self) -> &'static str {
         use LoadErrorKind::*;
 
-        let msg = match self.kind {
+        match self {
             UnexpectedStatement => "Met unexpected statement",
+            UnsupportedStatement => "Met unsupported statement",
             WrongNumberOfArguments => "Received wrong number of arguments",
             WrongTypeOfArguments => "Received unexpected type of arguments",
             UntriangulatedModel => "Model should be triangulated first to be loaded properly",
@@ -120,9 +282,52 @@ impl fmt::Display for LoadError {
             IndexOutOfRange => "Received index value out of range",
             BackslashAtEOF => r"A line is expected after the backslash (\)",
             TooBigGroupNumber => "Group number exceeded limitation.",
-        };
+            IndexOverflow => "Index doesn't fit in the index type",
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "{}: {}{}",
+            self.kind.description(),
+            self.message,
+            self.location
+        )
+    }
+}
 
-        write!(fmt, "{}: {}", msg, self.message)
+/// A statement which has been skipped, instead of failing the whole parse, in lenient mode.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Warning {
+    /// Path of the file which contains the skipped statement, if it's not the file being parsed,
+    /// e.g. a material library of `load_obj_with_materials`.
+    pub file: Option<PathBuf>,
+    /// Line number (1-based) where the skipped statement begins.
+    pub line: usize,
+    /// Keyword of the skipped statement, such as `v` or `newmtl`.
+    pub statement: String,
+    /// The token which caused the statement to be skipped, if it could be determined.
+    pub token: Option<String>,
+    /// Kind of the error which caused the statement to be skipped, or `None` if a number couldn't
+    /// be parsed.
+    pub kind: Option<LoadErrorKind>,
+    /// Human readable reason why the statement has been skipped.
+    pub reason: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = Location {
+            file: self.file.clone(),
+            line: Some(self.line),
+            statement: Some(self.statement.clone()),
+            token: self.token.clone(),
+        };
+        write!(fmt, "Skipped statement: {}{}", self.reason, location)
     }
 }
 
@@ -133,4 +338,12 @@ macro_rules! make_error {
             $message,
         )))
     };
+
+    ($kind:ident, $message:expr, $token:expr) => {
+        return Err($crate::error::ObjError::from($crate::error::LoadError::new(
+            $crate::error::LoadErrorKind::$kind,
+            $message,
+        ))
+        .with_token($token))
+    };
 }
@@ -0,0 +1,145 @@
+//! Picks the index type of a model by its number of vertices
+
+use std::convert::TryFrom;
+use std::io::BufRead;
+
+use crate::error::ObjResult;
+use crate::raw::{self, RawObj};
+use crate::{FromRawVertex, LoadOptions, Obj, Vertex};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Load a wavefront OBJ file with the given options, with `u16` indices if the model has at
+/// most 65,536 vertices and every index fits in `u16`, and `u32` indices otherwise.
+///
+/// ```rust
+/// use obj::{load_obj_auto, AutoObj, Indices, LoadOptions, Position};
+///
+/// let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+/// let model: AutoObj<Position> = load_obj_auto(&input[..], &LoadOptions::new())?;
+///
+/// assert_eq!(model.vertices().len(), 3);
+/// assert_eq!(model.indices(), Indices::U16(&[0, 1, 2]));
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn load_obj_auto<V: FromRawVertex<u32>, T: BufRead>(
+    input: T,
+    options: &LoadOptions,
+) -> ObjResult<AutoObj<V>> {
+    let raw = raw::parse_obj_with(input, &options.parse)?;
+    AutoObj::with_options(raw, options)
+}
+
+/// 3D model object loaded from wavefront OBJ, whose index type is picked by its number of
+/// vertices.
+///
+/// Small models use `u16` indices, which take half the memory. Models whose vertices can't all be
+/// referred to with `u16` indices use `u32` indices.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AutoObj<V = Vertex> {
+    /// The model has at most 65,536 vertices, and every index fits in `u16`.
+    U16(Obj<V, u16>),
+    /// The model has more than 65,536 vertices, or an index which doesn't fit in `u16`.
+    U32(Obj<V, u32>),
+}
+
+impl<V: FromRawVertex<u32>> AutoObj<V> {
+    /// Create `AutoObj` from `RawObj` object.
+    pub fn new(raw: RawObj) -> ObjResult<Self> {
+        Self::with_options(raw, &LoadOptions::default())
+    }
+
+    /// Create `AutoObj` from `RawObj` object, with the given options.
+    ///
+    /// The parse options in `options` are ignored, since `raw` has already been parsed.
+    pub fn with_options(raw: RawObj, options: &LoadOptions) -> ObjResult<Self> {
+        let obj: Obj<V, u32> = Obj::with_options(raw, options)?;
+        if obj.vertices.len() > usize::from(u16::MAX) + 1 {
+            return Ok(AutoObj::U32(obj));
+        }
+
+        // Custom vertex types may produce indices which don't fit, even for a small model
+        let indices: Option<Vec<u16>> =
+            obj.indices.iter().map(|&i| u16::try_from(i).ok()).collect();
+        match indices {
+            Some(indices) => Ok(AutoObj::U16(Obj {
+                name: obj.name,
+                vertices: obj.vertices,
+                indices,
+            })),
+            None => Ok(AutoObj::U32(obj)),
+        }
+    }
+}
+
+impl<V> AutoObj<V> {
+    /// Object's name.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            AutoObj::U16(obj) => obj.name.as_deref(),
+            AutoObj::U32(obj) => obj.name.as_deref(),
+        }
+    }
+
+    /// Vertex buffer.
+    pub fn vertices(&self) -> &[V] {
+        match self {
+            AutoObj::U16(obj) => &obj.vertices,
+            AutoObj::U32(obj) => &obj.vertices,
+        }
+    }
+
+    /// Index buffer.
+    pub fn indices(&self) -> Indices<'_> {
+        match self {
+            AutoObj::U16(obj) => Indices::U16(&obj.indices),
+            AutoObj::U32(obj) => Indices::U32(&obj.indices),
+        }
+    }
+}
+
+/// Index buffer of `AutoObj`, or a part of it.
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum Indices<'a> {
+    /// Indices which all fit in `u16`.
+    U16(&'a [u16]),
+    /// Indices of a model with more than 65,536 vertices, or an index which doesn't fit in `u16`.
+    U32(&'a [u32]),
+}
+
+impl<'a> Indices<'a> {
+    /// Returns the number of indices.
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    /// Returns `true` if there's no index.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the `i`-th index, or `None` if it's out of bounds.
+    pub fn get(&self, i: usize) -> Option<u32> {
+        match self {
+            Indices::U16(indices) => indices.get(i).map(|&index| u32::from(index)),
+            Indices::U32(indices) => indices.get(i).copied(),
+        }
+    }
+
+    /// Iterates over the indices, widened to `u32`.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + 'a {
+        let (narrow, wide): (&[u16], &[u32]) = match *self {
+            Indices::U16(indices) => (indices, &[]),
+            Indices::U32(indices) => (&[], indices),
+        };
+        narrow
+            .iter()
+            .map(|&index| u32::from(index))
+            .chain(wide.iter().copied())
+    }
+}
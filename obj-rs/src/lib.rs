@@ -27,14 +27,25 @@ dome.indices;
 
 #[macro_use]
 mod error;
+mod index;
+mod loader;
+mod mesh;
 pub mod raw;
 
-pub use crate::error::{LoadError, LoadErrorKind, ObjError, ObjResult};
-
-use crate::raw::object::Polygon;
-use num_traits::FromPrimitive;
+pub use crate::error::{LoadError, LoadErrorKind, Location, ObjError, ObjResult, Warning};
+pub use crate::index::{load_obj_auto, AutoObj, Indices};
+pub use crate::loader::{
+    load_obj_with_materials, load_obj_with_materials_from, FileProvider, Filesystem, MaterialObj,
+};
+pub use crate::mesh::{load_meshed_obj, MeshedObj, SubMesh};
+pub use crate::raw::freeform::{CurveTechnique, SurfaceTechnique};
+pub use crate::raw::{NormalGeneration, ParseOptions, Tessellation, Triangulation};
+
+use crate::raw::object::{Group, Polygon, Range};
+use indexmap::IndexMap;
+use num_traits::{FromPrimitive, ToPrimitive};
 use std::collections::hash_map::{Entry, HashMap};
-use std::io::BufRead;
+use std::io::{BufRead, Write};
 
 #[cfg(feature = "glium")]
 use glium::implement_vertex;
@@ -51,6 +62,137 @@ pub fn load_obj<V: FromRawVertex<I>, T: BufRead, I>(input: T) -> ObjResult<Obj<V
     Obj::new(raw)
 }
 
+/// Load a wavefront OBJ file into Rust & OpenGL friendly format, with the given options.
+///
+/// ```rust
+/// use std::fs::File;
+/// use std::io::BufReader;
+/// use obj::{load_obj_with, LoadOptions, Obj, Position, Triangulation};
+///
+/// // cube.obj consists of quads
+/// let input = BufReader::new(File::open("tests/fixtures/cube.obj")?);
+/// let options = LoadOptions::new().triangulate(Triangulation::Fan);
+/// let cube: Obj<Position> = load_obj_with(input, &options)?;
+///
+/// assert_eq!(cube.indices.len(), 6 * 2 * 3);
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn load_obj_with<V: FromRawVertex<I>, T: BufRead, I>(
+    input: T,
+    options: &LoadOptions,
+) -> ObjResult<Obj<V, I>> {
+    let raw = raw::parse_obj_with(input, &options.parse)?;
+    Obj::with_options(raw, options)
+}
+
+/// Load a wavefront OBJ file with the given options, as a separate model for each object started
+/// with an `o` statement.
+///
+/// Elements which don't belong to any object are not loaded.
+///
+/// ```rust
+/// use obj::{load_objects, LoadOptions, Obj, Position};
+///
+/// let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\no left\nf 1 2 3\no right\nf 2 4 3\n";
+/// let objects = load_objects::<Position, _, u16>(&input[..], &LoadOptions::new())?;
+///
+/// assert_eq!(objects.len(), 2);
+/// assert_eq!(objects["right"].name.as_deref(), Some("right"));
+/// assert_eq!(objects["right"].vertices.len(), 3);
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn load_objects<V: FromRawVertex<I>, T: BufRead, I>(
+    input: T,
+    options: &LoadOptions,
+) -> ObjResult<IndexMap<String, Obj<V, I>>> {
+    let raw = raw::parse_obj_with(input, &options.parse)?;
+    raw.objects
+        .keys()
+        .filter_map(|name| Some((name.clone(), raw.object(name)?)))
+        .map(|(name, object)| Ok((name, Obj::with_options(object, options)?)))
+        .collect()
+}
+
+/// Load a wavefront OBJ file from an asynchronous reader into Rust & OpenGL friendly format.
+#[cfg(feature = "tokio")]
+pub async fn load_obj_async<V, T, I>(input: T) -> ObjResult<Obj<V, I>>
+where
+    V: FromRawVertex<I>,
+    T: tokio::io::AsyncBufRead + Unpin,
+{
+    let raw = raw::parse_obj_async(input).await?;
+    Obj::new(raw)
+}
+
+/// Load a wavefront OBJ file from an asynchronous reader into Rust & OpenGL friendly format, with
+/// the given options.
+#[cfg(feature = "tokio")]
+pub async fn load_obj_async_with<V, T, I>(input: T, options: &LoadOptions) -> ObjResult<Obj<V, I>>
+where
+    V: FromRawVertex<I>,
+    T: tokio::io::AsyncBufRead + Unpin,
+{
+    let raw = raw::parse_obj_async_with(input, &options.parse).await?;
+    Obj::with_options(raw, options)
+}
+
+/// Options which control how `load_obj_with` and `Obj::with_options` load a model.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    parse: ParseOptions,
+    tessellation: Option<Tessellation>,
+    triangulation: Option<Triangulation>,
+    normals: Option<NormalGeneration>,
+}
+
+impl LoadOptions {
+    /// Creates options which load a model the same way as `load_obj` does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the options used to parse the OBJ file.
+    pub fn parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse = options;
+        self
+    }
+
+    /// Approximates B-spline and NURBS surfaces with triangles with the given method, instead of
+    /// ignoring them.
+    pub fn tessellate(mut self, method: Tessellation) -> Self {
+        self.tessellation = Some(method);
+        self
+    }
+
+    /// Splits polygons with more than 3 vertices into triangles with the given method, instead
+    /// of failing with `UntriangulatedModel`.
+    pub fn triangulate(mut self, method: Triangulation) -> Self {
+        self.triangulation = Some(method);
+        self
+    }
+
+    /// Generates normal vectors of polygons which don't have them with the given method, instead
+    /// of failing with `InsufficientData` when loading `Vertex` or `TexturedVertex`.
+    pub fn generate_normals(mut self, method: NormalGeneration) -> Self {
+        self.normals = Some(method);
+        self
+    }
+
+    /// Tessellates, triangulates and generates normal vectors of `raw` as requested.
+    pub(crate) fn transform(&self, raw: &mut raw::RawObj) -> ObjResult<()> {
+        if let Some(method) = self.tessellation {
+            raw.tessellate(method)?;
+        }
+        if let Some(method) = self.triangulation {
+            raw.triangulate(method);
+        }
+        if let Some(method) = self.normals {
+            raw.generate_normals(method);
+        }
+        Ok(())
+    }
+}
+
 /// 3D model object loaded from wavefront OBJ.
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -66,8 +208,26 @@ pub struct Obj<V = Vertex, I = u16> {
 impl<V: FromRawVertex<I>, I> Obj<V, I> {
     /// Create `Obj` from `RawObj` object.
     pub fn new(raw: raw::RawObj) -> ObjResult<Self> {
-        let (vertices, indices) =
-            FromRawVertex::process(raw.positions, raw.normals, raw.tex_coords, raw.polygons)?;
+        Self::with_options(raw, &LoadOptions::default())
+    }
+
+    /// Create `Obj` from `RawObj` object, with the given options.
+    ///
+    /// The parse options in `options` are ignored, since `raw` has already been parsed.
+    pub fn with_options(mut raw: raw::RawObj, options: &LoadOptions) -> ObjResult<Self> {
+        options.transform(&mut raw)?;
+        Self::from_transformed(raw)
+    }
+
+    /// Builds the vertex and index buffers of `raw`, whose options have already been applied.
+    pub(crate) fn from_transformed(raw: raw::RawObj) -> ObjResult<Self> {
+        let (vertices, indices) = V::process_with_colors(
+            raw.positions,
+            raw.colors,
+            raw.normals,
+            raw.tex_coords,
+            raw.polygons,
+        )?;
 
         Ok(Obj {
             name: raw.name,
@@ -77,6 +237,177 @@ impl<V: FromRawVertex<I>, I> Obj<V, I> {
     }
 }
 
+impl<V: ToRawVertex, I: ToPrimitive> Obj<V, I> {
+    /// Converts the model back into a `RawObj` made of triangles.
+    ///
+    /// Equal positions, normal vectors and texture coordinates of the vertices are stored only
+    /// once. Positions with different colors are stored separately.
+    pub fn to_raw(&self) -> ObjResult<raw::RawObj> {
+        let triangles = self.indices.chunks_exact(3);
+        if !triangles.remainder().is_empty() {
+            make_error!(
+                UntriangulatedModel,
+                "Index buffer should consist of triangles"
+            );
+        }
+
+        // Positions are stored together with their colors
+        let mut positions = Pool::<6>::default();
+        let mut normals = Pool::<3>::default();
+        let mut tex_coords = Pool::<3>::default();
+        let colored = self.vertices.first().and_then(V::color).is_some();
+
+        let mut polygons = Vec::with_capacity(self.indices.len() / 3);
+        for triangle in triangles {
+            let mut corners = Vec::with_capacity(3);
+            for index in triangle {
+                let vertex = match index.to_usize().and_then(|i| self.vertices.get(i)) {
+                    Some(vertex) => vertex,
+                    None => make_error!(IndexOutOfRange, "Index doesn't point to any vertex"),
+                };
+                let [x, y, z] = vertex.position();
+                let [r, g, b] = vertex.color().unwrap_or([1.0; 3]);
+                corners.push((
+                    positions.index([x, y, z, r, g, b]),
+                    vertex.tex_coord().map(|t| tex_coords.index(t)),
+                    vertex.normal().map(|n| normals.index(n)),
+                ));
+            }
+
+            polygons.push(match corners[0] {
+                (_, None, None) => Polygon::P(corners.iter().map(|c| c.0).collect()),
+                (_, Some(_), None) => {
+                    Polygon::PT(corners.iter().map(|c| (c.0, c.1.unwrap())).collect())
+                }
+                (_, None, Some(_)) => {
+                    Polygon::PN(corners.iter().map(|c| (c.0, c.2.unwrap())).collect())
+                }
+                (_, Some(_), Some(_)) => Polygon::PTN(
+                    corners
+                        .iter()
+                        .map(|c| (c.0, c.1.unwrap(), c.2.unwrap()))
+                        .collect(),
+                ),
+            });
+        }
+
+        let group = |ranges: Vec<Range>| Group {
+            points: Vec::new(),
+            lines: Vec::new(),
+            polygons: ranges,
+        };
+        let whole = Range {
+            start: 0,
+            end: polygons.len(),
+        };
+
+        // Every polygon belongs to the default group, as if there was no `g` statement
+        let mut groups = IndexMap::new();
+        if !polygons.is_empty() {
+            groups.insert(String::from("default"), group(vec![whole]));
+        }
+
+        // Every polygon is drawn without material
+        let mut meshes = IndexMap::new();
+        if !polygons.is_empty() {
+            meshes.insert(String::new(), group(vec![whole]));
+        }
+
+        // The model is a single object
+        let mut objects = IndexMap::new();
+        if let Some(name) = &self.name {
+            if !polygons.is_empty() {
+                objects.insert(name.clone(), group(vec![whole]));
+            }
+        }
+
+        Ok(raw::RawObj {
+            name: self.name.clone(),
+            material_libraries: Vec::new(),
+
+            positions: positions
+                .items
+                .iter()
+                .map(|p| (p[0], p[1], p[2], 1.0))
+                .collect(),
+            colors: if colored {
+                positions.items.iter().map(|p| (p[3], p[4], p[5])).collect()
+            } else {
+                Vec::new()
+            },
+            tex_coords: tex_coords
+                .items
+                .into_iter()
+                .map(|t| (t[0], t[1], t[2]))
+                .collect(),
+            normals: normals
+                .items
+                .into_iter()
+                .map(|n| (n[0], n[1], n[2]))
+                .collect(),
+            param_vertices: Vec::new(),
+
+            points: Vec::new(),
+            lines: Vec::new(),
+            polygons,
+
+            groups,
+            meshes,
+            smoothing_groups: IndexMap::new(),
+            merging_groups: IndexMap::new(),
+            objects,
+
+            free_forms: Vec::new(),
+            connections: Vec::new(),
+
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Writes the model in wavefront `.obj` format.
+    ///
+    /// ```rust
+    /// use obj::{Obj, Position};
+    ///
+    /// let triangle = Obj {
+    ///     name: None,
+    ///     vertices: vec![
+    ///         Position { position: [0.0, 0.0, 0.0] },
+    ///         Position { position: [1.0, 0.0, 0.0] },
+    ///         Position { position: [0.0, 1.0, 0.0] },
+    ///     ],
+    ///     indices: vec![0u16, 1, 2],
+    /// };
+    ///
+    /// let mut output = Vec::new();
+    /// triangle.write_obj(&mut output)?;
+    /// assert_eq!(output, b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+    /// # Ok::<(), obj::ObjError>(())
+    /// ```
+    pub fn write_obj<W: Write>(&self, output: W) -> ObjResult<()> {
+        raw::write_obj(&self.to_raw()?, output)?;
+        Ok(())
+    }
+}
+
+/// Vectors which are stored only once, by their bit patterns.
+#[derive(Default)]
+struct Pool<const N: usize> {
+    items: Vec<[f32; N]>,
+    indices: HashMap<[u32; N], usize>,
+}
+
+impl<const N: usize> Pool<N> {
+    /// Returns the index of the vector, adding it to the pool if it's not there yet.
+    fn index(&mut self, v: [f32; N]) -> usize {
+        let items = &mut self.items;
+        *self.indices.entry(v.map(f32::to_bits)).or_insert_with(|| {
+            items.push(v);
+            items.len() - 1
+        })
+    }
+}
+
 /// Conversion from `RawObj`'s raw data.
 pub trait FromRawVertex<I>: Sized {
     /// Build vertex and index buffer from raw object data.
@@ -86,6 +417,63 @@ pub trait FromRawVertex<I>: Sized {
         tex_coords: Vec<(f32, f32, f32)>,
         polygons: Vec<Polygon>,
     ) -> ObjResult<(Vec<Self>, Vec<I>)>;
+
+    /// Build vertex and index buffer from raw object data, including the colors of the positions.
+    ///
+    /// `colors` is empty if the model doesn't have vertex colors. By default, the colors are
+    /// ignored and `process` is called.
+    fn process_with_colors(
+        vertices: Vec<(f32, f32, f32, f32)>,
+        colors: Vec<(f32, f32, f32)>,
+        normals: Vec<(f32, f32, f32)>,
+        tex_coords: Vec<(f32, f32, f32)>,
+        polygons: Vec<Polygon>,
+    ) -> ObjResult<(Vec<Self>, Vec<I>)> {
+        let _ = colors;
+        Self::process(vertices, normals, tex_coords, polygons)
+    }
+
+    /// Number of indices which `process` emits for the given polygon.
+    ///
+    /// It is used to find the sub-meshes of `MeshedObj` in the index buffer. By default, one index
+    /// is emitted for each vertex of the polygon.
+    fn index_count(polygon: &Polygon) -> usize {
+        match polygon {
+            Polygon::P(vec) => vec.len(),
+            Polygon::PT(vec) | Polygon::PN(vec) => vec.len(),
+            Polygon::PTN(vec) => vec.len(),
+        }
+    }
+}
+
+/// Converts an index of the vertex buffer into the index type, failing with `IndexOverflow` if it
+/// doesn't fit, e.g. if a model with more than 65,536 vertices is loaded with `u16` indices.
+fn to_index<I: FromPrimitive>(index: usize) -> ObjResult<I> {
+    match I::from_usize(index) {
+        Some(index) => Ok(index),
+        None => make_error!(IndexOverflow, "Too many vertices for the index type"),
+    }
+}
+
+/// Conversion into `RawObj`'s raw data, the reverse of `FromRawVertex`.
+pub trait ToRawVertex {
+    /// Position vector of the vertex.
+    fn position(&self) -> [f32; 3];
+
+    /// Normal vector of the vertex, or `None` if the vertex type doesn't have one.
+    fn normal(&self) -> Option<[f32; 3]> {
+        None
+    }
+
+    /// Texture coordinates of the vertex, or `None` if the vertex type doesn't have them.
+    fn tex_coord(&self) -> Option<[f32; 3]> {
+        None
+    }
+
+    /// Color of the vertex, or `None` if the vertex type doesn't have one.
+    fn color(&self) -> Option<[f32; 3]> {
+        None
+    }
 }
 
 /// Vertex data type of `Obj` which contains position and normal data of a vertex.
@@ -104,6 +492,16 @@ implement_vertex!(Vertex, position, normal);
 #[cfg(feature = "vulkano")]
 impl_vertex!(Vertex, position, normal);
 
+impl ToRawVertex for Vertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn normal(&self) -> Option<[f32; 3]> {
+        Some(self.normal)
+    }
+}
+
 impl<I: FromPrimitive + Copy> FromRawVertex<I> for Vertex {
     fn process(
         positions: Vec<(f32, f32, f32, f32)>,
@@ -115,7 +513,7 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for Vertex {
         let mut ib = Vec::with_capacity(polygons.len() * 3);
         {
             let mut cache = HashMap::new();
-            let mut map = |pi: usize, ni: usize| {
+            let mut map = |pi: usize, ni: usize| -> ObjResult<()> {
                 // Look up cache
                 let index = match cache.entry((pi, ni)) {
                     // Cache miss -> make new, store it on cache
@@ -126,8 +524,7 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for Vertex {
                             position: [p.0, p.1, p.2],
                             normal: [n.0, n.1, n.2],
                         };
-                        let index = I::from_usize(vb.len())
-                            .expect("Unable to convert the index from usize");
+                        let index = to_index(vb.len())?;
                         vb.push(vertex);
                         entry.insert(index);
                         index
@@ -135,7 +532,8 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for Vertex {
                     // Cache hit -> use it
                     Entry::Occupied(entry) => *entry.get(),
                 };
-                ib.push(index)
+                ib.push(index);
+                Ok(())
             };
 
             for polygon in polygons {
@@ -146,12 +544,12 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for Vertex {
                     ),
                     Polygon::PN(ref vec) if vec.len() == 3 => {
                         for &(pi, ni) in vec {
-                            map(pi, ni)
+                            map(pi, ni)?
                         }
                     }
                     Polygon::PTN(ref vec) if vec.len() == 3 => {
                         for &(pi, _, ni) in vec {
-                            map(pi, ni)
+                            map(pi, ni)?
                         }
                     }
                     _ => make_error!(
@@ -180,6 +578,12 @@ implement_vertex!(Position, position);
 #[cfg(feature = "vulkano")]
 impl_vertex!(Position, position);
 
+impl ToRawVertex for Position {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+}
+
 impl<I: FromPrimitive> FromRawVertex<I> for Position {
     fn process(
         vertices: Vec<(f32, f32, f32, f32)>,
@@ -195,25 +599,26 @@ impl<I: FromPrimitive> FromRawVertex<I> for Position {
             .collect();
         let mut ib = Vec::with_capacity(polygons.len() * 3);
         {
-            let mut map = |pi: usize| {
-                ib.push(I::from_usize(pi).expect("Unable to convert the index from usize"))
+            let mut map = |pi: usize| -> ObjResult<()> {
+                ib.push(to_index(pi)?);
+                Ok(())
             };
 
             for polygon in polygons {
                 match polygon {
                     Polygon::P(ref vec) if vec.len() == 3 => {
                         for &pi in vec {
-                            map(pi)
+                            map(pi)?
                         }
                     }
                     Polygon::PT(ref vec) | Polygon::PN(ref vec) if vec.len() == 3 => {
                         for &(pi, _) in vec {
-                            map(pi)
+                            map(pi)?
                         }
                     }
                     Polygon::PTN(ref vec) if vec.len() == 3 => {
                         for &(pi, _, _) in vec {
-                            map(pi)
+                            map(pi)?
                         }
                     }
                     _ => make_error!(
@@ -245,6 +650,20 @@ implement_vertex!(TexturedVertex, position, normal, texture);
 #[cfg(feature = "vulkano")]
 impl_vertex!(TexturedVertex, position, normal, texture);
 
+impl ToRawVertex for TexturedVertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn normal(&self) -> Option<[f32; 3]> {
+        Some(self.normal)
+    }
+
+    fn tex_coord(&self) -> Option<[f32; 3]> {
+        Some(self.texture)
+    }
+}
+
 impl<I: FromPrimitive + Copy> FromRawVertex<I> for TexturedVertex {
     fn process(
         positions: Vec<(f32, f32, f32, f32)>,
@@ -256,7 +675,7 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for TexturedVertex {
         let mut ib = Vec::with_capacity(polygons.len() * 3);
         {
             let mut cache = HashMap::new();
-            let mut map = |pi: usize, ni: usize, ti: usize| {
+            let mut map = |pi: usize, ni: usize, ti: usize| -> ObjResult<()> {
                 // Look up cache
                 let index = match cache.entry((pi, ni, ti)) {
                     // Cache miss -> make new, store it on cache
@@ -269,8 +688,7 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for TexturedVertex {
                             normal: [n.0, n.1, n.2],
                             texture: [t.0, t.1, t.2],
                         };
-                        let index = I::from_usize(vb.len())
-                            .expect("Unable to convert the index from usize");
+                        let index = to_index(vb.len())?;
                         vb.push(vertex);
                         entry.insert(index);
                         index
@@ -278,7 +696,8 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for TexturedVertex {
                     // Cache hit -> use it
                     Entry::Occupied(entry) => *entry.get(),
                 };
-                ib.push(index)
+                ib.push(index);
+                Ok(())
             };
 
             for polygon in polygons {
@@ -287,7 +706,7 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for TexturedVertex {
                     Polygon::PT(_) => make_error!(InsufficientData, "Tried to extract normal data which are not contained in the model"),
                     Polygon::PN(_) => make_error!(InsufficientData, "Tried to extract texture data which are not contained in the model"),
                     Polygon::PTN(ref vec) if vec.len() == 3 => {
-                        for &(pi, ti, ni) in vec { map(pi, ni, ti) }
+                        for &(pi, ti, ni) in vec { map(pi, ni, ti)? }
                     }
                     _ => make_error!(UntriangulatedModel, "Model should be triangulated first to be loaded properly")
                 }
@@ -298,6 +717,122 @@ impl<I: FromPrimitive + Copy> FromRawVertex<I> for TexturedVertex {
     }
 }
 
+/// Vertex data type of `Obj` which contains position, normal and color data of a vertex.
+///
+/// Colors are read from `v x y z r g b` statements, which many scanners and tools like MeshLab
+/// write. Positions without color in a model with vertex colors are white.
+#[derive(Copy, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "vulkano", derive(Default))]
+pub struct ColoredVertex {
+    /// Position vector of a vertex.
+    pub position: [f32; 3],
+    /// Normal vertor of a vertex.
+    pub normal: [f32; 3],
+    /// Color of a vertex.
+    pub color: [f32; 3],
+}
+
+#[cfg(feature = "glium")]
+implement_vertex!(ColoredVertex, position, normal, color);
+#[cfg(feature = "vulkano")]
+impl_vertex!(ColoredVertex, position, normal, color);
+
+impl ToRawVertex for ColoredVertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn normal(&self) -> Option<[f32; 3]> {
+        Some(self.normal)
+    }
+
+    fn color(&self) -> Option<[f32; 3]> {
+        Some(self.color)
+    }
+}
+
+impl<I: FromPrimitive + Copy> FromRawVertex<I> for ColoredVertex {
+    fn process(
+        positions: Vec<(f32, f32, f32, f32)>,
+        normals: Vec<(f32, f32, f32)>,
+        tex_coords: Vec<(f32, f32, f32)>,
+        polygons: Vec<Polygon>,
+    ) -> ObjResult<(Vec<Self>, Vec<I>)> {
+        Self::process_with_colors(positions, Vec::new(), normals, tex_coords, polygons)
+    }
+
+    fn process_with_colors(
+        positions: Vec<(f32, f32, f32, f32)>,
+        colors: Vec<(f32, f32, f32)>,
+        normals: Vec<(f32, f32, f32)>,
+        _: Vec<(f32, f32, f32)>,
+        polygons: Vec<Polygon>,
+    ) -> ObjResult<(Vec<Self>, Vec<I>)> {
+        if colors.is_empty() && !polygons.is_empty() {
+            make_error!(
+                InsufficientData,
+                "Tried to extract color data which are not contained in the model"
+            );
+        }
+
+        let mut vb = Vec::with_capacity(polygons.len() * 3);
+        let mut ib = Vec::with_capacity(polygons.len() * 3);
+        {
+            let mut cache = HashMap::new();
+            let mut map = |pi: usize, ni: usize| -> ObjResult<()> {
+                // Look up cache
+                let index = match cache.entry((pi, ni)) {
+                    // Cache miss -> make new, store it on cache
+                    Entry::Vacant(entry) => {
+                        let p = positions[pi];
+                        let n = normals[ni];
+                        let c = colors[pi];
+                        let vertex = ColoredVertex {
+                            position: [p.0, p.1, p.2],
+                            normal: [n.0, n.1, n.2],
+                            color: [c.0, c.1, c.2],
+                        };
+                        let index = to_index(vb.len())?;
+                        vb.push(vertex);
+                        entry.insert(index);
+                        index
+                    }
+                    // Cache hit -> use it
+                    Entry::Occupied(entry) => *entry.get(),
+                };
+                ib.push(index);
+                Ok(())
+            };
+
+            for polygon in polygons {
+                match polygon {
+                    Polygon::P(_) | Polygon::PT(_) => make_error!(
+                        InsufficientData,
+                        "Tried to extract normal data which are not contained in the model"
+                    ),
+                    Polygon::PN(ref vec) if vec.len() == 3 => {
+                        for &(pi, ni) in vec {
+                            map(pi, ni)?
+                        }
+                    }
+                    Polygon::PTN(ref vec) if vec.len() == 3 => {
+                        for &(pi, _, ni) in vec {
+                            map(pi, ni)?
+                        }
+                    }
+                    _ => make_error!(
+                        UntriangulatedModel,
+                        "Model should be triangulated first to be loaded properly"
+                    ),
+                }
+            }
+        }
+        vb.shrink_to_fit();
+        Ok((vb, ib))
+    }
+}
+
 #[cfg(feature = "glium")]
 mod glium_support {
     use super::Obj;
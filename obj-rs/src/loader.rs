@@ -0,0 +1,184 @@
+//! Loads OBJ files together with the MTL files which they refer to
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::error::{ObjError, ObjResult, Warning};
+use crate::raw::material::{parse_mtl_with, Material, RawMtl};
+use crate::raw::object::parse_obj_with;
+use crate::{FromRawVertex, LoadOptions, MeshedObj, Obj, SubMesh, Vertex};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Source of OBJ files and the MTL files which they refer to.
+///
+/// It is implemented for `Filesystem`, and for closures which open a path, so files can be served
+/// from archives or memory as well.
+///
+/// ```rust
+/// use std::collections::HashMap;
+/// use std::io::{self, Cursor};
+/// use std::path::Path;
+/// use obj::{load_obj_with_materials_from, LoadOptions, MaterialObj, Position};
+///
+/// let mut files = HashMap::new();
+/// files.insert("model/box.obj", "mtllib box.mtl\nv 0 0 0\nusemtl red\np 1");
+/// files.insert("model/box.mtl", "newmtl red\nKd 1 0 0");
+///
+/// let mut provider = |path: &Path| match files.get(path.to_str().unwrap()) {
+///     Some(content) => Ok(Cursor::new(content.as_bytes())),
+///     None => Err(io::Error::from(io::ErrorKind::NotFound)),
+/// };
+/// let model: MaterialObj<Position> =
+///     load_obj_with_materials_from(&mut provider, "model/box.obj", &LoadOptions::new())?;
+///
+/// assert!(model.library.materials.contains_key("red"));
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub trait FileProvider {
+    /// Reader of an opened file.
+    type Reader: BufRead;
+
+    /// Opens the file at the given path.
+    fn open(&mut self, path: &Path) -> io::Result<Self::Reader>;
+}
+
+/// `FileProvider` which opens files from the filesystem.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Filesystem;
+
+impl FileProvider for Filesystem {
+    type Reader = BufReader<File>;
+
+    fn open(&mut self, path: &Path) -> io::Result<Self::Reader> {
+        Ok(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<F, R> FileProvider for F
+where
+    F: FnMut(&Path) -> io::Result<R>,
+    R: BufRead,
+{
+    type Reader = R;
+
+    fn open(&mut self, path: &Path) -> io::Result<Self::Reader> {
+        self(path)
+    }
+}
+
+/// 3D model object loaded from wavefront OBJ, together with the materials which it uses.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MaterialObj<V = Vertex, I = u16> {
+    /// The model.
+    pub obj: Obj<V, I>,
+    /// Parts of the model which use different materials, as in `MeshedObj::sub_meshes`.
+    pub sub_meshes: Vec<SubMesh>,
+    /// Materials of every `.mtl` file which the model refers to with `mtllib`, merged into one.
+    ///
+    /// If more than one file defines a material with the same name, the one from the file listed
+    /// first is used.
+    pub library: RawMtl,
+}
+
+impl<V, I> MaterialObj<V, I> {
+    /// Returns the material of the given sub-mesh, or `None` if the sub-mesh doesn't have a
+    /// material or no library defines it.
+    pub fn material(&self, sub_mesh: &SubMesh) -> Option<&Material> {
+        let name = sub_mesh.material.as_ref()?;
+        self.library.materials.get(name)
+    }
+
+    /// Iterates over the sub-meshes of the model, together with their materials.
+    pub fn sub_meshes(&self) -> impl Iterator<Item = (&SubMesh, Option<&Material>)> {
+        self.sub_meshes
+            .iter()
+            .map(move |sub_mesh| (sub_mesh, self.material(sub_mesh)))
+    }
+}
+
+/// Load a wavefront OBJ file from the filesystem with the given options, together with the `.mtl`
+/// files which it refers to.
+///
+/// Paths in `mtllib` statements are resolved relative to the directory of the OBJ file.
+///
+/// ```rust
+/// use obj::{load_obj_with_materials, LoadOptions, MaterialObj, Position, Triangulation};
+///
+/// let options = LoadOptions::new().triangulate(Triangulation::Fan);
+/// let cube: MaterialObj<Position> =
+///     load_obj_with_materials("tests/fixtures/cube.obj", &options)?;
+///
+/// for (sub_mesh, material) in cube.sub_meshes() {
+///     let indices = cube.obj.sub_mesh_indices(sub_mesh);
+///     let diffuse = material.and_then(|m| m.diffuse.as_ref());
+///     // Draw `indices` with the `diffuse` color
+/// }
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn load_obj_with_materials<V, I, P>(
+    path: P,
+    options: &LoadOptions,
+) -> ObjResult<MaterialObj<V, I>>
+where
+    V: FromRawVertex<I>,
+    P: AsRef<Path>,
+{
+    load_obj_with_materials_from(&mut Filesystem, path, options)
+}
+
+/// Load a wavefront OBJ file from the given `FileProvider`, together with the `.mtl` files which
+/// it refers to.
+///
+/// Paths in `mtllib` statements are resolved relative to the directory of the OBJ file. In
+/// lenient mode, warnings of every `.mtl` file are collected in `MaterialObj::library`, with the
+/// path of their file. Errors of the OBJ file and of every `.mtl` file are reported with the path
+/// of their file as well.
+pub fn load_obj_with_materials_from<F, V, I, P>(
+    provider: &mut F,
+    path: P,
+    options: &LoadOptions,
+) -> ObjResult<MaterialObj<V, I>>
+where
+    F: FileProvider,
+    V: FromRawVertex<I>,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let raw = provider
+        .open(path)
+        .map_err(ObjError::from)
+        .and_then(|input| parse_obj_with(input, &options.parse))
+        .map_err(|e| e.in_file(path))?;
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut library = RawMtl::default();
+    for file in &raw.material_libraries {
+        let mtl_path: PathBuf = directory.join(file);
+        let mtl = provider
+            .open(&mtl_path)
+            .map_err(ObjError::from)
+            .and_then(|input| parse_mtl_with(input, &options.parse))
+            .map_err(|e| e.in_file(&mtl_path))?;
+
+        for (name, material) in mtl.materials {
+            library.materials.entry(name).or_insert(material);
+        }
+        library
+            .warnings
+            .extend(mtl.warnings.into_iter().map(|warning| Warning {
+                file: Some(mtl_path.clone()),
+                ..warning
+            }));
+    }
+
+    let MeshedObj { obj, sub_meshes } = MeshedObj::with_options(raw, options)?;
+    Ok(MaterialObj {
+        obj,
+        sub_meshes,
+        library,
+    })
+}
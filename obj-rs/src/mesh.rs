@@ -0,0 +1,186 @@
+//! Splits the index buffer of a model into parts which use different materials
+
+use std::io::{BufRead, Write};
+
+use indexmap::IndexMap;
+use num_traits::ToPrimitive;
+
+use crate::error::ObjResult;
+use crate::raw::object::{Group, Range};
+use crate::raw::{self, RawObj};
+use crate::{FromRawVertex, LoadOptions, Obj, ToRawVertex, Vertex};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Load a wavefront OBJ file with the given options, together with the parts of the model which
+/// use different materials.
+///
+/// ```rust
+/// use obj::{load_meshed_obj, LoadOptions, MeshedObj, Position};
+///
+/// let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 2\n";
+/// let model: MeshedObj<Position> = load_meshed_obj(&input[..], &LoadOptions::new())?;
+///
+/// assert_eq!(model.sub_meshes.len(), 2);
+/// assert_eq!(model.sub_meshes[1].material.as_deref(), Some("blue"));
+/// assert_eq!(model.obj.sub_mesh_indices(&model.sub_meshes[1]), &[0, 2, 1]);
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn load_meshed_obj<V: FromRawVertex<I>, T: BufRead, I>(
+    input: T,
+    options: &LoadOptions,
+) -> ObjResult<MeshedObj<V, I>> {
+    let raw = raw::parse_obj_with(input, &options.parse)?;
+    MeshedObj::with_options(raw, options)
+}
+
+/// 3D model object loaded from wavefront OBJ, together with the parts of it which use different
+/// materials.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MeshedObj<V = Vertex, I = u16> {
+    /// The model.
+    pub obj: Obj<V, I>,
+    /// Parts of the model which use different materials, in the order of the polygons in the OBJ
+    /// file.
+    ///
+    /// Every run of consecutive polygons with the same material becomes a sub-mesh, so a material
+    /// appears more than once if its polygons are interleaved with the ones of other materials.
+    pub sub_meshes: Vec<SubMesh>,
+}
+
+/// A part of `Obj` which is drawn with a single material.
+///
+/// Indices of every sub-mesh are stored contiguously in `Obj::indices`, so each sub-mesh can be
+/// drawn with a single draw call.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SubMesh {
+    /// Name of the material specified by `usemtl`, or `None` for polygons which come before any
+    /// `usemtl` statement.
+    pub material: Option<String>,
+    /// Position of the first index of the sub-mesh in the index buffer (inclusive).
+    pub start: usize,
+    /// Position of the last index of the sub-mesh in the index buffer (exclusive).
+    pub end: usize,
+}
+
+impl<V, I> Obj<V, I> {
+    /// Returns the part of the index buffer which belongs to the given sub-mesh.
+    pub fn sub_mesh_indices(&self, sub_mesh: &SubMesh) -> &[I] {
+        &self.indices[sub_mesh.start..sub_mesh.end]
+    }
+}
+
+impl<V: FromRawVertex<I>, I> MeshedObj<V, I> {
+    /// Create `MeshedObj` from `RawObj` object.
+    pub fn new(raw: RawObj) -> ObjResult<Self> {
+        Self::with_options(raw, &LoadOptions::default())
+    }
+
+    /// Create `MeshedObj` from `RawObj` object, with the given options.
+    ///
+    /// The parse options in `options` are ignored, since `raw` has already been parsed.
+    pub fn with_options(mut raw: RawObj, options: &LoadOptions) -> ObjResult<Self> {
+        options.transform(&mut raw)?;
+
+        let runs = material_runs(&raw);
+        let counts: Vec<_> = raw.polygons.iter().map(V::index_count).collect();
+        let obj = Obj::from_transformed(raw)?;
+
+        // Position of the first index of each polygon, and the end of the index buffer
+        let mut offsets = Vec::with_capacity(counts.len() + 1);
+        offsets.push(0);
+        for count in counts {
+            offsets.push(offsets[offsets.len() - 1] + count);
+        }
+        if offsets[offsets.len() - 1] != obj.indices.len() {
+            make_error!(
+                InsufficientData,
+                "The number of indices doesn't match `FromRawVertex::index_count`"
+            );
+        }
+
+        let sub_meshes = runs
+            .into_iter()
+            .map(|(material, range)| SubMesh {
+                material,
+                start: offsets[range.start],
+                end: offsets[range.end],
+            })
+            .collect();
+
+        Ok(MeshedObj { obj, sub_meshes })
+    }
+}
+
+/// Returns the material of every run of consecutive polygons with the same material, together
+/// with the range of the run.
+fn material_runs(raw: &RawObj) -> Vec<(Option<String>, Range)> {
+    // Polygons which don't belong to any mesh are treated as polygons without material
+    let mut material_of = vec![""; raw.polygons.len()];
+    for (name, mesh) in &raw.meshes {
+        for range in &mesh.polygons {
+            for slot in &mut material_of[range.start..range.end] {
+                *slot = name;
+            }
+        }
+    }
+
+    let mut runs: Vec<(Option<String>, Range)> = Vec::new();
+    for (i, &name) in material_of.iter().enumerate() {
+        let material = if name.is_empty() { None } else { Some(name) };
+        match runs.last_mut() {
+            Some((last, range)) if last.as_deref() == material => range.end = i + 1,
+            _ => runs.push((
+                material.map(String::from),
+                Range {
+                    start: i,
+                    end: i + 1,
+                },
+            )),
+        }
+    }
+    runs
+}
+
+impl<V: ToRawVertex, I: ToPrimitive> MeshedObj<V, I> {
+    /// Converts the model back into a `RawObj` made of triangles.
+    ///
+    /// It is the same as `Obj::to_raw`, except that each sub-mesh becomes a mesh of its material.
+    pub fn to_raw(&self) -> ObjResult<RawObj> {
+        let mut raw = self.obj.to_raw()?;
+
+        let mut meshes = IndexMap::new();
+        for sub_mesh in &self.sub_meshes {
+            if sub_mesh.start == sub_mesh.end {
+                continue;
+            }
+            let name = sub_mesh.material.clone().unwrap_or_default();
+            meshes
+                .entry(name)
+                .or_insert_with(|| Group {
+                    points: Vec::new(),
+                    lines: Vec::new(),
+                    polygons: Vec::new(),
+                })
+                .polygons
+                .push(Range {
+                    start: sub_mesh.start / 3,
+                    end: sub_mesh.end / 3,
+                });
+        }
+        if !meshes.is_empty() {
+            raw.meshes = meshes;
+        }
+
+        Ok(raw)
+    }
+
+    /// Writes the model in wavefront `.obj` format, with a `usemtl` statement for each sub-mesh.
+    pub fn write_obj<W: Write>(&self, output: W) -> ObjResult<()> {
+        raw::write_obj(&self.to_raw()?, output)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Parses `.obj` and `.mtl` formats from asynchronous readers
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::error::ObjResult;
+use crate::raw::chunk::continues;
+use crate::raw::lexer::Lexer;
+use crate::raw::material::{MtlParser, RawMtl};
+use crate::raw::object::{finish_free_forms, parse_chunk, Context, Groups, RawObj, VertexCounts};
+use crate::raw::ParseOptions;
+
+/// Chunks are read until they are at least this big, so the parser isn't called for each line.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Parses a wavefront `.obj` format from an asynchronous reader.
+///
+/// The result is exactly the same as the one of `parse_obj`.
+pub async fn parse_obj_async<T>(input: T) -> ObjResult<RawObj>
+where
+    T: AsyncBufRead + Unpin,
+{
+    parse_obj_async_with(input, &ParseOptions::default()).await
+}
+
+/// Parses a wavefront `.obj` format from an asynchronous reader with the given options.
+///
+/// The input is read in chunks of whole lines, and each chunk is parsed as soon as it has been
+/// read. The result, including errors and warnings, is exactly the same as the one of
+/// `parse_obj_with`.
+///
+/// ```rust
+/// use obj::raw::{parse_obj_async_with, ParseOptions};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// // Any `AsyncBufRead`, e.g. a `tokio::io::BufReader` over a file or a network stream
+/// let input = &b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nvx 1\n"[..];
+/// let raw = parse_obj_async_with(input, &ParseOptions::new().lenient(true)).await?;
+///
+/// assert_eq!(raw.polygons.len(), 1);
+/// assert_eq!(raw.warnings[0].line, 5);
+/// # Ok::<(), obj::ObjError>(())
+/// # }).unwrap();
+/// ```
+pub async fn parse_obj_async_with<T>(input: T, options: &ParseOptions) -> ObjResult<RawObj>
+where
+    T: AsyncBufRead + Unpin,
+{
+    let mut chunks = Chunks::new(input);
+    let mut raw = RawObj::empty();
+    let mut context = Context::default();
+    let mut groups = Groups::new();
+
+    while let Some(lexer) = chunks.next().await? {
+        let chunk = parse_chunk(lexer, options, &context, &mut VertexCounts::default())?;
+        context = chunk.end.clone();
+        raw.append(chunk, &mut groups);
+    }
+    groups.finish(&mut raw);
+    finish_free_forms(&mut raw, &context.free_form, options)?;
+
+    Ok(raw)
+}
+
+/// Parses a wavefront `.mtl` format from an asynchronous reader.
+///
+/// The result is exactly the same as the one of `parse_mtl`.
+pub async fn parse_mtl_async<T>(input: T) -> ObjResult<RawMtl>
+where
+    T: AsyncBufRead + Unpin,
+{
+    parse_mtl_async_with(input, &ParseOptions::default()).await
+}
+
+/// Parses a wavefront `.mtl` format from an asynchronous reader with the given options.
+///
+/// The result, including errors and warnings, is exactly the same as the one of
+/// `parse_mtl_with`.
+pub async fn parse_mtl_async_with<T>(input: T, options: &ParseOptions) -> ObjResult<RawMtl>
+where
+    T: AsyncBufRead + Unpin,
+{
+    let mut chunks = Chunks::new(input);
+    let mut parser = MtlParser::default();
+
+    while let Some(lexer) = chunks.next().await? {
+        parser.parse(lexer, options)?;
+    }
+
+    Ok(parser.finish())
+}
+
+/// Reads the input in chunks which consist of whole logical lines.
+struct Chunks<T> {
+    input: T,
+    buffer: Vec<u8>,
+    /// Number of physical lines read so far.
+    lines: usize,
+}
+
+impl<T: AsyncBufRead + Unpin> Chunks<T> {
+    fn new(input: T) -> Self {
+        Chunks {
+            input,
+            buffer: Vec::new(),
+            lines: 0,
+        }
+    }
+
+    /// Reads the next chunk, and returns the lexer for it. Returns `None` at the end of the input.
+    async fn next(&mut self) -> ObjResult<Option<Lexer<&[u8]>>> {
+        let lines = self.lines;
+        self.buffer.clear();
+
+        loop {
+            let start = self.buffer.len();
+            if self.input.read_until(b'\n', &mut self.buffer).await? == 0 {
+                break;
+            }
+            self.lines += 1;
+
+            let line = &self.buffer[start..];
+            if self.buffer.len() >= CHUNK_SIZE && line.ends_with(b"\n") && !continues(line) {
+                break;
+            }
+        }
+
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Lexer::after_lines(&self.buffer[..], lines)))
+    }
+}
@@ -0,0 +1,94 @@
+//! Helpers for parsing the input in chunks
+
+use indexmap::IndexMap;
+
+use crate::raw::object::{Chunk, Groups, RawObj, DEFAULT_COLOR};
+
+/// Returns whether the last line of the input ends with a backslash, which joins it with the next
+/// line.
+pub fn continues(input: &[u8]) -> bool {
+    let line = &input[..input.len() - 1];
+    let line = match line.iter().rposition(|&b| b == b'\n') {
+        Some(i) => &line[i + 1..],
+        None => line,
+    };
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = match line.iter().position(|&b| b == b'#') {
+        Some(i) => &line[..i],
+        None => line,
+    };
+    line.ends_with(b"\\")
+}
+
+impl RawObj {
+    /// Creates a `RawObj` which doesn't have anything.
+    pub(crate) fn empty() -> Self {
+        RawObj {
+            name: None,
+            material_libraries: Vec::new(),
+
+            positions: Vec::new(),
+            colors: Vec::new(),
+            tex_coords: Vec::new(),
+            normals: Vec::new(),
+            param_vertices: Vec::new(),
+
+            points: Vec::new(),
+            lines: Vec::new(),
+            polygons: Vec::new(),
+
+            groups: IndexMap::new(),
+            meshes: IndexMap::new(),
+            smoothing_groups: IndexMap::new(),
+            merging_groups: IndexMap::new(),
+            objects: IndexMap::new(),
+
+            free_forms: Vec::new(),
+            connections: Vec::new(),
+
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Appends a chunk which has been parsed with the state at the end of `self`, whose group
+    /// statements are replayed on `groups`. The groups are moved into `self` by
+    /// `Groups::finish` after the last chunk.
+    pub(crate) fn append(&mut self, chunk: Chunk, groups: &mut Groups) {
+        let Chunk {
+            raw: chunk,
+            named,
+            statements,
+            ..
+        } = chunk;
+        let offset = (self.points.len(), self.lines.len(), self.polygons.len());
+
+        if named {
+            self.name = chunk.name;
+        }
+        self.material_libraries.extend(chunk.material_libraries);
+
+        if !self.colors.is_empty() || !chunk.colors.is_empty() {
+            let mut colors = chunk.colors;
+            colors.resize(chunk.positions.len(), DEFAULT_COLOR);
+            self.colors.resize(self.positions.len(), DEFAULT_COLOR);
+            self.colors.extend(colors);
+        }
+        self.positions.extend(chunk.positions);
+        self.tex_coords.extend(chunk.tex_coords);
+        self.normals.extend(chunk.normals);
+        self.param_vertices.extend(chunk.param_vertices);
+
+        self.points.extend(chunk.points);
+        self.lines.extend(chunk.lines);
+        self.polygons.extend(chunk.polygons);
+
+        groups.replay(statements, offset);
+
+        // Indices of trimming curves and connected surfaces are already counted from the beginning
+        // of the file
+        self.free_forms.extend(chunk.free_forms);
+        self.connections.extend(chunk.connections);
+
+        self.warnings.extend(chunk.warnings);
+    }
+}
@@ -0,0 +1,274 @@
+//! Evaluates free-form curves of `RawObj`, and approximates them with polylines
+
+use crate::error::ObjResult;
+use crate::raw::freeform::{CurveTechnique, FreeForm, Geometry};
+use crate::raw::object::{Line, Range, RawObj, DEFAULT_COLOR};
+use crate::raw::spline::{self, Spline, MAX_SEGMENTS, MEASURED_SEGMENTS};
+
+/// Method used to approximate free-form curves with polylines.
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum CurveApproximation {
+    /// Uses the `ctech` technique of each curve, or the given one for curves without `ctech`.
+    Ctech(CurveTechnique),
+    /// Uses the given technique for every curve, ignoring `ctech` statements.
+    Override(CurveTechnique),
+}
+
+/// Free-form curve of a `RawObj`, which can be evaluated at any parameter in its range.
+///
+/// Curves of every basis are supported. Points of `curv2` curves are in the parameter space of
+/// surfaces, with 0 as the third coordinate.
+///
+/// ```rust
+/// use obj::raw::freeform::CurveTechnique;
+/// use obj::raw::{parse_obj, Curve};
+///
+/// let input = b"v 0 0 0\nv 1 2 0\nv 2 0 0\ncstype bezier\ndeg 2\ncurv 0 1 1 2 3\nparm u 0 1\nend\n";
+/// let raw = parse_obj(&input[..])?;
+/// let curve = Curve::new(&raw, &raw.free_forms[0])?;
+///
+/// assert_eq!(curve.range(), (0.0, 1.0));
+/// assert_eq!(curve.evaluate(0.5), [1.0, 1.0, 0.0]);
+/// assert_eq!(curve.polyline(CurveTechnique::Parametric(2.0)).len(), 5);
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub struct Curve<'a> {
+    spline: Spline<'a>,
+    /// Control points in homogeneous coordinates.
+    points: Vec<[f32; 4]>,
+    rational: bool,
+    range: (f32, f32),
+}
+
+impl<'a> Curve<'a> {
+    /// Prepares a `curv` or `curv2` curve of `raw` to be evaluated.
+    ///
+    /// The curve is evaluated over the range of its `curv` statement, or over all of its
+    /// parameters if it's a `curv2` curve, with the global parameters of its `parm u` statement.
+    /// Rational curves use `w` of their control points as weights.
+    ///
+    /// Fails with `InsufficientData` if the parameters, the degree or the basis matrix of the
+    /// curve don't match its control points, or if it's a surface.
+    pub fn new(raw: &RawObj, free_form: &'a FreeForm) -> ObjResult<Self> {
+        let (range, control_points) = match &free_form.geometry {
+            Geometry::Curve {
+                start,
+                end,
+                control_points,
+            } => {
+                let points: Option<Vec<_>> = control_points
+                    .iter()
+                    .map(|&i| raw.positions.get(i).copied())
+                    .collect();
+                (Some((*start, *end)), points)
+            }
+            Geometry::Curve2 { control_points } => {
+                let points: Option<Vec<_>> = control_points
+                    .iter()
+                    .map(|&i| raw.param_vertices.get(i).map(|&(u, v, w)| (u, v, 0.0, w)))
+                    .collect();
+                (None, points)
+            }
+            Geometry::Surface { .. } => {
+                make_error!(InsufficientData, "Expected a curve instead of a surface")
+            }
+        };
+        let control_points = match control_points {
+            Some(control_points) => control_points,
+            None => make_error!(IndexOutOfRange, "Control points are out of range"),
+        };
+
+        let spline = Spline::new(free_form, 0, control_points.len())?;
+        if spline.count() != control_points.len() {
+            make_error!(
+                InsufficientData,
+                "The number of control points doesn't match the knots"
+            );
+        }
+
+        let mut points = Vec::with_capacity(control_points.len());
+        for (x, y, z, w) in control_points {
+            let w = if free_form.rational { w } else { 1.0 };
+            if w <= 0.0 {
+                make_error!(InsufficientData, "Expected positive weights");
+            }
+            points.push([x * w, y * w, z * w, w]);
+        }
+
+        let range = spline.clamp(range.unwrap_or_else(|| spline.domain()))?;
+        Ok(Curve {
+            spline,
+            points,
+            rational: free_form.rational,
+            range,
+        })
+    }
+
+    /// Returns the starting and the ending parameter values of the curve.
+    pub fn range(&self) -> (f32, f32) {
+        self.range
+    }
+
+    /// Returns the point of the curve at the given parameter, which is clamped to the range.
+    pub fn evaluate(&self, t: f32) -> [f32; 3] {
+        let t = t.max(self.range.0).min(self.range.1);
+        let (first, values, _) = self.spline.basis(t);
+
+        let mut sum = [0.0f32; 4];
+        for (point, value) in self.points[first..].iter().zip(values) {
+            for k in 0..4 {
+                sum[k] += value * point[k];
+            }
+        }
+        // Basis functions of Taylor and basis matrix curves don't always sum to 1
+        let w = if self.rational { sum[3] } else { 1.0 };
+        [sum[0] / w, sum[1] / w, sum[2] / w]
+    }
+
+    /// Approximates the curve with a polyline, and returns its points from the start to the end.
+    pub fn polyline(&self, technique: CurveTechnique) -> Vec<[f32; 3]> {
+        self.polyline_between(self.range, technique)
+    }
+
+    /// Approximates the part of the curve between the given parameters, which go backwards if
+    /// `start` is greater than `end`, as trimming curves may do.
+    pub(crate) fn polyline_between(
+        &self,
+        range: (f32, f32),
+        technique: CurveTechnique,
+    ) -> Vec<[f32; 3]> {
+        let parameters = self.parameters_between(range, technique);
+        parameters.into_iter().map(|t| self.evaluate(t)).collect()
+    }
+
+    /// Returns the parameters of the points of `polyline_between`.
+    pub(crate) fn parameters_between(
+        &self,
+        (start, end): (f32, f32),
+        technique: CurveTechnique,
+    ) -> Vec<f32> {
+        let clamp = |t: f32| t.max(self.range.0).min(self.range.1);
+        let (first, last) = (clamp(start.min(end)), clamp(start.max(end)));
+        if first == last {
+            return vec![first];
+        }
+
+        let breaks = self.spline.breaks((first, last));
+        let mut parameters = vec![breaks[0]];
+        for span in breaks.windows(2) {
+            let mut segments = self.segments((span[0], span[1]), technique);
+
+            // The length is underestimated where the curve speeds up between measured points
+            if let CurveTechnique::Spatial(length) = technique {
+                loop {
+                    let samples = self.samples((span[0], span[1]), segments);
+                    let longest = samples
+                        .windows(2)
+                        .map(|pair| spline::distance(pair[0], pair[1]))
+                        .fold(0.0, f32::max);
+                    if longest <= length || segments == MAX_SEGMENTS {
+                        break;
+                    }
+                    segments = spline::segments(segments as f32 * longest / length);
+                }
+            }
+            parameters.extend_from_slice(&divide((span[0], span[1]), segments)[1..]);
+        }
+        if start > end {
+            parameters.reverse();
+        }
+        parameters
+    }
+
+    /// Returns the points at both ends of the segments of a span.
+    fn samples(&self, span: (f32, f32), segments: usize) -> Vec<[f32; 3]> {
+        let parameters = divide(span, segments);
+        parameters.into_iter().map(|t| self.evaluate(t)).collect()
+    }
+
+    /// Returns the number of segments of a span.
+    fn segments(&self, span: (f32, f32), technique: CurveTechnique) -> usize {
+        let segments = match technique {
+            CurveTechnique::Parametric(resolution) => resolution * self.spline.degree() as f32,
+            CurveTechnique::Spatial(length) => self.measure(span).0 / length,
+            CurveTechnique::Curvature { distance, angle } => {
+                spline::by_curvature(self.measure(span), distance, angle)
+            }
+        };
+        spline::segments(segments)
+    }
+
+    /// Returns the length of the span, and the angle by which the curve turns within it.
+    fn measure(&self, span: (f32, f32)) -> (f32, f32) {
+        spline::measure(&self.samples(span, MEASURED_SEGMENTS))
+    }
+}
+
+/// Returns the parameters at both ends of the segments of a span, which is divided evenly.
+fn divide((start, end): (f32, f32), segments: usize) -> Vec<f32> {
+    (0..=segments)
+        .map(|k| start + (end - start) * k as f32 / segments as f32)
+        .collect()
+}
+
+impl RawObj {
+    /// Approximates the `curv` curves of `free_forms` with polylines.
+    ///
+    /// The polylines are appended to `lines` as `Line::P`, with new positions. They belong to the
+    /// mesh of the material of the curve, and to no other group. The curves are removed from
+    /// `free_forms`, while `curv2` curves and surfaces are kept.
+    ///
+    /// Fails in the same way as `Curve::new`, in which case `self` is left untouched.
+    ///
+    /// ```rust
+    /// use obj::raw::freeform::CurveTechnique;
+    /// use obj::raw::object::Line;
+    /// use obj::raw::{parse_obj, CurveApproximation};
+    ///
+    /// let input = b"v 0 0 0\nv 1 0 0\ncstype bezier\ndeg 1\ncurv 0 1 1 2\nparm u 0 1\nend\n";
+    /// let mut raw = parse_obj(&input[..])?;
+    /// raw.approximate_curves(CurveApproximation::Ctech(CurveTechnique::Parametric(4.0)))?;
+    ///
+    /// assert_eq!(raw.lines, [Line::P(vec![2, 3, 4, 5, 6])]);
+    /// assert!(raw.free_forms.is_empty());
+    /// # Ok::<(), obj::ObjError>(())
+    /// ```
+    pub fn approximate_curves(&mut self, method: CurveApproximation) -> ObjResult<()> {
+        let mut polylines = Vec::new();
+        for (i, free_form) in self.free_forms.iter().enumerate() {
+            if let Geometry::Curve { .. } = free_form.geometry {
+                let technique = match method {
+                    CurveApproximation::Ctech(default) => {
+                        free_form.curve_technique.unwrap_or(default)
+                    }
+                    CurveApproximation::Override(technique) => technique,
+                };
+                polylines.push((i, Curve::new(self, free_form)?.polyline(technique)));
+            }
+        }
+
+        for (i, polyline) in &polylines {
+            let offset = self.positions.len();
+            if !self.colors.is_empty() {
+                let len = self.colors.len() + polyline.len();
+                self.colors.resize(len, DEFAULT_COLOR);
+            }
+            for &[x, y, z] in polyline {
+                self.positions.push((x, y, z, 1.0));
+            }
+            self.lines
+                .push(Line::P((offset..offset + polyline.len()).collect()));
+
+            let range = Range {
+                start: self.lines.len() - 1,
+                end: self.lines.len(),
+            };
+            let material = self.free_forms[*i].material.clone();
+            self.extend_mesh(&material, |group| &mut group.lines, range);
+        }
+
+        let removed: Vec<_> = polylines.iter().map(|&(i, _)| i).collect();
+        self.remove_free_forms(&removed);
+        Ok(())
+    }
+}
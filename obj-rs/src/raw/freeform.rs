@@ -0,0 +1,359 @@
+//! Free-form curves and surfaces of `.obj` format
+
+use crate::error::{LoadError, LoadErrorKind, ObjError, ObjResult};
+
+/// Basis of free-form curves and surfaces, given by a `cstype` statement.
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum Basis {
+    /// `bmatrix`, whose basis matrices are given by `bmat` statements.
+    BasisMatrix,
+    /// `bezier`
+    Bezier,
+    /// `bspline`, whose knot vectors are given by `parm` statements.
+    BSpline,
+    /// `cardinal`
+    Cardinal,
+    /// `taylor`
+    Taylor,
+}
+
+impl Basis {
+    /// Keyword of the basis in `cstype` statements.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Basis::BasisMatrix => "bmatrix",
+            Basis::Bezier => "bezier",
+            Basis::BSpline => "bspline",
+            Basis::Cardinal => "cardinal",
+            Basis::Taylor => "taylor",
+        }
+    }
+}
+
+/// Parameter direction of free-form curves and surfaces. Curves only have the `u` direction.
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum Direction {
+    /// `u`
+    U,
+    /// `v`
+    V,
+}
+
+/// Technique used to approximate surfaces with triangles, given by a `stech` statement.
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum SurfaceTechnique {
+    /// `cparma ures vres`, which subdivides each patch `ures * degree` and `vres * degree` times
+    /// in the `u` and `v` directions.
+    ParametricA {
+        /// Resolution in the `u` direction.
+        u: f32,
+        /// Resolution in the `v` direction.
+        v: f32,
+    },
+    /// `cparmb uvres`, which subdivides each patch with the same resolution in both directions.
+    ParametricB(f32),
+    /// `cspace maxlength`, which subdivides until every edge is at most `maxlength` long.
+    Spatial(f32),
+    /// `curv maxdist maxangle`, which subdivides until the triangles are at most `maxdist` away
+    /// from the surface, and the surface turns at most `maxangle` degrees within a triangle.
+    Curvature {
+        /// Maximum distance between the surface and the triangles.
+        distance: f32,
+        /// Maximum angle in degrees.
+        angle: f32,
+    },
+}
+
+/// Technique used to approximate curves with line segments, given by a `ctech` statement.
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum CurveTechnique {
+    /// `cparm res`, which subdivides each segment of the curve `res * degree` times.
+    Parametric(f32),
+    /// `cspace maxlength`, which subdivides until every line segment is at most `maxlength` long.
+    Spatial(f32),
+    /// `curv maxdist maxangle`, which subdivides until the line segments are at most `maxdist`
+    /// away from the curve, and the curve turns at most `maxangle` degrees within a line segment.
+    Curvature {
+        /// Maximum distance between the curve and the line segments.
+        distance: f32,
+        /// Maximum angle in degrees.
+        angle: f32,
+    },
+}
+
+/// Control vertex of a surface, which consists of the indices of its position, and optionally
+/// of its texture coordinates and normal vector.
+pub type SurfaceVertex = (usize, Option<usize>, Option<usize>);
+
+/// Part of a `curv2` curve, used in `trim`, `hole` and `scrv` statements.
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub struct TrimCurve {
+    /// Starting parameter value of the part.
+    pub start: f32,
+    /// Ending parameter value of the part.
+    pub end: f32,
+    /// Index of the curve in `RawObj::free_forms`. `ObjVisitor` receives the index of the `curv2`
+    /// statement among all `curv2` statements instead.
+    pub curve: usize,
+}
+
+/// Edge which two surfaces share, given by a `con` statement.
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub struct Connection {
+    /// Indices of the surfaces in `RawObj::free_forms`. `ObjVisitor` receives the indices of the
+    /// `surf` statements among all `surf` statements instead.
+    pub surfaces: [usize; 2],
+    /// Parts of `curv2` curves in the parameter spaces of the surfaces, which go along the edge
+    /// from the same end.
+    pub curves: [TrimCurve; 2],
+}
+
+/// Geometry of a free-form element.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Geometry {
+    /// Curve in 3D space, given by `curv u0 u1 v1 v2 ...`.
+    Curve {
+        /// Starting parameter value of the curve.
+        start: f32,
+        /// Ending parameter value of the curve.
+        end: f32,
+        /// Indices of the control points in `positions`.
+        control_points: Vec<usize>,
+    },
+    /// Curve in the parameter space of a surface, given by `curv2 vp1 vp2 ...`.
+    Curve2 {
+        /// Indices of the control points in `param_vertices`.
+        control_points: Vec<usize>,
+    },
+    /// Surface, given by `surf s0 s1 t0 t1 v1/vt1/vn1 ...`.
+    Surface {
+        /// Starting and ending parameter values in the `u` direction.
+        s: (f32, f32),
+        /// Starting and ending parameter values in the `v` direction.
+        t: (f32, f32),
+        /// Control vertices, in the order of the `surf` statement.
+        control_points: Vec<SurfaceVertex>,
+    },
+}
+
+/// Free-form curve or surface, with the attributes which were active when it was declared and the
+/// statements of its body.
+///
+/// Arrays which are indexed by `Direction` hold the values of the `u` and `v` directions.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FreeForm {
+    /// Curve or surface.
+    pub geometry: Geometry,
+    /// Whether the curve or surface is rational, i.e. `cstype rat ...`.
+    pub rational: bool,
+    /// Basis given by `cstype`.
+    pub basis: Basis,
+    /// Degrees given by `deg`. The degree of the `v` direction is 0 if it's omitted.
+    pub degree: [usize; 2],
+    /// Basis matrices given by `bmat`, in row major order. They're empty if not given.
+    pub basis_matrix: [Vec<f32>; 2],
+    /// Step sizes given by `step`. They're 0 if not given.
+    pub step: [usize; 2],
+    /// Approximation technique given by `stech`, if any.
+    pub technique: Option<SurfaceTechnique>,
+    /// Approximation technique given by `ctech`, if any.
+    pub curve_technique: Option<CurveTechnique>,
+    /// Material given by the last `usemtl` statement before the element, or an empty string if
+    /// there's none.
+    pub material: String,
+    /// Global parameter values, i.e. knot vectors, given by `parm` statements in the body.
+    pub parameters: [Vec<f32>; 2],
+    /// Outer trimming loops given by `trim` statements in the body.
+    pub trims: Vec<Vec<TrimCurve>>,
+    /// Inner trimming loops given by `hole` statements in the body.
+    pub holes: Vec<Vec<TrimCurve>>,
+    /// Special curves given by `scrv` statements in the body.
+    pub special_curves: Vec<Vec<TrimCurve>>,
+    /// Indices of special points in `param_vertices`, given by `sp` statements in the body.
+    pub special_points: Vec<usize>,
+}
+
+impl FreeForm {
+    /// Returns whether the element is a `curv2` curve.
+    pub fn is_curve2(&self) -> bool {
+        matches!(self.geometry, Geometry::Curve2 { .. })
+    }
+}
+
+/// Free-form attributes which are active, and the element whose body is being parsed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FreeFormState {
+    rational: bool,
+    basis: Option<Basis>,
+    degree: Option<[usize; 2]>,
+    basis_matrix: [Vec<f32>; 2],
+    step: [usize; 2],
+    technique: Option<SurfaceTechnique>,
+    curve_technique: Option<CurveTechnique>,
+    /// Element whose body is being parsed, until `end`.
+    open: Option<FreeForm>,
+    /// Line of the statement which has started `open`.
+    line: usize,
+    /// Number of elements which have been ended before.
+    count: usize,
+    /// Indices in `free_forms` of every `curv2` element so far.
+    curves2: Vec<usize>,
+    /// Indices in `free_forms` of every `surf` element so far.
+    surfaces: Vec<usize>,
+}
+
+impl FreeFormState {
+    /// Number of `curv2` statements so far.
+    pub fn curves2(&self) -> usize {
+        self.curves2.len()
+    }
+
+    /// Number of `surf` statements so far.
+    pub fn surfaces(&self) -> usize {
+        self.surfaces.len()
+    }
+
+    pub fn cstype(&mut self, rational: bool, basis: Basis) {
+        self.rational = rational;
+        self.basis = Some(basis);
+    }
+
+    pub fn degree(&mut self, u: usize, v: Option<usize>) {
+        self.degree = Some([u, v.unwrap_or(0)]);
+    }
+
+    pub fn basis_matrix(&mut self, direction: Direction, matrix: &[f32]) {
+        self.basis_matrix[direction as usize] = matrix.to_vec();
+    }
+
+    pub fn step(&mut self, u: usize, v: Option<usize>) {
+        self.step = [u, v.unwrap_or(0)];
+    }
+
+    pub fn surface_technique(&mut self, technique: SurfaceTechnique) {
+        self.technique = Some(technique);
+    }
+
+    pub fn curve_technique(&mut self, technique: CurveTechnique) {
+        self.curve_technique = Some(technique);
+    }
+
+    /// Starts the body of a new element, which uses the given material.
+    pub fn start(&mut self, geometry: Geometry, material: String) -> ObjResult<()> {
+        if self.open.is_some() {
+            make_error!(
+                UnexpectedStatement,
+                "Expected `end` of the previous curve or surface"
+            );
+        }
+        let (basis, degree) = match (self.basis, self.degree) {
+            (Some(basis), Some(degree)) => (basis, degree),
+            _ => make_error!(
+                UnexpectedStatement,
+                "Expected `cstype` and `deg` before curves and surfaces"
+            ),
+        };
+
+        // Elements are stored in the order of their `end`, which comes before the next element
+        match geometry {
+            Geometry::Curve2 { .. } => self.curves2.push(self.count),
+            Geometry::Surface { .. } => self.surfaces.push(self.count),
+            Geometry::Curve { .. } => {}
+        }
+        self.open = Some(FreeForm {
+            geometry,
+            rational: self.rational,
+            basis,
+            degree,
+            basis_matrix: self.basis_matrix.clone(),
+            step: self.step,
+            technique: self.technique,
+            curve_technique: self.curve_technique,
+            material,
+            parameters: [Vec::new(), Vec::new()],
+            trims: Vec::new(),
+            holes: Vec::new(),
+            special_curves: Vec::new(),
+            special_points: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Returns the element whose body is being parsed.
+    pub fn body(&mut self) -> ObjResult<&mut FreeForm> {
+        match &mut self.open {
+            Some(free_form) => Ok(free_form),
+            None => make_error!(
+                UnexpectedStatement,
+                "Expected `curv`, `curv2` or `surf` before the body statement"
+            ),
+        }
+    }
+
+    /// Converts the indices of `curv2` statements into the indices of the elements.
+    pub fn trim_curves(&self, curves: &[TrimCurve]) -> ObjResult<Vec<TrimCurve>> {
+        curves
+            .iter()
+            .map(|curve| match self.curves2.get(curve.curve) {
+                // The curve has to be ended before
+                Some(&index) if index < self.count => Ok(TrimCurve {
+                    curve: index,
+                    ..*curve
+                }),
+                _ => make_error!(IndexOutOfRange, "Expected a `curv2` curve which has ended"),
+            })
+            .collect()
+    }
+
+    /// Converts the indices of `surf` and `curv2` statements into the indices of the elements.
+    pub fn connection(&self, connection: &Connection) -> ObjResult<Connection> {
+        let mut surfaces = connection.surfaces;
+        for surface in &mut surfaces {
+            *surface = match self.surfaces.get(*surface) {
+                Some(&index) if index < self.count => index,
+                _ => make_error!(IndexOutOfRange, "Expected a `surf` surface which has ended"),
+            };
+        }
+        let curves = self.trim_curves(&connection.curves)?;
+        Ok(Connection {
+            surfaces,
+            curves: [curves[0], curves[1]],
+        })
+    }
+
+    /// Ends the body of the element, and returns the element.
+    pub fn end(&mut self) -> ObjResult<FreeForm> {
+        match self.open.take() {
+            Some(free_form) => {
+                self.count += 1;
+                Ok(free_form)
+            }
+            None => make_error!(
+                UnexpectedStatement,
+                "Expected `curv`, `curv2` or `surf` before `end`"
+            ),
+        }
+    }
+
+    /// Records the line of the statement which has started the last element.
+    pub fn started_at(&mut self, line: usize) {
+        self.line = line;
+    }
+
+    /// Fails if the body of an element hasn't been ended at the end of the file.
+    pub fn finish(&self) -> ObjResult<()> {
+        let statement = match self.open.as_ref().map(|free_form| &free_form.geometry) {
+            None => return Ok(()),
+            Some(Geometry::Curve { .. }) => "curv",
+            Some(Geometry::Curve2 { .. }) => "curv2",
+            Some(Geometry::Surface { .. }) => "surf",
+        };
+        let error = LoadError::new(
+            LoadErrorKind::UnexpectedStatement,
+            "Expected `end` of the curve or surface before the end of the file",
+        );
+        Err(ObjError::from(error)
+            .at_line(self.line)
+            .in_statement(statement))
+    }
+}
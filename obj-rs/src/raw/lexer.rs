@@ -1,19 +1,20 @@
-use crate::error::{LoadError, LoadErrorKind, ObjError, ObjResult};
-use std::io::{BufRead, Lines, Result};
-use std::iter::Map;
-
-fn strip_comment(mut line: String) -> String {
-    if let Some(idx) = line.find('#') {
-        line.truncate(idx)
+use crate::error::{LoadError, LoadErrorKind, ObjError, ObjResult, Warning};
+use crate::raw::util::SmallVec;
+use crate::raw::ParseOptions;
+use std::io::BufRead;
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(idx) => &line[..idx],
+        None => line,
     }
-    line
 }
 
 #[test]
 fn test_strip_commect() {
     macro_rules! t {
         ($input:expr => $output:expr) => {
-            assert_eq!(strip_comment(String::from($input)), String::from($output));
+            assert_eq!(strip_comment($input), $output);
         };
     }
 
@@ -23,89 +24,150 @@ fn test_strip_commect() {
     t!("" => "");
 }
 
-type StrippedLines<T> = Map<Lines<T>, fn(Result<String>) -> Result<String>>;
-
+/// Reads logical lines from the input, reusing a single buffer for all of them.
 pub struct Lexer<T> {
-    stripped_lines: StrippedLines<T>,
+    input: T,
+    /// Number of physical lines read so far.
+    line_number: usize,
+    buffer: String,
+    /// Comments of the current line if they are kept, and the end of each one of them.
+    comments: Option<(String, Vec<usize>)>,
 }
 
 impl<T: BufRead> Lexer<T> {
     pub fn new(input: T) -> Self {
+        Self::after_lines(input, 0)
+    }
+
+    /// Creates a lexer for the input which starts after the given number of lines, e.g. a chunk
+    /// in the middle of a file.
+    pub fn after_lines(input: T, lines: usize) -> Self {
         Lexer {
-            stripped_lines: input.lines().map(|result| result.map(strip_comment)),
+            input,
+            line_number: lines,
+            buffer: String::new(),
+            comments: None,
         }
     }
-}
 
-impl<T: BufRead> Iterator for Lexer<T> {
-    type Item = ObjResult<String>;
+    /// Keeps the comments of each line, instead of discarding them.
+    pub fn keep_comments(mut self) -> Self {
+        self.comments = Some((String::new(), Vec::new()));
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // Check if maybe_line has finished
-        let maybe_line;
-        match self.stripped_lines.next() {
-            None => return None,
-            Some(val) => maybe_line = val,
+    /// Reads the next logical line, and returns the (1-based) physical line number where it
+    /// begins. Lines which end with a backslash are joined with the next line.
+    pub fn next_line(&mut self) -> ObjResult<Option<usize>> {
+        let line_number = self.line_number + 1;
+        self.buffer.clear();
+        if let Some((comments, ends)) = &mut self.comments {
+            comments.clear();
+            ends.clear();
         }
 
-        // Check if maybe_line has errored
-        let line;
-        match maybe_line {
-            Err(e) => return Some(Err(ObjError::Io(e))),
-            Ok(val) => line = val,
-        }
+        loop {
+            let start = self.buffer.len();
+            if self.input.read_line(&mut self.buffer)? == 0 {
+                if start == 0 {
+                    return Ok(None);
+                }
+                return Err(ObjError::Load(LoadError::new(
+                    LoadErrorKind::BackslashAtEOF,
+                    "Expected a line, but met an EOF",
+                ))
+                .at_line(line_number));
+            }
+            self.line_number += 1;
 
-        // Merge lines connected with backslashes
-        let mut buffer = String::new();
-        match line.strip_suffix('\\') {
-            None => buffer.push_str(&line),
-            Some(stripped) => {
-                buffer.push_str(stripped);
-                buffer.push(' ');
-
-                // Search for the next lines
-                loop {
-                    let line;
-                    match self.stripped_lines.next() {
-                        None => {
-                            return Some(Err(ObjError::Load(LoadError::new(
-                                LoadErrorKind::BackslashAtEOF,
-                                "Expected a line, but met an EOF",
-                            ))))
-                        }
-                        Some(Err(e)) => return Some(Err(ObjError::Io(e))),
-                        Some(Ok(val)) => line = val,
-                    }
-                    match line.strip_suffix('\\') {
-                        Some(stripped) => {
-                            buffer.push_str(stripped);
-                            buffer.push(' ');
-                        }
-                        None => {
-                            buffer.push_str(&line);
-                            break;
-                        }
-                    }
+            // Strip the line ending and the comment, the same way as `BufRead::lines` does
+            if self.buffer.ends_with('\n') {
+                self.buffer.pop();
+                if self.buffer.ends_with('\r') {
+                    self.buffer.pop();
+                }
+            }
+            let end = start + strip_comment(&self.buffer[start..]).len();
+            if let Some((comments, ends)) = &mut self.comments {
+                if end < self.buffer.len() {
+                    comments.push_str(&self.buffer[end + 1..]);
+                    ends.push(comments.len());
                 }
             }
+            self.buffer.truncate(end);
+
+            // Merge lines connected with backslashes
+            if !self.buffer.ends_with('\\') {
+                break;
+            }
+            self.buffer.pop();
+            self.buffer.push(' ');
         }
 
-        Some(Ok(buffer))
+        Ok(Some(line_number))
+    }
+
+    /// Returns the current logical line without comments.
+    pub fn line(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Returns the text after `#` of each comment in the current line, if comments are kept.
+    pub fn comments(&self) -> impl Iterator<Item = &str> {
+        let (comments, ends): (&str, &[usize]) = match &self.comments {
+            Some((comments, ends)) => (comments, ends),
+            None => ("", &[]),
+        };
+        let starts = std::iter::once(0).chain(ends.iter().copied());
+        starts
+            .zip(ends)
+            .map(move |(start, &end)| &comments[start..end])
     }
 }
 
-pub fn lex<T, F>(input: T, mut callback: F) -> ObjResult<()>
+/// Splits the input into statements, and calls `callback` with the line number, the keyword and
+/// the arguments of each statement.
+///
+/// If the lexer keeps comments, each comment is passed to `callback` after the statement on the
+/// same line, as a statement whose keyword is `#` and whose only argument is the comment.
+///
+/// Errors returned by `callback` are annotated with the line number and the keyword of the
+/// statement. In lenient mode, such errors are collected as warnings instead, and the parsing
+/// continues with the next statement.
+///
+/// Nothing is allocated per statement, unless a statement has more than 32 arguments.
+pub fn lex<T, F>(
+    mut lexer: Lexer<T>,
+    options: &ParseOptions,
+    mut callback: F,
+) -> ObjResult<Vec<Warning>>
 where
     T: BufRead,
-    F: FnMut(&str, &[&str]) -> ObjResult<()>,
+    F: FnMut(usize, &str, &[&str]) -> ObjResult<()>,
 {
-    for maybe_buffer in Lexer::new(input) {
-        if let [stmt, ref args @ ..] = maybe_buffer?.split_whitespace().collect::<Vec<_>>()[..] {
-            callback(stmt, args)?
+    let mut warnings = Vec::new();
+    let mut handle = |line: usize, stmt: &str, args: &[&str]| {
+        if let Err(e) = callback(line, stmt, args) {
+            let e = e.at_line(line).in_statement(stmt);
+            if !options.is_lenient() {
+                return Err(e);
+            }
+            warnings.push(e.into_warning()?);
+        }
+        Ok(())
+    };
+
+    while let Some(line) = lexer.next_line()? {
+        let tokens: SmallVec<&str, 33> = lexer.line().split_whitespace().collect();
+        if let [stmt, ref args @ ..] = tokens[..] {
+            handle(line, stmt, args)?;
+        }
+        for comment in lexer.comments() {
+            handle(line, "#", &[comment])?;
         }
     }
 
-    Ok(())
+    Ok(warnings)
 }
 
 #[test]
@@ -124,21 +186,25 @@ bmat u  1       -3      3       -1      0       3       -6      3       \
 bmat u  1       -3      3       -1      0       3       -6      3       0       0       3       -3      0       0       0       1
 "#;
 
-    assert!(lex(&mut input.as_bytes(), |stmt, args| {
-        match stmt {
-            "statement0" => assert_eq!(args, ["arg0", "arg1", "arg2"]),
-            "statement1" => assert_eq!(args, ["arg0", "arg1"]),
-            "statement2" => assert_eq!(args, ["Hello,", "world!"]),
-            "bmat" => assert_eq!(
-                args,
-                [
-                    "u", "1", "-3", "3", "-1", "0", "3", "-6", "3", "0", "0", "3", "-3", "0", "0",
-                    "0", "1"
-                ]
-            ),
-            _ => panic!("Unit test failed"),
+    assert!(lex(
+        Lexer::new(input.as_bytes()),
+        &ParseOptions::new(),
+        |_, stmt, args| {
+            match stmt {
+                "statement0" => assert_eq!(args, ["arg0", "arg1", "arg2"]),
+                "statement1" => assert_eq!(args, ["arg0", "arg1"]),
+                "statement2" => assert_eq!(args, ["Hello,", "world!"]),
+                "bmat" => assert_eq!(
+                    args,
+                    [
+                        "u", "1", "-3", "3", "-1", "0", "3", "-6", "3", "0", "0", "3", "-3", "0",
+                        "0", "0", "1"
+                    ]
+                ),
+                _ => panic!("Unit test failed"),
+            }
+            Ok(())
         }
-        Ok(())
-    })
+    )
     .is_ok());
 }
@@ -1,91 +1,235 @@
 //! Parses `.mtl` format which stores material data
 
-use std::collections::HashMap;
-use std::io::BufRead;
+use std::io::{self, BufRead, Write};
 use std::mem::take;
 
-use crate::error::ObjResult;
-use crate::raw::lexer::lex;
-use crate::raw::util::parse_args;
+use indexmap::IndexMap;
+
+use crate::error::{ObjResult, Warning};
+use crate::raw::lexer::{lex, Lexer};
+use crate::raw::util::{parse_args, parse_token};
+use crate::raw::ParseOptions;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Parses a wavefront `.mtl` format *(incomplete)*
 pub fn parse_mtl<T: BufRead>(input: T) -> ObjResult<RawMtl> {
-    let mut materials = HashMap::new();
-
-    // Properties of the material being currently parsed
-    let mut name: Option<String> = None;
-    let mut mat: Material = Material::default();
-
-    lex(input, |stmt, args| {
-        match stmt {
-            // Material name statement
-            "newmtl" => {
-                // Finish whatever material we were parsing
-                if let Some(name) = name.take() {
-                    materials.insert(name, take(&mut mat));
+    parse_mtl_with(input, &ParseOptions::default())
+}
+
+/// Parses a wavefront `.mtl` format with the given options *(incomplete)*
+pub fn parse_mtl_with<T: BufRead>(input: T, options: &ParseOptions) -> ObjResult<RawMtl> {
+    let mut parser = MtlParser::default();
+    parser.parse(Lexer::new(input), options)?;
+    Ok(parser.finish())
+}
+
+/// State of the `.mtl` parser, which can be fed with consecutive chunks of the input.
+#[derive(Default)]
+pub(crate) struct MtlParser {
+    materials: IndexMap<String, Material>,
+    /// Name of the material being currently parsed
+    name: Option<String>,
+    /// Properties of the material being currently parsed
+    mat: Material,
+    /// Whether the last `newmtl` statement was malformed, so properties have no material to go to
+    orphaned: bool,
+    warnings: Vec<Warning>,
+}
+
+impl MtlParser {
+    /// Parses the next chunk of the input.
+    pub fn parse<T: BufRead>(&mut self, lexer: Lexer<T>, options: &ParseOptions) -> ObjResult<()> {
+        let MtlParser {
+            materials,
+            name,
+            mat,
+            orphaned,
+            ..
+        } = self;
+
+        let warnings = lex(lexer, options, |_, stmt, args| {
+            // Properties after a malformed `newmtl` are skipped, which happens only in lenient mode
+            if *orphaned && stmt != "newmtl" {
+                make_error!(
+                    UnexpectedStatement,
+                    "Expected a valid `newmtl` statement before the property"
+                );
+            }
+
+            match stmt {
+                // Material name statement
+                "newmtl" => {
+                    // Finish whatever material we were parsing
+                    if let Some(name) = name.take() {
+                        materials.insert(name, take(mat));
+                    }
+
+                    match args {
+                        [arg] => {
+                            *name = Some((*arg).to_string());
+                            *orphaned = false;
+                        }
+                        _ => {
+                            *orphaned = true;
+                            make_error!(WrongNumberOfArguments, "Expected exactly 1 argument")
+                        }
+                    }
                 }
 
-                match args {
-                    [arg] => name = Some((*arg).to_string()),
+                // Material color and illumination statements
+                "Ka" => mat.ambient = Some(parse_color(args)?),
+                "Kd" => mat.diffuse = Some(parse_color(args)?),
+                "Ks" => mat.specular = Some(parse_color(args)?),
+                "Ke" => mat.emissive = Some(parse_color(args)?),
+                "Km" => make_error!(UnsupportedStatement, "Statement 'Km' is not supported yet"),
+                "Tf" => mat.transmission_filter = Some(parse_color(args)?),
+                "Ns" => match args {
+                    [arg] => mat.specular_exponent = Some(parse_token(arg)?),
                     _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
-                }
+                },
+                "Ni" => match args {
+                    [arg] => mat.optical_density = Some(parse_token(arg)?),
+                    _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
+                },
+                "illum" => match args {
+                    [arg] => mat.illumination_model = Some(parse_token(arg)?),
+                    _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
+                },
+                "d" => match args {
+                    [arg] => mat.dissolve = Some(parse_token(arg)?),
+                    _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
+                },
+                "Tr" => match args {
+                    [arg] => mat.dissolve = Some(1.0 - parse_token::<f32>(arg)?),
+                    _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
+                },
+
+                // Texture map statements
+                "map_Ka" => mat.ambient_map = Some(parse_texture_map(args)?),
+                "map_Kd" => mat.diffuse_map = Some(parse_texture_map(args)?),
+                "map_Ks" => mat.specular_map = Some(parse_texture_map(args)?),
+                "map_Ke" => mat.emissive_map = Some(parse_texture_map(args)?),
+                "map_d" => mat.dissolve_map = Some(parse_texture_map(args)?),
+                "map_aat" | "map_refl" => make_error!(
+                    UnsupportedStatement,
+                    "Texture map options are not supported yet"
+                ),
+                "map_bump" | "map_Bump" | "bump" => mat.bump_map = Some(parse_texture_map(args)?),
+                "disp" => make_error!(
+                    UnsupportedStatement,
+                    "Displacement maps are not supported yet"
+                ),
+
+                // Reflection map statement
+                "refl" => make_error!(
+                    UnsupportedStatement,
+                    "Reflection maps are not supported yet"
+                ),
+
+                // Unexpected statement
+                _ => make_error!(UnexpectedStatement, "Received unknown statement"),
             }
 
-            // Material color and illumination statements
-            "Ka" => mat.ambient = Some(parse_color(args)?),
-            "Kd" => mat.diffuse = Some(parse_color(args)?),
-            "Ks" => mat.specular = Some(parse_color(args)?),
-            "Ke" => mat.emissive = Some(parse_color(args)?),
-            "Km" => unimplemented!(),
-            "Tf" => mat.transmission_filter = Some(parse_color(args)?),
-            "Ns" => match args {
-                [arg] => mat.specular_exponent = Some(arg.parse()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
-            },
-            "Ni" => match args {
-                [arg] => mat.optical_density = Some(arg.parse()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
-            },
-            "illum" => match args {
-                [arg] => mat.illumination_model = Some(arg.parse()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
-            },
-            "d" => match args {
-                [arg] => mat.dissolve = Some(arg.parse()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
-            },
-            "Tr" => match args {
-                [arg] => mat.dissolve = Some(1.0 - arg.parse::<f32>()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected exactly 1 argument"),
-            },
-
-            // Texture map statements
-            "map_Ka" => mat.ambient_map = Some(parse_texture_map(args)?),
-            "map_Kd" => mat.diffuse_map = Some(parse_texture_map(args)?),
-            "map_Ks" => mat.specular_map = Some(parse_texture_map(args)?),
-            "map_Ke" => mat.emissive_map = Some(parse_texture_map(args)?),
-            "map_d" => mat.dissolve_map = Some(parse_texture_map(args)?),
-            "map_aat" => unimplemented!(),
-            "map_refl" => unimplemented!(),
-            "map_bump" | "map_Bump" | "bump" => mat.bump_map = Some(parse_texture_map(args)?),
-            "disp" => unimplemented!(),
-
-            // Reflection map statement
-            "refl" => unimplemented!(),
-
-            // Unexpected statement
-            _ => make_error!(UnexpectedStatement, "Received unknown statement"),
-        }
+            Ok(())
+        })?;
 
+        self.warnings.extend(warnings);
         Ok(())
-    })?;
+    }
 
-    // Insert the final material
-    if let Some(name) = name {
-        materials.insert(name, mat);
+    /// Finishes parsing after the last chunk of the input.
+    pub fn finish(mut self) -> RawMtl {
+        // Insert the final material
+        if let Some(name) = self.name {
+            self.materials.insert(name, self.mat);
+        }
+
+        RawMtl {
+            materials: self.materials,
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// Writes a `RawMtl` in wavefront `.mtl` format.
+///
+/// Materials are written in the order of `materials`, which is the order of the `newmtl`
+/// statements when parsed. Warnings are not written.
+///
+/// ```rust
+/// use obj::raw::material::{parse_mtl, write_mtl};
+///
+/// let mtl = parse_mtl(&b"newmtl red\nKd 1 0 0\nmap_Kd red.png\n"[..])?;
+///
+/// let mut output = Vec::new();
+/// write_mtl(&mtl, &mut output)?;
+/// assert_eq!(output, b"newmtl red\nKd 1 0 0\nmap_Kd red.png\n");
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn write_mtl<W: Write>(mtl: &RawMtl, mut output: W) -> io::Result<()> {
+    for (i, (name, material)) in mtl.materials.iter().enumerate() {
+        if i > 0 {
+            writeln!(output)?;
+        }
+        write_material(name, material, &mut output)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a single material in wavefront `.mtl` format, starting with a `newmtl` statement.
+pub fn write_material<W: Write>(name: &str, material: &Material, mut output: W) -> io::Result<()> {
+    writeln!(output, "newmtl {}", name)?;
+
+    // Material color and illumination statements
+    let colors = [
+        ("Ka", &material.ambient),
+        ("Kd", &material.diffuse),
+        ("Ks", &material.specular),
+        ("Ke", &material.emissive),
+        ("Tf", &material.transmission_filter),
+    ];
+    for (stmt, color) in &colors {
+        match color {
+            Some(MtlColor::Rgb(r, g, b)) => writeln!(output, "{} {} {} {}", stmt, r, g, b)?,
+            Some(MtlColor::Xyz(x, y, z)) => writeln!(output, "{} xyz {} {} {}", stmt, x, y, z)?,
+            Some(MtlColor::Spectral(file, factor)) => {
+                writeln!(output, "{} spectral {} {}", stmt, file, factor)?
+            }
+            None => {}
+        }
+    }
+    if let Some(exponent) = material.specular_exponent {
+        writeln!(output, "Ns {}", exponent)?;
+    }
+    if let Some(density) = material.optical_density {
+        writeln!(output, "Ni {}", density)?;
+    }
+    if let Some(dissolve) = material.dissolve {
+        writeln!(output, "d {}", dissolve)?;
+    }
+    if let Some(model) = material.illumination_model {
+        writeln!(output, "illum {}", model)?;
     }
 
-    Ok(RawMtl { materials })
+    // Texture map statements
+    let maps = [
+        ("map_Ka", &material.ambient_map),
+        ("map_Kd", &material.diffuse_map),
+        ("map_Ks", &material.specular_map),
+        ("map_Ke", &material.emissive_map),
+        ("map_d", &material.dissolve_map),
+        ("bump", &material.bump_map),
+    ];
+    for (stmt, map) in &maps {
+        if let Some(map) = map {
+            writeln!(output, "{} {}", stmt, map.file)?;
+        }
+    }
+
+    Ok(())
 }
 
 /// Parses a color from the arguments of a statement
@@ -103,7 +247,7 @@ fn parse_color(args: &[&str]) -> ObjResult<MtlColor> {
 
         "spectral" => match args[1..] {
             [name] => MtlColor::Spectral(name.to_string(), 1.0),
-            [name, multiplier] => MtlColor::Spectral(name.to_string(), multiplier.parse()?),
+            [name, multiplier] => MtlColor::Spectral(name.to_string(), parse_token(multiplier)?),
             _ => make_error!(WrongNumberOfArguments, "Expected 1 or 2 arguments"),
         },
 
@@ -126,14 +270,19 @@ fn parse_texture_map(args: &[&str]) -> ObjResult<MtlTextureMap> {
 }
 
 /// Low-level Rust binding for `.mtl` format *(incomplete)*.
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RawMtl {
-    /// Map from the material name to its properties
-    pub materials: HashMap<String, Material>,
+    /// Map from the material name to its properties, in the order of declaration
+    pub materials: IndexMap<String, Material>,
+
+    /// Statements which have been skipped in lenient mode.
+    pub warnings: Vec<Warning>,
 }
 
 /// A single material from a `.mtl` file
 #[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Material {
     /// The ambient color, specified by `Ka`
     pub ambient: Option<MtlColor>,
@@ -169,6 +318,7 @@ pub struct Material {
 
 /// A color specified in a `.mtl` file
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MtlColor {
     /// Color in the RGB color space
     Rgb(f32, f32, f32),
@@ -183,6 +333,7 @@ pub enum MtlColor {
 
 /// A texture map specified in a `.mtl` file
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MtlTextureMap {
     /// The name of the texture file
     pub file: String,
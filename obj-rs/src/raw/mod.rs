@@ -1,9 +1,78 @@
 //! Provides low-level API for Wavefront OBJ format.
 
+#![forbid(unsafe_code)]
+
+#[cfg(feature = "tokio")]
+mod asynchronous;
+#[cfg(any(feature = "rayon", feature = "tokio"))]
+mod chunk;
+mod curve;
+pub mod freeform;
 mod lexer;
 pub mod material;
+mod normals;
 pub mod object;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod spline;
+mod tessellation;
+mod triangulation;
+mod trimming;
 mod util;
 
-pub use self::material::{parse_mtl, RawMtl};
-pub use self::object::{parse_obj, RawObj};
+#[cfg(feature = "tokio")]
+pub use self::asynchronous::{
+    parse_mtl_async, parse_mtl_async_with, parse_obj_async, parse_obj_async_with,
+};
+pub use self::curve::{Curve, CurveApproximation};
+pub use self::material::{parse_mtl, parse_mtl_with, write_mtl, RawMtl};
+pub use self::normals::NormalGeneration;
+pub use self::object::{
+    parse_obj, parse_obj_with, visit_obj, visit_obj_with, write_obj, ObjVisitor, RawObj,
+};
+#[cfg(feature = "rayon")]
+pub use self::parallel::{parse_obj_parallel, parse_obj_parallel_with};
+pub use self::tessellation::Tessellation;
+pub use self::triangulation::Triangulation;
+/// Map which keeps the order of insertion, used for the groups of `RawObj` and the materials of
+/// `RawMtl`.
+pub use indexmap::IndexMap;
+
+/// Options which control how `.obj` and `.mtl` files are parsed.
+///
+/// ```rust
+/// use obj::raw::{parse_obj_with, ParseOptions};
+///
+/// let input = b"v 0 0 0\n#MRGB ff0000\nPr 0.5\nv 1 0 0";
+/// let raw = parse_obj_with(&input[..], &ParseOptions::new().lenient(true))?;
+///
+/// assert_eq!(raw.positions.len(), 2);
+/// assert_eq!(raw.warnings.len(), 1);
+/// assert_eq!(raw.warnings[0].line, 3);
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    lenient: bool,
+}
+
+impl ParseOptions {
+    /// Creates options for the strict mode, which stops at the first erroneous statement.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to skip unknown or malformed statements instead of failing.
+    ///
+    /// Every skipped statement is recorded as a `Warning` in the result. Errors which aren't
+    /// caused by a single statement, like IO errors, still stop the parser.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Returns whether the lenient mode is enabled.
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+}
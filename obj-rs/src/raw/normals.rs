@@ -0,0 +1,149 @@
+//! Generates normal vectors of `RawObj` which doesn't have them
+
+use std::collections::HashMap;
+
+use crate::raw::object::{Polygon, RawObj};
+
+/// Method used to generate normal vectors of polygons which don't have them.
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum NormalGeneration {
+    /// Every vertex of a polygon gets the normal vector of the polygon, so the model looks
+    /// faceted.
+    Flat,
+    /// Each vertex gets the average of the normal vectors of the polygons which share it within
+    /// the same smoothing group, weighted by the angle of the polygons at the vertex.
+    ///
+    /// Polygons in different smoothing groups don't affect each other, so the edges between them
+    /// stay hard. Polygons which don't belong to any smoothing group (`s off`) get flat normals.
+    Smooth,
+}
+
+impl RawObj {
+    /// Generates normal vectors of polygons which don't have them.
+    ///
+    /// The generated normal vectors are appended to `normals`, and `Polygon::P` and
+    /// `Polygon::PT` are turned into `Polygon::PN` and `Polygon::PTN` respectively. Polygons which
+    /// already have normal vectors are left untouched.
+    pub fn generate_normals(&mut self, method: NormalGeneration) {
+        // Smoothing group of each polygon, `None` for `s off`
+        let mut smoothing_groups = vec![None; self.polygons.len()];
+        if method == NormalGeneration::Smooth {
+            for (&id, group) in &self.smoothing_groups {
+                for range in &group.polygons {
+                    for slot in &mut smoothing_groups[range.start..range.end] {
+                        *slot = Some(id);
+                    }
+                }
+            }
+        }
+
+        let face_normals: Vec<_> = self
+            .polygons
+            .iter()
+            .map(|polygon| face_normal(&corners(&self.positions, polygon)))
+            .collect();
+
+        // Sum of the weighted face normals around each vertex of each smoothing group
+        let mut sums = HashMap::new();
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            let id = match smoothing_groups[i] {
+                Some(id) => id,
+                None => continue,
+            };
+            let corners = corners(&self.positions, polygon);
+            for (j, &p) in position_indices(polygon).iter().enumerate() {
+                let len = corners.len();
+                let angle = angle(
+                    corners[(j + len - 1) % len],
+                    corners[j],
+                    corners[(j + 1) % len],
+                );
+                let sum = sums.entry((p, id)).or_insert([0.0; 3]);
+                for axis in 0..3 {
+                    sum[axis] += face_normals[i][axis] * angle;
+                }
+            }
+        }
+
+        // Index of the generated normal vector of each vertex of each smoothing group
+        let mut smooth_normals = HashMap::with_capacity(sums.len());
+        let normals = &mut self.normals;
+        for (i, polygon) in self.polygons.iter_mut().enumerate() {
+            let flat_index = match *polygon {
+                Polygon::P(_) | Polygon::PT(_) => normals.len(),
+                Polygon::PN(_) | Polygon::PTN(_) => continue,
+            };
+
+            let mut normal_of = |p: usize| match smoothing_groups[i] {
+                None => {
+                    if flat_index == normals.len() {
+                        let n = face_normals[i];
+                        normals.push((n[0], n[1], n[2]));
+                    }
+                    flat_index
+                }
+                Some(id) => *smooth_normals.entry((p, id)).or_insert_with(|| {
+                    let n = normalize(sums[&(p, id)]);
+                    normals.push((n[0], n[1], n[2]));
+                    normals.len() - 1
+                }),
+            };
+
+            *polygon = match polygon {
+                Polygon::P(vec) => Polygon::PN(vec.iter().map(|&p| (p, normal_of(p))).collect()),
+                Polygon::PT(vec) => {
+                    Polygon::PTN(vec.iter().map(|&(p, t)| (p, t, normal_of(p))).collect())
+                }
+                Polygon::PN(_) | Polygon::PTN(_) => unreachable!(),
+            };
+        }
+    }
+}
+
+fn position_indices(polygon: &Polygon) -> Vec<usize> {
+    match polygon {
+        Polygon::P(vec) => vec.clone(),
+        Polygon::PT(vec) | Polygon::PN(vec) => vec.iter().map(|&(p, _)| p).collect(),
+        Polygon::PTN(vec) => vec.iter().map(|&(p, _, _)| p).collect(),
+    }
+}
+
+fn corners(positions: &[(f32, f32, f32, f32)], polygon: &Polygon) -> Vec<[f32; 3]> {
+    position_indices(polygon)
+        .into_iter()
+        .map(|p| {
+            let p = positions[p];
+            [p.0, p.1, p.2]
+        })
+        .collect()
+}
+
+/// Unit normal vector of a polygon, computed with Newell's method. It is the zero vector if the
+/// polygon is degenerate.
+pub(crate) fn face_normal(corners: &[[f32; 3]]) -> [f32; 3] {
+    let mut normal = [0.0f32; 3];
+    for (i, a) in corners.iter().enumerate() {
+        let b = corners[(i + 1) % corners.len()];
+        normal[0] += (a[1] - b[1]) * (a[2] + b[2]);
+        normal[1] += (a[2] - b[2]) * (a[0] + b[0]);
+        normal[2] += (a[0] - b[0]) * (a[1] + b[1]);
+    }
+    normalize(normal)
+}
+
+/// Angle between `a - b` and `c - b`, in radians.
+pub(crate) fn angle(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> f32 {
+    let u = normalize([a[0] - b[0], a[1] - b[1], a[2] - b[2]]);
+    let v = normalize([c[0] - b[0], c[1] - b[1], c[2] - b[2]]);
+    let dot = u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+    dot.clamp(-1.0, 1.0).acos()
+}
+
+pub(crate) fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > 0.0 {
+        [v[0] / len, v[1] / len, v[2] / len]
+    } else {
+        v
+    }
+}
@@ -1,20 +1,26 @@
 //! Parses `.obj` format which stores 3D mesh data
 
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
 use std::hash::Hash;
-use std::io::BufRead;
+use std::io::{self, BufRead, Write};
 use std::mem;
 
-use crate::error::ObjResult;
-use crate::raw::lexer::lex;
-use crate::raw::util::parse_args;
+use indexmap::map::Entry;
+use indexmap::IndexMap;
+
+use crate::error::{ObjResult, Warning};
+use crate::raw::freeform::{
+    Basis, Connection, CurveTechnique, Direction, FreeForm, FreeFormState, Geometry,
+    SurfaceTechnique, SurfaceVertex, TrimCurve,
+};
+use crate::raw::lexer::{lex, Lexer};
+use crate::raw::util::{parse_args, parse_token, SmallVec};
+use crate::raw::ParseOptions;
 
 macro_rules! parse_args {
     {
         $first:expr, $rest:expr,
         $($pat:pat => $type:ident::$name:ident[$exp:expr]),*,
-        ! => $error:expr
+        ! => $kind:ident, $message:expr
     } => (
         match split_vertex_group($first)[..] {
             $($pat => $type::$name({
@@ -22,19 +28,19 @@ macro_rules! parse_args {
                 for param in $rest {
                     match split_vertex_group(param)[..] {
                         $pat => points.push($exp),
-                        _ => $error
+                        _ => make_error!($kind, $message, param)
                     }
                 }
                 points
             }),)*
-            _ => $error
+            _ => make_error!($kind, $message, $first)
         }
     )
 }
 
 // Helper function for handling the indexes.
 //
-// If total size of the collection is 5:
+// If total size of the collection is 5, i.e. `len` is 5:
 //
 // - ["1", "2", "3", "4", "5"] → [0, 1, 2, 3, 4]
 // - ["-5", "-4", "-3", "-2", "-1"] → [0, 1, 2, 3, 4]
@@ -47,11 +53,11 @@ macro_rules! parse_args {
 //
 // If the index is > 0 then it's simply the position in the list such
 // that 1 is the first vertex.
-fn try_index<T>(collection: &[T], input: &str) -> ObjResult<usize> {
+fn try_index(len: usize, input: &str) -> ObjResult<usize> {
     use crate::error::{LoadError, LoadErrorKind, ObjError};
     use std::convert::TryInto;
 
-    let len: isize = collection.len().try_into().map_err(|_| {
+    let len: isize = len.try_into().map_err(|_| {
         ObjError::Load(LoadError::new(
             LoadErrorKind::IndexOutOfRange,
             "Too many items in collection",
@@ -59,23 +65,23 @@ fn try_index<T>(collection: &[T], input: &str) -> ObjResult<usize> {
     })?;
 
     // Should be [-len, -1] ∪ [1, len]
-    let index: isize = input.parse()?;
+    let index: isize = parse_token(input)?;
 
     let ret = if index < -len {
         // (∞, -len)
-        make_error!(IndexOutOfRange, "Too small index value");
+        make_error!(IndexOutOfRange, "Too small index value", input);
     } else if index < 0 {
         // [-len, 0)
         len + index
     } else if index == 0 {
         // {0}
-        make_error!(IndexOutOfRange, "Index value shouldn't be zero");
+        make_error!(IndexOutOfRange, "Index value shouldn't be zero", input);
     } else if index <= len {
         // (0, len]
         index - 1
     } else {
         // (len, ∞)
-        make_error!(IndexOutOfRange, "Too big index value");
+        make_error!(IndexOutOfRange, "Too big index value", input);
     };
 
     Ok(ret as usize)
@@ -83,83 +89,357 @@ fn try_index<T>(collection: &[T], input: &str) -> ObjResult<usize> {
 
 /// Parses a wavefront `.obj` format.
 pub fn parse_obj<T: BufRead>(input: T) -> ObjResult<RawObj> {
-    let mut name = None;
-    let mut material_libraries = Vec::new();
+    parse_obj_with(input, &ParseOptions::default())
+}
+
+/// Parses a wavefront `.obj` format with the given options.
+pub fn parse_obj_with<T: BufRead>(input: T, options: &ParseOptions) -> ObjResult<RawObj> {
+    let mut chunk = parse_chunk(
+        Lexer::new(input),
+        options,
+        &Context::default(),
+        &mut VertexCounts::default(),
+    )?;
+    finish_free_forms(&mut chunk.raw, &chunk.end.free_form, options)?;
+    Ok(chunk.raw)
+}
+
+/// Checks that the file doesn't end in the body of a curve or surface. In lenient mode, the
+/// unfinished element is skipped with a warning instead.
+pub(crate) fn finish_free_forms(
+    raw: &mut RawObj,
+    free_form: &FreeFormState,
+    options: &ParseOptions,
+) -> ObjResult<()> {
+    if let Err(e) = free_form.finish() {
+        if !options.is_lenient() {
+            return Err(e);
+        }
+        raw.warnings.push(e.into_warning()?);
+    }
+    Ok(())
+}
+
+/// Receives the statements of a wavefront `.obj` file one by one, while the file is being parsed
+/// with `visit_obj`.
+///
+/// Every method does nothing by default, so a visitor only implements the statements which it's
+/// interested in. Indices of the elements are 0-based, and relative (negative) indices are
+/// already resolved, the same as in `RawObj`. Errors returned by a method stop the parser, or
+/// are collected as warnings in lenient mode.
+///
+/// ```rust
+/// use obj::raw::object::{visit_obj, ObjVisitor};
+/// use obj::ObjResult;
+///
+/// /// Computes the bounding box of a model, without keeping it in memory.
+/// struct Bounds {
+///     min: [f32; 3],
+///     max: [f32; 3],
+/// }
+///
+/// impl ObjVisitor for Bounds {
+///     fn position(&mut self, x: f32, y: f32, z: f32, _: f32) -> ObjResult<()> {
+///         for (i, &value) in [x, y, z].iter().enumerate() {
+///             self.min[i] = self.min[i].min(value);
+///             self.max[i] = self.max[i].max(value);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut bounds = Bounds { min: [f32::INFINITY; 3], max: [f32::NEG_INFINITY; 3] };
+/// visit_obj(&b"v 0 -1 0\nv 2 1 0\nv 1 0 3\nf 1 2 3"[..], &mut bounds)?;
+///
+/// assert_eq!(bounds.min, [0.0, -1.0, 0.0]);
+/// assert_eq!(bounds.max, [2.0, 1.0, 3.0]);
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+#[allow(unused_variables)]
+pub trait ObjVisitor {
+    /// Called for each `v` statement. `w` is 1 if it's omitted.
+    fn position(&mut self, x: f32, y: f32, z: f32, w: f32) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called after `position` for each `v` statement with a vertex color, i.e. `v x y z r g b`
+    /// or `v x y z w r g b`.
+    fn color(&mut self, r: f32, g: f32, b: f32) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `vt` statement. Omitted `v` and `w` are 0.
+    fn tex_coord(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `vn` statement.
+    fn normal(&mut self, x: f32, y: f32, z: f32) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `vp` statement. `v` is 0 and `w` is 1 if they're omitted.
+    fn param_vertex(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each point of `p` statements.
+    fn point(&mut self, point: Point) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `l` statement.
+    fn line(&mut self, line: Line) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `f` or `fo` statement.
+    fn face(&mut self, polygon: Polygon) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `g` statement, with all the names of the groups it starts. `g` without
+    /// any name starts the `default` group.
+    fn group(&mut self, names: &[&str]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `usemtl` statement.
+    fn usemtl(&mut self, material: &str) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each file of `mtllib` statements.
+    fn mtllib(&mut self, path: &str) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `s` statement, with `None` for `s off`.
+    fn smoothing(&mut self, group: Option<usize>) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `mg` statement, with `None` for `mg off`.
+    fn merging(&mut self, group: Option<usize>) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `o` statement, with `None` if the name is omitted.
+    fn object(&mut self, name: Option<&str>) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `cstype` statement, with whether the curves and surfaces are rational.
+    fn cstype(&mut self, rational: bool, basis: Basis) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `deg` statement. `v` is `None` if it's omitted, as for curves.
+    fn degree(&mut self, u: usize, v: Option<usize>) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `bmat` statement, with the basis matrix in row major order.
+    fn basis_matrix(&mut self, direction: Direction, matrix: &[f32]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `step` statement. `v` is `None` if it's omitted, as for curves.
+    fn step(&mut self, u: usize, v: Option<usize>) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `stech` statement.
+    fn surface_technique(&mut self, technique: SurfaceTechnique) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `ctech` statement.
+    fn curve_technique(&mut self, technique: CurveTechnique) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `curv` statement, with the indices of its control points in positions.
+    fn curve(&mut self, start: f32, end: f32, control_points: &[usize]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `curv2` statement, with the indices of its control points in parameter
+    /// vertices.
+    fn curve2(&mut self, control_points: &[usize]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `surf` statement, with the parameter ranges in the `u` and `v` directions.
+    fn surface(
+        &mut self,
+        s: (f32, f32),
+        t: (f32, f32),
+        control_points: &[SurfaceVertex],
+    ) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `parm` statement.
+    fn parameters(&mut self, direction: Direction, values: &[f32]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `trim` statement. The `curve` of each part is the 0-based index of the
+    /// `curv2` statement among all of them.
+    fn trim(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `hole` statement, with curves like the ones of `trim`.
+    fn hole(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `scrv` statement, with curves like the ones of `trim`.
+    fn special_curve(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `sp` statement, with the indices of the points in parameter vertices.
+    fn special_points(&mut self, points: &[usize]) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `end` statement, which ends the body of a curve or surface.
+    fn end(&mut self) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each `con` statement. The surfaces are the 0-based indices of the `surf`
+    /// statements among all of them, and the curves are like the ones of `trim`.
+    fn connection(&mut self, connection: &Connection) -> ObjResult<()> {
+        Ok(())
+    }
+
+    /// Called for each comment, with the text after `#`. Comments are visited after the
+    /// statement on the same line.
+    fn comment(&mut self, comment: &str) -> ObjResult<()> {
+        Ok(())
+    }
+}
 
-    let mut positions = Vec::new();
-    let mut tex_coords = Vec::new();
-    let mut normals = Vec::new();
-    let mut param_vertices = Vec::new();
+/// Parses a wavefront `.obj` format, and passes each statement to the visitor instead of building
+/// a `RawObj`.
+pub fn visit_obj<T, V>(input: T, visitor: &mut V) -> ObjResult<()>
+where
+    T: BufRead,
+    V: ObjVisitor,
+{
+    visit_obj_with(input, &ParseOptions::default(), visitor)?;
+    Ok(())
+}
 
-    let mut points = Vec::new();
-    let mut lines = Vec::new();
-    let mut polygons = Vec::new();
+/// Parses a wavefront `.obj` format with the given options, and passes each statement to the
+/// visitor instead of building a `RawObj`.
+///
+/// Returns the statements which have been skipped in lenient mode.
+pub fn visit_obj_with<T, V>(
+    input: T,
+    options: &ParseOptions,
+    visitor: &mut V,
+) -> ObjResult<Vec<Warning>>
+where
+    T: BufRead,
+    V: ObjVisitor,
+{
+    visit_chunk(
+        Lexer::new(input).keep_comments(),
+        options,
+        [0; 4],
+        [0, 0],
+        &mut VertexCounts::default(),
+        visitor,
+        &mut None,
+    )
+}
 
-    let counter = Counter::new(&points, &lines, &polygons);
-    let mut group_builder = GroupBuilder::with_default(&counter, String::from("default"));
-    let mut mesh_builder = GroupBuilder::with_default(&counter, String::new());
-    let mut smoothing_builder = GroupBuilder::new(&counter);
-    let mut merging_builder = GroupBuilder::new(&counter);
+/// Parses a chunk of the input, whose relative indices refer to `vertices` vertex data, and to
+/// `free_forms` `curv2` and `surf` statements before the chunk in addition to the ones of the
+/// chunk.
+///
+/// The line of the last `curv`, `curv2` or `surf` statement of the chunk is stored in `started`.
+fn visit_chunk<T, S, V>(
+    lexer: Lexer<T>,
+    options: &ParseOptions,
+    vertices: [usize; 4],
+    free_forms: [usize; 2],
+    sink: &mut S,
+    visitor: &mut V,
+    started: &mut Option<usize>,
+) -> ObjResult<Vec<Warning>>
+where
+    T: BufRead,
+    S: VertexSink,
+    V: ObjVisitor,
+{
+    let [mut curves2, mut surfaces] = free_forms;
+    lex(lexer, options, |line, stmt, args: &[&str]| {
+        // Number of vertex data parsed so far, which relative indices refer to
+        let counts = sink.counts();
+        let positions = vertices[0] + counts[0];
+        let tex_coords = vertices[1] + counts[1];
+        let normals = vertices[2] + counts[2];
+        let param_vertices = vertices[3] + counts[3];
 
-    lex(input, |stmt, args: &[&str]| {
         match stmt {
             // Vertex data
-            "v" => positions.push(match parse_args(args)?[..] {
-                [x, y, z, w] => (x, y, z, w),
-                [x, y, z] => (x, y, z, 1.0),
-                _ => make_error!(WrongNumberOfArguments, "Expected 3 or 4 arguments"),
-            }),
-            "vt" => tex_coords.push(match parse_args(args)?[..] {
-                [u, v, w] => (u, v, w),
-                [u, v] => (u, v, 0.0),
-                [u] => (u, 0.0, 0.0),
-                _ => make_error!(WrongNumberOfArguments, "Expected 1, 2 or 3 arguments"),
-            }),
-            "vn" => normals.push(match parse_args(args)?[..] {
-                [x, y, z] => (x, y, z),
-                _ => make_error!(WrongNumberOfArguments, "Expected 3 arguments"),
-            }),
-            "vp" => param_vertices.push(match parse_args(args)?[..] {
-                [u, v, w] => (u, v, w),
-                [u, v] => (u, v, 1.0),
-                [u] => (u, 0.0, 1.0),
-                _ => make_error!(WrongNumberOfArguments, "Expected 1, 2 or 3 arguments"),
-            }),
+            "v" | "vt" | "vn" | "vp" => sink.vertex(line, stmt, args, visitor)?,
 
             // Free-form curve / surface attributes
-            // TODO: Use rational information
             "cstype" => {
-                let geometry = match args {
-                    ["rat", ty] => *ty,
-                    [ty] => *ty,
+                let (rational, basis) = match args {
+                    ["rat", ty] => (true, *ty),
+                    [ty] => (false, *ty),
                     _ => make_error!(WrongTypeOfArguments, "Expected 'rat xxx' or 'xxx' format"),
                 };
 
-                match geometry {
-                    "bmatrix" => unimplemented!(),
-                    "bezier" => unimplemented!(),
-                    "bspline" => unimplemented!(),
-                    "cardinal" => unimplemented!(),
-                    "taylor" => unimplemented!(),
+                let basis = match basis {
+                    "bmatrix" => Basis::BasisMatrix,
+                    "bezier" => Basis::Bezier,
+                    "bspline" => Basis::BSpline,
+                    "cardinal" => Basis::Cardinal,
+                    "taylor" => Basis::Taylor,
                     _ => make_error!(
                         WrongTypeOfArguments,
-                        "Expected one of 'bmatrix', 'bezier', 'bspline', 'cardinal' and 'taylor'"
+                        "Expected one of 'bmatrix', 'bezier', 'bspline', 'cardinal' and 'taylor'",
+                        basis
                     ),
-                }
+                };
+                visitor.cstype(rational, basis)?;
             }
-            "deg" => match parse_args(args)?[..] {
-                [_deg_u, _deg_v] => unimplemented!(),
-                [_deg_u] => unimplemented!(),
+            "deg" => match args {
+                [u] => visitor.degree(parse_token(u)?, None)?,
+                [u, v] => visitor.degree(parse_token(u)?, Some(parse_token(v)?))?,
+                _ => make_error!(WrongNumberOfArguments, "Expected 1 or 2 arguments"),
+            },
+            "bmat" => match args {
+                [direction, matrix @ ..] if !matrix.is_empty() => {
+                    visitor.basis_matrix(parse_direction(direction)?, &parse_args(matrix)?)?
+                }
+                _ => make_error!(WrongNumberOfArguments, "Expected at least 2 arguments"),
+            },
+            "step" => match args {
+                [u] => visitor.step(parse_token(u)?, None)?,
+                [u, v] => visitor.step(parse_token(u)?, Some(parse_token(v)?))?,
                 _ => make_error!(WrongNumberOfArguments, "Expected 1 or 2 arguments"),
             },
-            "bmat" => unimplemented!(),
-            "step" => unimplemented!(),
+            "stech" => visitor.surface_technique(surface_technique(args)?)?,
+            "ctech" => visitor.curve_technique(curve_technique(args)?)?,
 
             // Elements
             "p" => {
-                for v in args {
-                    let v = try_index(&positions, v)?;
-                    points.push(v);
+                let indices = args
+                    .iter()
+                    .map(|v| try_index(positions, v))
+                    .collect::<ObjResult<Vec<_>>>()?;
+                for index in indices {
+                    visitor.point(index)?;
                 }
             }
             "l" => match args {
@@ -171,12 +451,12 @@ pub fn parse_obj<T: BufRead>(input: T) -> ObjResult<RawObj> {
 
                     let line = parse_args! {
                         first, rest,
-                        [p] => Line::P[try_index(&positions, p)?],
-                        [p, t] => Line::PT[(try_index(&positions, p)?, try_index(&tex_coords, t)?)],
-                        ! => make_error!(WrongTypeOfArguments, "Unexpected vertex format, expected `#`, or `#/#`")
+                        [p] => Line::P[try_index(positions, p)?],
+                        [p, t] => Line::PT[(try_index(positions, p)?, try_index(tex_coords, t)?)],
+                        ! => WrongTypeOfArguments, "Unexpected vertex format, expected `#`, or `#/#`"
                     };
 
-                    lines.push(line);
+                    visitor.line(line)?;
                 }
             },
             "fo" | "f" => match args {
@@ -188,322 +468,2038 @@ pub fn parse_obj<T: BufRead>(input: T) -> ObjResult<RawObj> {
 
                     let polygon = parse_args! {
                         first, rest,
-                        [p] => Polygon::P[try_index(&positions, p)?],
-                        [p, t] => Polygon::PT[(try_index(&positions, p)?, try_index(&tex_coords, t)?)],
-                        [p, "", n] => Polygon::PN[(try_index(&positions, p)?, try_index(&normals, n)?)],
-                        [p, t, n] => Polygon::PTN[(try_index(&positions, p)?, try_index(&tex_coords, t)?, try_index(&normals, n)?)],
-                        ! => make_error!(WrongTypeOfArguments, "Unexpected vertex format, expected `#`, `#/#`, `#//#`, or `#/#/#`")
+                        [p] => Polygon::P[try_index(positions, p)?],
+                        [p, t] => Polygon::PT[(try_index(positions, p)?, try_index(tex_coords, t)?)],
+                        [p, "", n] => Polygon::PN[(try_index(positions, p)?, try_index(normals, n)?)],
+                        [p, t, n] => Polygon::PTN[(try_index(positions, p)?, try_index(tex_coords, t)?, try_index(normals, n)?)],
+                        ! => WrongTypeOfArguments, "Unexpected vertex format, expected `#`, `#/#`, `#//#`, or `#/#/#`"
                     };
 
-                    polygons.push(polygon);
+                    visitor.face(polygon)?;
                 }
             },
-            "curv" => unimplemented!(),
-            "curv2" => unimplemented!(),
-            "surf" => unimplemented!(),
-
-            // Free-form curve / surface body statements
-            "parm" => unimplemented!(),
-            "trim" => unimplemented!(),
-            "hole" => unimplemented!(),
-            "scrv" => unimplemented!(),
-            "sp" => unimplemented!(),
-            "end" => unimplemented!(),
-
-            // Connectivity between free-form surfaces
-            "con" => unimplemented!(),
+            "curv" => match args {
+                [start, end, control_points @ ..] if control_points.len() >= 2 => {
+                    let control_points = control_points
+                        .iter()
+                        .map(|v| try_index(positions, v))
+                        .collect::<ObjResult<Vec<_>>>()?;
+                    visitor.curve(parse_token(start)?, parse_token(end)?, &control_points)?;
+                    *started = Some(line);
+                }
+                _ => make_error!(WrongNumberOfArguments, "Expected at least 4 arguments"),
+            },
+            "curv2" => {
+                if args.len() < 2 {
+                    make_error!(WrongNumberOfArguments, "Expected at least 2 arguments")
+                }
 
-            // Grouping
-            "g" => match args {
-                [name] => group_builder.start((*name).to_string()),
-                _ => make_error!(
-                    WrongNumberOfArguments,
-                    "Expected group name parameter, but nothing has been supplied"
-                ),
+                let control_points = args
+                    .iter()
+                    .map(|v| try_index(param_vertices, v))
+                    .collect::<ObjResult<Vec<_>>>()?;
+                visitor.curve2(&control_points)?;
+                curves2 += 1;
+                *started = Some(line);
+            }
+            "surf" => match args {
+                [s0, s1, t0, t1, control_points @ ..] if !control_points.is_empty() => {
+                    let control_points = control_points
+                        .iter()
+                        .map(|v| surface_vertex(v, positions, tex_coords, normals))
+                        .collect::<ObjResult<Vec<_>>>()?;
+                    let s = (parse_token(s0)?, parse_token(s1)?);
+                    let t = (parse_token(t0)?, parse_token(t1)?);
+                    visitor.surface(s, t, &control_points)?;
+                    surfaces += 1;
+                    *started = Some(line);
+                }
+                _ => make_error!(WrongNumberOfArguments, "Expected at least 5 arguments"),
             },
-            "s" => match args {
-                ["off"] | ["0"] => smoothing_builder.end(),
-                [param] => smoothing_builder.start(param.parse()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected only 1 argument"),
+
+            // Free-form curve / surface body statements
+            "parm" => match args {
+                [direction, values @ ..] if values.len() >= 2 => {
+                    visitor.parameters(parse_direction(direction)?, &parse_args(values)?)?
+                }
+                _ => make_error!(WrongNumberOfArguments, "Expected at least 3 arguments"),
             },
-            "mg" => match args {
-                ["off"] | ["0"] => merging_builder.end(),
-                [param] => merging_builder.start(param.parse()?),
-                _ => make_error!(WrongNumberOfArguments, "Expected only 1 argument"),
+            "trim" => visitor.trim(&trim_curves(args, curves2)?)?,
+            "hole" => visitor.hole(&trim_curves(args, curves2)?)?,
+            "scrv" => visitor.special_curve(&trim_curves(args, curves2)?)?,
+            "sp" => {
+                if args.is_empty() {
+                    make_error!(WrongNumberOfArguments, "Expected at least 1 argument")
+                }
+
+                let points = args
+                    .iter()
+                    .map(|v| try_index(param_vertices, v))
+                    .collect::<ObjResult<Vec<_>>>()?;
+                visitor.special_points(&points)?;
+            }
+            "end" => match args {
+                [] => visitor.end()?,
+                _ => make_error!(WrongNumberOfArguments, "Expected no argument"),
             },
-            "o" => {
-                name = match args {
-                    [] => None,
-                    _ => Some(args.join(" ")),
-                    // TODO: "name a  b" will be parsed as "name a b"
+
+            // Connectivity between free-form surfaces
+            "con" => {
+                if args.len() != 8 {
+                    make_error!(WrongNumberOfArguments, "Expected 8 arguments")
                 }
+
+                // Each surface is followed by its curve, as in `trim` statements
+                let (first, second) = args.split_at(4);
+                let curves = [&first[1..], &second[1..]].concat();
+                let curves = trim_curves(&curves, curves2)?;
+                visitor.connection(&Connection {
+                    surfaces: [
+                        try_index(surfaces, first[0])?,
+                        try_index(surfaces, second[0])?,
+                    ],
+                    curves: [curves[0], curves[1]],
+                })?;
             }
 
+            // Grouping
+            "g" => visitor.group(group_names(args))?,
+            "s" => visitor.smoothing(group_number(args)?)?,
+            "mg" => visitor.merging(group_number(args)?)?,
+            "o" => visitor.object(object_name(args).as_deref())?,
+
             // Display / render attributes
-            "bevel" => unimplemented!(),
-            "c_interp" => unimplemented!(),
-            "d_interp" => unimplemented!(),
-            "lod" => unimplemented!(),
-            "usemtl" => match args {
-                [material] => mesh_builder.start((*material).to_string()),
-                _ => make_error!(WrongNumberOfArguments, "Expected only 1 argument"),
-            },
+            "bevel" | "c_interp" | "d_interp" | "lod" => make_error!(
+                UnsupportedStatement,
+                "Display and render attributes are not supported yet"
+            ),
+            "usemtl" => visitor.usemtl(material_name(args)?)?,
             "mtllib" => {
-                material_libraries.reserve(args.len());
                 for &path in args {
-                    material_libraries.push(path.to_string());
+                    visitor.mtllib(path)?;
                 }
             }
-            "shadow_obj" => unimplemented!(),
-            "trace_obj" => unimplemented!(),
-            "ctech" => unimplemented!(),
-            "stech" => unimplemented!(),
+            "shadow_obj" | "trace_obj" => make_error!(
+                UnsupportedStatement,
+                "Display and render attributes are not supported yet"
+            ),
+
+            // Comments
+            "#" => visitor.comment(args[0])?,
 
             // Unexpected statement
             _ => make_error!(UnexpectedStatement, "Received unknown statement"),
         }
 
         Ok(())
-    })?;
-
-    group_builder.end();
-    mesh_builder.end();
-    smoothing_builder.end();
-    merging_builder.end();
-
-    Ok(RawObj {
-        name,
-        material_libraries,
-
-        positions,
-        tex_coords,
-        normals,
-        param_vertices,
-
-        points,
-        lines,
-        polygons,
-
-        groups: group_builder.result,
-        meshes: mesh_builder.result,
-        smoothing_groups: smoothing_builder.result,
-        merging_groups: merging_builder.result,
     })
 }
 
-/// Splits a string with '/'.
-fn split_vertex_group(input: &str) -> Vec<&str> {
-    input.split('/').collect()
-}
-
-/// Counts current total count of parsed `points`, `lines` and `polygons`.
-struct Counter {
-    points: *const Vec<Point>,
-    lines: *const Vec<Line>,
-    polygons: *const Vec<Polygon>,
+/// Parses the direction of a `bmat` or `parm` statement.
+fn parse_direction(input: &str) -> ObjResult<Direction> {
+    match input {
+        "u" => Ok(Direction::U),
+        "v" => Ok(Direction::V),
+        _ => make_error!(WrongTypeOfArguments, "Expected 'u' or 'v'", input),
+    }
 }
 
-impl Counter {
-    /// Constructs a new `Counter`.
-    fn new(
-        points: *const Vec<Point>,
-        lines: *const Vec<Line>,
-        polygons: *const Vec<Polygon>,
-    ) -> Self {
-        Counter {
-            points,
-            lines,
-            polygons,
+/// Parses the arguments of a `stech` statement.
+fn surface_technique(args: &[&str]) -> ObjResult<SurfaceTechnique> {
+    let technique = match args {
+        ["cparma", u, v] => SurfaceTechnique::ParametricA {
+            u: parse_token(u)?,
+            v: parse_token(v)?,
+        },
+        ["cparmb", uv] => SurfaceTechnique::ParametricB(parse_token(uv)?),
+        ["cspace", length] => SurfaceTechnique::Spatial(parse_token(length)?),
+        ["curv", distance, angle] => SurfaceTechnique::Curvature {
+            distance: parse_token(distance)?,
+            angle: parse_token(angle)?,
+        },
+        ["cparma", ..] | ["curv", ..] => {
+            make_error!(WrongNumberOfArguments, "Expected 3 arguments")
         }
-    }
+        ["cparmb", ..] | ["cspace", ..] => {
+            make_error!(WrongNumberOfArguments, "Expected 2 arguments")
+        }
+        [technique, ..] => make_error!(
+            WrongTypeOfArguments,
+            "Expected one of 'cparma', 'cparmb', 'cspace' and 'curv'",
+            technique
+        ),
+        [] => make_error!(WrongNumberOfArguments, "Expected at least 1 argument"),
+    };
+    Ok(technique)
+}
 
-    /// Returns a current count of parsed `(points, lines, polygons)`.
-    fn get(&self) -> (usize, usize, usize) {
-        unsafe {
-            (
-                (*self.points).len(),
-                (*self.lines).len(),
-                (*self.polygons).len(),
-            )
+/// Parses the arguments of a `ctech` statement.
+fn curve_technique(args: &[&str]) -> ObjResult<CurveTechnique> {
+    let technique = match args {
+        ["cparm", resolution] => CurveTechnique::Parametric(parse_token(resolution)?),
+        ["cspace", length] => CurveTechnique::Spatial(parse_token(length)?),
+        ["curv", distance, angle] => CurveTechnique::Curvature {
+            distance: parse_token(distance)?,
+            angle: parse_token(angle)?,
+        },
+        ["cparm", ..] | ["cspace", ..] => {
+            make_error!(WrongNumberOfArguments, "Expected 2 arguments")
         }
-    }
+        ["curv", ..] => make_error!(WrongNumberOfArguments, "Expected 3 arguments"),
+        [technique, ..] => make_error!(
+            WrongTypeOfArguments,
+            "Expected one of 'cparm', 'cspace' and 'curv'",
+            technique
+        ),
+        [] => make_error!(WrongNumberOfArguments, "Expected at least 1 argument"),
+    };
+    Ok(technique)
 }
 
-/// Helper for creating `groups`, `meshes`, `smoothing_groups` and `merging_groups` member of
-/// `Obj`.
-struct GroupBuilder<'a, K> {
-    counter: &'a Counter,
-    /// `Some(K)` if some group has been started, `None` otherwise.
-    current: Option<K>,
-    result: HashMap<K, Group>,
+/// Parses a control vertex of a `surf` statement.
+fn surface_vertex(
+    input: &str,
+    positions: usize,
+    tex_coords: usize,
+    normals: usize,
+) -> ObjResult<SurfaceVertex> {
+    let vertex = match split_vertex_group(input)[..] {
+        [p] => (try_index(positions, p)?, None, None),
+        [p, t] => (
+            try_index(positions, p)?,
+            Some(try_index(tex_coords, t)?),
+            None,
+        ),
+        [p, "", n] => (try_index(positions, p)?, None, Some(try_index(normals, n)?)),
+        [p, t, n] => (
+            try_index(positions, p)?,
+            Some(try_index(tex_coords, t)?),
+            Some(try_index(normals, n)?),
+        ),
+        _ => make_error!(
+            WrongTypeOfArguments,
+            "Unexpected vertex format, expected `#`, `#/#`, `#//#`, or `#/#/#`",
+            input
+        ),
+    };
+    Ok(vertex)
 }
 
-impl<'a, K> GroupBuilder<'a, K>
-where
-    K: Clone + Eq + Hash,
-{
-    fn new(counter: &'a Counter) -> Self {
-        GroupBuilder {
-            counter,
-            current: None,
-            result: HashMap::new(),
-        }
+/// Parses the arguments of a `trim`, `hole` or `scrv` statement, whose curves refer to the
+/// `curves2` `curv2` statements so far.
+fn trim_curves(args: &[&str], curves2: usize) -> ObjResult<Vec<TrimCurve>> {
+    let curves = args.chunks_exact(3);
+    if args.is_empty() || !curves.remainder().is_empty() {
+        make_error!(WrongNumberOfArguments, "Expected a multiple of 3 arguments")
     }
 
-    fn with_default(counter: &'a Counter, default: K) -> Self {
-        let mut result = HashMap::with_capacity(1);
-        result.insert(default.clone(), Group::new((0, 0, 0)));
-
-        GroupBuilder {
-            counter,
-            current: Some(default),
-            result,
-        }
-    }
+    curves
+        .map(|curve| {
+            Ok(TrimCurve {
+                start: parse_token(curve[0])?,
+                end: parse_token(curve[1])?,
+                curve: try_index(curves2, curve[2])?,
+            })
+        })
+        .collect()
+}
 
-    /// Starts a group whose name is `input`.
-    fn start(&mut self, input: K) {
-        let count = self.counter.get();
-
-        match self.current {
-            // Started same group twice, do nothing
-            Some(ref current) if *current == input => return,
-            // Changing group
-            Some(ref mut current) => {
-                // Close the past group and start a new one
-                let past = mem::replace(current, input.clone());
-                match self.result.entry(past) {
-                    Entry::Vacant(_) => unreachable!(),
-                    Entry::Occupied(mut e) => {
-                        let was_empty_group = e.get_mut().end(count);
-                        // Remove the past group if the past group is empty
-                        if was_empty_group {
-                            e.remove();
-                        }
-                    }
-                }
+/// Parses a `v`, `vt`, `vn` or `vp` statement, and passes it to the visitor.
+pub(crate) fn visit_vertex<V: ObjVisitor>(
+    stmt: &str,
+    args: &[&str],
+    visitor: &mut V,
+) -> ObjResult<()> {
+    match stmt {
+        "v" => match parse_args(args)?[..] {
+            [x, y, z, w] => visitor.position(x, y, z, w),
+            [x, y, z] => visitor.position(x, y, z, 1.0),
+            // Vertex colors, which many tools write after the position
+            [x, y, z, w, r, g, b] => {
+                visitor.position(x, y, z, w)?;
+                visitor.color(r, g, b)
             }
-            // Starting a new group
-            None => self.current = Some(input.clone()),
-        }
-
-        self.result
-            .entry(input)
-            .and_modify(|e| e.start(count))
-            .or_insert_with(|| Group::new(count));
-    }
-
-    /// Ends a current group.
-    fn end(&mut self) {
-        match mem::replace(&mut self.current, None) {
-            // Closed group twice, do nothing
-            None => {}
-            // Closing a group
-            Some(current) => {
-                match self.result.entry(current) {
-                    Entry::Vacant(_) => unreachable!(),
-                    Entry::Occupied(mut e) => {
-                        let count = self.counter.get();
-                        let was_empty_group = e.get_mut().end(count);
-                        // Remove the past group if the past group is empty
-                        if was_empty_group {
-                            e.remove();
-                        }
-                    }
-                }
+            [x, y, z, r, g, b] => {
+                visitor.position(x, y, z, 1.0)?;
+                visitor.color(r, g, b)
             }
-        }
+            _ => make_error!(WrongNumberOfArguments, "Expected 3, 4, 6 or 7 arguments"),
+        },
+        "vt" => match parse_args(args)?[..] {
+            [u, v, w] => visitor.tex_coord(u, v, w),
+            [u, v] => visitor.tex_coord(u, v, 0.0),
+            [u] => visitor.tex_coord(u, 0.0, 0.0),
+            _ => make_error!(WrongNumberOfArguments, "Expected 1, 2 or 3 arguments"),
+        },
+        "vn" => match parse_args(args)?[..] {
+            [x, y, z] => visitor.normal(x, y, z),
+            _ => make_error!(WrongNumberOfArguments, "Expected 3 arguments"),
+        },
+        "vp" => match parse_args(args)?[..] {
+            [u, v, w] => visitor.param_vertex(u, v, w),
+            [u, v] => visitor.param_vertex(u, v, 1.0),
+            [u] => visitor.param_vertex(u, 0.0, 1.0),
+            _ => make_error!(WrongNumberOfArguments, "Expected 1, 2 or 3 arguments"),
+        },
+        _ => unreachable!(),
     }
 }
 
-/// Constant which is used to represent undefined bound of range.
-const UNDEFINED: usize = ::std::usize::MAX;
-
-impl Group {
-    fn new(count: (usize, usize, usize)) -> Self {
-        let mut ret = Group {
-            points: Vec::with_capacity(1),
-            lines: Vec::with_capacity(1),
-            polygons: Vec::with_capacity(1),
-        };
-        ret.start(count);
-        ret
+/// Returns the index of the kind of vertex data in `VertexSink::counts`.
+pub(crate) fn vertex_kind(stmt: &str) -> usize {
+    match stmt {
+        "v" => 0,
+        "vt" => 1,
+        "vn" => 2,
+        "vp" => 3,
+        _ => unreachable!(),
     }
+}
 
-    fn start(&mut self, count: (usize, usize, usize)) {
-        self.points.push(Range {
-            start: count.0,
-            end: UNDEFINED,
-        });
-        self.lines.push(Range {
-            start: count.1,
-            end: UNDEFINED,
-        });
-        self.polygons.push(Range {
-            start: count.2,
-            end: UNDEFINED,
-        })
+/// State of the parser at the beginning of a chunk of the input, so that chunks of a file can be
+/// parsed separately.
+#[derive(Clone, Debug)]
+pub(crate) struct Context {
+    /// Number of positions, texture coordinates, normals and parameter vertices before the chunk.
+    pub vertices: [usize; 4],
+    /// Groups which are active at the beginning of the chunk.
+    pub groups: Vec<String>,
+    /// Material which is active at the beginning of the chunk.
+    pub mesh: String,
+    /// Smoothing group which is active at the beginning of the chunk.
+    pub smoothing_group: Option<usize>,
+    /// Merging group which is active at the beginning of the chunk.
+    pub merging_group: Option<usize>,
+    /// Object which is active at the beginning of the chunk.
+    pub object: Option<String>,
+    /// Free-form attributes, and the curve or surface whose body continues in the chunk.
+    pub free_form: FreeFormState,
+}
+
+impl Default for Context {
+    /// State at the beginning of a file.
+    fn default() -> Self {
+        Context {
+            vertices: [0; 4],
+            groups: vec![String::from("default")],
+            mesh: String::new(),
+            smoothing_group: None,
+            merging_group: None,
+            object: None,
+            free_form: FreeFormState::default(),
+        }
     }
+}
 
-    /// Closes group, return true if self is empty
-    fn end(&mut self, count: (usize, usize, usize)) -> bool {
-        end(&mut self.points, count.0);
-        end(&mut self.lines, count.1);
-        end(&mut self.polygons, count.2);
+/// Handles `v`, `vt`, `vn` and `vp` statements.
+pub(crate) trait VertexSink {
+    /// Handles a vertex data statement on the given line.
+    fn vertex<V: ObjVisitor>(
+        &mut self,
+        line: usize,
+        stmt: &str,
+        args: &[&str],
+        visitor: &mut V,
+    ) -> ObjResult<()>;
 
-        fn end(vec: &mut Vec<Range>, end: usize) {
-            let last = vec.len() - 1;
-            assert_eq!(vec[last].end, UNDEFINED);
-            if vec[last].start != end {
-                vec[last].end = end;
-            } else {
-                vec.pop();
-            }
-        }
+    /// Number of positions, texture coordinates, normals and parameter vertices handled so far.
+    fn counts(&self) -> [usize; 4];
+}
 
-        self.points.is_empty() && self.lines.is_empty() && self.polygons.is_empty()
+/// `VertexSink` which parses vertex data, and counts them.
+#[derive(Default)]
+pub(crate) struct VertexCounts([usize; 4]);
+
+impl VertexSink for VertexCounts {
+    fn vertex<V: ObjVisitor>(
+        &mut self,
+        _: usize,
+        stmt: &str,
+        args: &[&str],
+        visitor: &mut V,
+    ) -> ObjResult<()> {
+        visit_vertex(stmt, args, visitor)?;
+        self.0[vertex_kind(stmt)] += 1;
+        Ok(())
+    }
+
+    fn counts(&self) -> [usize; 4] {
+        self.0
     }
 }
 
-/// Low-level Rust binding for `.obj` format.
-pub struct RawObj {
-    /// Name of the object.
-    pub name: Option<String>,
-    /// `.mtl` files which required by this object.
-    pub material_libraries: Vec<String>,
+/// Color of the vertices without color, in a model whose other vertices have colors.
+pub(crate) const DEFAULT_COLOR: (f32, f32, f32) = (1.0, 1.0, 1.0);
 
-    /// Position vectors of each vertex.
+/// Vertex data of `RawObj`.
+#[derive(Default)]
+pub(crate) struct VertexData {
     pub positions: Vec<(f32, f32, f32, f32)>,
-    /// Texture coordinates of each vertex.
+    /// Colors of the positions, which may be shorter than `positions` until `pad_colors` is
+    /// called.
+    pub colors: Vec<(f32, f32, f32)>,
     pub tex_coords: Vec<(f32, f32, f32)>,
-    /// Normal vectors of each vertex.
     pub normals: Vec<(f32, f32, f32)>,
-    /// Parametric vertices.
     pub param_vertices: Vec<(f32, f32, f32)>,
+}
 
-    /// Points which stores the index data of position vectors.
-    pub points: Vec<Point>,
-    /// Lines which store the index data of vectors.
-    pub lines: Vec<Line>,
-    /// Polygons which store the index data of vectors.
-    pub polygons: Vec<Polygon>,
+impl VertexData {
+    /// Number of positions, texture coordinates, normals and parameter vertices.
+    #[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+    pub fn counts(&self) -> [usize; 4] {
+        [
+            self.positions.len(),
+            self.tex_coords.len(),
+            self.normals.len(),
+            self.param_vertices.len(),
+        ]
+    }
 
-    /// Groups of multiple geometries.
-    pub groups: HashMap<String, Group>,
-    /// Geometries which consist in a same material.
-    pub meshes: HashMap<String, Group>,
-    /// Smoothing groups.
-    pub smoothing_groups: HashMap<usize, Group>,
-    /// Merging groups.
-    pub merging_groups: HashMap<usize, Group>,
+    /// Gives the default color to the positions without color, if any position has a color.
+    pub fn pad_colors(&mut self) {
+        if !self.colors.is_empty() {
+            self.colors.resize(self.positions.len(), DEFAULT_COLOR);
+        }
+    }
 }
 
-/// The `Point` type which stores the index of the position vector.
-pub type Point = usize;
+impl ObjVisitor for VertexData {
+    fn position(&mut self, x: f32, y: f32, z: f32, w: f32) -> ObjResult<()> {
+        self.positions.push((x, y, z, w));
+        Ok(())
+    }
 
-/// The `Line` type.
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum Line {
+    fn color(&mut self, r: f32, g: f32, b: f32) -> ObjResult<()> {
+        // The color belongs to the last position
+        self.colors.resize(self.positions.len() - 1, DEFAULT_COLOR);
+        self.colors.push((r, g, b));
+        Ok(())
+    }
+
+    fn tex_coord(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        self.tex_coords.push((u, v, w));
+        Ok(())
+    }
+
+    fn normal(&mut self, x: f32, y: f32, z: f32) -> ObjResult<()> {
+        self.normals.push((x, y, z));
+        Ok(())
+    }
+
+    fn param_vertex(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        self.param_vertices.push((u, v, w));
+        Ok(())
+    }
+}
+
+/// Result of parsing a chunk of the input.
+pub(crate) struct Chunk {
+    /// Ranges of the groups are relative to the beginning of the chunk.
+    pub raw: RawObj,
+    /// Whether the chunk has an `o` statement.
+    #[cfg_attr(not(any(feature = "rayon", feature = "tokio")), allow(dead_code))]
+    pub named: bool,
+    /// State of the parser at the end of the chunk.
+    pub end: Context,
+    /// Group statements of the chunk, which build the groups when the chunk is appended.
+    #[cfg_attr(not(any(feature = "rayon", feature = "tokio")), allow(dead_code))]
+    pub statements: GroupStatements,
+}
+
+/// Keys of the groups which are started by each group statement, and the number of elements
+/// before the statement. The ones which end the groups don't have any key.
+type Statements<K> = Vec<(Vec<K>, (usize, usize, usize))>;
+
+/// Group statements of each kind in a chunk, whose numbers of elements are counted from the
+/// beginning of the chunk.
+#[cfg_attr(not(any(feature = "rayon", feature = "tokio")), allow(dead_code))]
+pub(crate) struct GroupStatements {
+    groups: Statements<String>,
+    meshes: Statements<String>,
+    smoothing_groups: Statements<usize>,
+    merging_groups: Statements<usize>,
+    objects: Statements<String>,
+}
+
+/// Groups of each kind of the chunks which have been appended, whose last ranges are still open
+/// until the end of the file.
+///
+/// Ranges of the chunks can't be simply joined, since a group which is left and started again
+/// has two ranges even if nothing comes between them, so the statements are replayed instead.
+#[cfg(any(feature = "rayon", feature = "tokio"))]
+pub(crate) struct Groups {
+    groups: GroupBuilder<String>,
+    meshes: GroupBuilder<String>,
+    smoothing_groups: GroupBuilder<usize>,
+    merging_groups: GroupBuilder<usize>,
+    objects: GroupBuilder<String>,
+}
+
+#[cfg(any(feature = "rayon", feature = "tokio"))]
+impl Groups {
+    /// Groups which are active at the beginning of a file.
+    pub fn new() -> Self {
+        let context = Context::default();
+        let mut groups = GroupBuilder::new();
+        groups.start_all(context.groups, (0, 0, 0));
+        Groups {
+            groups,
+            meshes: GroupBuilder::with_default(context.mesh),
+            smoothing_groups: GroupBuilder::new(),
+            merging_groups: GroupBuilder::new(),
+            objects: GroupBuilder::new(),
+        }
+    }
+
+    /// Replays the group statements of a chunk, whose elements start at `offset`.
+    pub fn replay(&mut self, statements: GroupStatements, offset: (usize, usize, usize)) {
+        replay(&mut self.groups, statements.groups, offset);
+        replay(&mut self.meshes, statements.meshes, offset);
+        replay(
+            &mut self.smoothing_groups,
+            statements.smoothing_groups,
+            offset,
+        );
+        replay(&mut self.merging_groups, statements.merging_groups, offset);
+        replay(&mut self.objects, statements.objects, offset);
+
+        fn replay<K: Clone + Eq + Hash>(
+            builder: &mut GroupBuilder<K>,
+            statements: Statements<K>,
+            offset: (usize, usize, usize),
+        ) {
+            for (keys, count) in statements {
+                let count = (count.0 + offset.0, count.1 + offset.1, count.2 + offset.2);
+                builder.start_all(keys, count);
+            }
+        }
+    }
+
+    /// Ends the groups at the end of the file, and moves them into `raw`.
+    pub fn finish(self, raw: &mut RawObj) {
+        let count = (raw.points.len(), raw.lines.len(), raw.polygons.len());
+        let Groups {
+            mut groups,
+            mut meshes,
+            mut smoothing_groups,
+            mut merging_groups,
+            mut objects,
+        } = self;
+        groups.end(count);
+        meshes.end(count);
+        smoothing_groups.end(count);
+        merging_groups.end(count);
+        objects.end(count);
+
+        raw.groups = groups.result;
+        raw.meshes = meshes.result;
+        raw.smoothing_groups = smoothing_groups.result;
+        raw.merging_groups = merging_groups.result;
+        raw.objects = objects.result;
+    }
+}
+
+/// `ObjVisitor` which builds a `RawObj`.
+struct Builder {
+    name: Option<String>,
+    named: bool,
+    material_libraries: Vec<String>,
+    vertices: VertexData,
+
+    points: Vec<Point>,
+    lines: Vec<Line>,
+    polygons: Vec<Polygon>,
+
+    groups: GroupBuilder<String>,
+    meshes: GroupBuilder<String>,
+    smoothing_groups: GroupBuilder<usize>,
+    merging_groups: GroupBuilder<usize>,
+    objects: GroupBuilder<String>,
+
+    free_form: FreeFormState,
+    free_forms: Vec<FreeForm>,
+    connections: Vec<Connection>,
+}
+
+impl Builder {
+    /// Returns a current count of parsed `(points, lines, polygons)`.
+    fn count(&self) -> (usize, usize, usize) {
+        (self.points.len(), self.lines.len(), self.polygons.len())
+    }
+
+    /// Returns the material which is active.
+    fn material(&self) -> String {
+        self.meshes.current.first().cloned().unwrap_or_default()
+    }
+}
+
+impl ObjVisitor for Builder {
+    fn position(&mut self, x: f32, y: f32, z: f32, w: f32) -> ObjResult<()> {
+        self.vertices.position(x, y, z, w)
+    }
+
+    fn color(&mut self, r: f32, g: f32, b: f32) -> ObjResult<()> {
+        self.vertices.color(r, g, b)
+    }
+
+    fn tex_coord(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        self.vertices.tex_coord(u, v, w)
+    }
+
+    fn normal(&mut self, x: f32, y: f32, z: f32) -> ObjResult<()> {
+        self.vertices.normal(x, y, z)
+    }
+
+    fn param_vertex(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        self.vertices.param_vertex(u, v, w)
+    }
+
+    fn point(&mut self, point: Point) -> ObjResult<()> {
+        self.points.push(point);
+        Ok(())
+    }
+
+    fn line(&mut self, line: Line) -> ObjResult<()> {
+        self.lines.push(line);
+        Ok(())
+    }
+
+    fn face(&mut self, polygon: Polygon) -> ObjResult<()> {
+        self.polygons.push(polygon);
+        Ok(())
+    }
+
+    fn group(&mut self, names: &[&str]) -> ObjResult<()> {
+        let names = names.iter().map(|name| name.to_string()).collect();
+        self.groups.start_all(names, self.count());
+        Ok(())
+    }
+
+    fn usemtl(&mut self, material: &str) -> ObjResult<()> {
+        self.meshes.start(material.to_string(), self.count());
+        Ok(())
+    }
+
+    fn mtllib(&mut self, path: &str) -> ObjResult<()> {
+        self.material_libraries.push(path.to_string());
+        Ok(())
+    }
+
+    fn smoothing(&mut self, group: Option<usize>) -> ObjResult<()> {
+        match group {
+            Some(id) => self.smoothing_groups.start(id, self.count()),
+            None => self.smoothing_groups.end(self.count()),
+        }
+        Ok(())
+    }
+
+    fn merging(&mut self, group: Option<usize>) -> ObjResult<()> {
+        match group {
+            Some(id) => self.merging_groups.start(id, self.count()),
+            None => self.merging_groups.end(self.count()),
+        }
+        Ok(())
+    }
+
+    fn object(&mut self, name: Option<&str>) -> ObjResult<()> {
+        self.named = true;
+        self.name = name.map(str::to_string);
+        match name {
+            Some(name) => self.objects.start(name.to_string(), self.count()),
+            None => self.objects.end(self.count()),
+        }
+        Ok(())
+    }
+
+    fn cstype(&mut self, rational: bool, basis: Basis) -> ObjResult<()> {
+        self.free_form.cstype(rational, basis);
+        Ok(())
+    }
+
+    fn degree(&mut self, u: usize, v: Option<usize>) -> ObjResult<()> {
+        self.free_form.degree(u, v);
+        Ok(())
+    }
+
+    fn basis_matrix(&mut self, direction: Direction, matrix: &[f32]) -> ObjResult<()> {
+        self.free_form.basis_matrix(direction, matrix);
+        Ok(())
+    }
+
+    fn step(&mut self, u: usize, v: Option<usize>) -> ObjResult<()> {
+        self.free_form.step(u, v);
+        Ok(())
+    }
+
+    fn surface_technique(&mut self, technique: SurfaceTechnique) -> ObjResult<()> {
+        self.free_form.surface_technique(technique);
+        Ok(())
+    }
+
+    fn curve_technique(&mut self, technique: CurveTechnique) -> ObjResult<()> {
+        self.free_form.curve_technique(technique);
+        Ok(())
+    }
+
+    fn curve(&mut self, start: f32, end: f32, control_points: &[usize]) -> ObjResult<()> {
+        let geometry = Geometry::Curve {
+            start,
+            end,
+            control_points: control_points.to_vec(),
+        };
+        self.free_form.start(geometry, self.material())
+    }
+
+    fn curve2(&mut self, control_points: &[usize]) -> ObjResult<()> {
+        let geometry = Geometry::Curve2 {
+            control_points: control_points.to_vec(),
+        };
+        self.free_form.start(geometry, self.material())
+    }
+
+    fn surface(
+        &mut self,
+        s: (f32, f32),
+        t: (f32, f32),
+        control_points: &[SurfaceVertex],
+    ) -> ObjResult<()> {
+        let geometry = Geometry::Surface {
+            s,
+            t,
+            control_points: control_points.to_vec(),
+        };
+        self.free_form.start(geometry, self.material())
+    }
+
+    fn parameters(&mut self, direction: Direction, values: &[f32]) -> ObjResult<()> {
+        self.free_form.body()?.parameters[direction as usize] = values.to_vec();
+        Ok(())
+    }
+
+    fn trim(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        let curves = self.free_form.trim_curves(curves)?;
+        self.free_form.body()?.trims.push(curves);
+        Ok(())
+    }
+
+    fn hole(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        let curves = self.free_form.trim_curves(curves)?;
+        self.free_form.body()?.holes.push(curves);
+        Ok(())
+    }
+
+    fn special_curve(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        let curves = self.free_form.trim_curves(curves)?;
+        self.free_form.body()?.special_curves.push(curves);
+        Ok(())
+    }
+
+    fn special_points(&mut self, points: &[usize]) -> ObjResult<()> {
+        self.free_form.body()?.special_points.extend(points);
+        Ok(())
+    }
+
+    fn end(&mut self) -> ObjResult<()> {
+        let free_form = self.free_form.end()?;
+        self.free_forms.push(free_form);
+        Ok(())
+    }
+
+    fn connection(&mut self, connection: &Connection) -> ObjResult<()> {
+        let connection = self.free_form.connection(connection)?;
+        self.connections.push(connection);
+        Ok(())
+    }
+}
+
+/// Parses a chunk of the input, which starts with the given state.
+pub(crate) fn parse_chunk<T, S>(
+    lexer: Lexer<T>,
+    options: &ParseOptions,
+    context: &Context,
+    sink: &mut S,
+) -> ObjResult<Chunk>
+where
+    T: BufRead,
+    S: VertexSink,
+{
+    let mut builder = Builder {
+        name: None,
+        named: false,
+        material_libraries: Vec::new(),
+        vertices: VertexData::default(),
+
+        points: Vec::new(),
+        lines: Vec::new(),
+        polygons: Vec::new(),
+
+        groups: GroupBuilder::new(),
+        meshes: GroupBuilder::with_default(context.mesh.clone()),
+        smoothing_groups: GroupBuilder::new(),
+        merging_groups: GroupBuilder::new(),
+        objects: GroupBuilder::new(),
+
+        free_form: context.free_form.clone(),
+        free_forms: Vec::new(),
+        connections: Vec::new(),
+    };
+    let start = builder.count();
+    builder.groups.start_all(context.groups.clone(), start);
+    if let Some(id) = context.smoothing_group {
+        builder.smoothing_groups.start(id, start);
+    }
+    if let Some(id) = context.merging_group {
+        builder.merging_groups.start(id, start);
+    }
+    if let Some(name) = &context.object {
+        builder.objects.start(name.clone(), start);
+    }
+    builder.groups.statements = Some(Vec::new());
+    builder.meshes.statements = Some(Vec::new());
+    builder.smoothing_groups.statements = Some(Vec::new());
+    builder.merging_groups.statements = Some(Vec::new());
+    builder.objects.statements = Some(Vec::new());
+
+    let mut started = None;
+    let warnings = visit_chunk(
+        lexer,
+        options,
+        context.vertices,
+        [context.free_form.curves2(), context.free_form.surfaces()],
+        sink,
+        &mut builder,
+        &mut started,
+    )?;
+    if let Some(line) = started {
+        builder.free_form.started_at(line);
+    }
+
+    let count = builder.count();
+    let Builder {
+        name,
+        named,
+        material_libraries,
+        mut vertices,
+        points,
+        lines,
+        polygons,
+        mut groups,
+        mut meshes,
+        mut smoothing_groups,
+        mut merging_groups,
+        mut objects,
+        free_form,
+        free_forms,
+        connections,
+    } = builder;
+
+    let mut vertex_counts = context.vertices;
+    for (total, count) in vertex_counts.iter_mut().zip(&sink.counts()) {
+        *total += count;
+    }
+    let end = Context {
+        vertices: vertex_counts,
+        groups: groups.current.clone(),
+        mesh: meshes.current.first().cloned().unwrap_or_default(),
+        smoothing_group: smoothing_groups.current.first().copied(),
+        merging_group: merging_groups.current.first().copied(),
+        object: objects.current.first().cloned(),
+        free_form,
+    };
+
+    let statements = GroupStatements {
+        groups: groups.statements.take().unwrap_or_default(),
+        meshes: meshes.statements.take().unwrap_or_default(),
+        smoothing_groups: smoothing_groups.statements.take().unwrap_or_default(),
+        merging_groups: merging_groups.statements.take().unwrap_or_default(),
+        objects: objects.statements.take().unwrap_or_default(),
+    };
+
+    groups.end(count);
+    meshes.end(count);
+    smoothing_groups.end(count);
+    merging_groups.end(count);
+    objects.end(count);
+    let (groups, meshes) = (groups.result, meshes.result);
+    let (smoothing_groups, merging_groups) = (smoothing_groups.result, merging_groups.result);
+    let objects = objects.result;
+
+    vertices.pad_colors();
+    let raw = RawObj {
+        name,
+        material_libraries,
+
+        positions: vertices.positions,
+        colors: vertices.colors,
+        tex_coords: vertices.tex_coords,
+        normals: vertices.normals,
+        param_vertices: vertices.param_vertices,
+
+        points,
+        lines,
+        polygons,
+
+        groups,
+        meshes,
+        smoothing_groups,
+        merging_groups,
+        objects,
+
+        free_forms,
+        connections,
+
+        warnings,
+    };
+    Ok(Chunk {
+        raw,
+        named,
+        end,
+        statements,
+    })
+}
+
+/// Parses the arguments of a `g` statement, which starts the `default` group if there's none.
+pub(crate) fn group_names<'a, 'b>(args: &'b [&'a str]) -> &'b [&'a str] {
+    match args {
+        [] => &["default"],
+        _ => args,
+    }
+}
+
+/// Parses the arguments of a `usemtl` statement.
+pub(crate) fn material_name<'a>(args: &[&'a str]) -> ObjResult<&'a str> {
+    match args {
+        [material] => Ok(material),
+        _ => make_error!(WrongNumberOfArguments, "Expected only 1 argument"),
+    }
+}
+
+/// Parses the arguments of an `o` statement, `None` if the name is omitted.
+pub(crate) fn object_name(args: &[&str]) -> Option<String> {
+    match args {
+        [] => None,
+        _ => Some(args.join(" ")),
+        // TODO: "name a  b" will be parsed as "name a b"
+    }
+}
+
+/// Parses the arguments of a `s` or `mg` statement, `None` for `off`.
+pub(crate) fn group_number(args: &[&str]) -> ObjResult<Option<usize>> {
+    match args {
+        ["off"] | ["0"] => Ok(None),
+        [param] => Ok(Some(parse_token(param)?)),
+        _ => make_error!(WrongNumberOfArguments, "Expected only 1 argument"),
+    }
+}
+
+/// Writes a `RawObj` in wavefront `.obj` format.
+///
+/// Points, lines and polygons are written in sections, each element with the `o`, `g`, `usemtl`,
+/// `s` and `mg` statements needed to restore its groups. Free-form curves and surfaces follow
+/// them, and connections between surfaces come last. A group which has two ranges next to each
+/// other is ended and started again between them, with `o`, `s off`, `mg off`, or a `g` or
+/// `usemtl` statement which starts an unused group.
+///
+/// Parsing the output gives back the same `RawObj`, except for `warnings`, and except for one
+/// known limitation: elements without material are written before the others, points first, then
+/// lines, then polygons. If they are of more than one kind, a group which continues from them to
+/// the elements with material may be ended and started again in between, and then comes back with
+/// two ranges next to each other.
+///
+/// ```rust
+/// use obj::raw::object::{parse_obj, write_obj};
+///
+/// let raw = parse_obj(&b"v 0 0 0\nv 1 0 0\nv 0 1 0\ng triangle\nf 1 2 3\n"[..])?;
+///
+/// let mut output = Vec::new();
+/// write_obj(&raw, &mut output)?;
+/// assert_eq!(output, b"v 0 0 0\nv 1 0 0\nv 0 1 0\ng triangle\nf 1 2 3\n");
+/// # Ok::<(), obj::ObjError>(())
+/// ```
+pub fn write_obj<W: Write>(obj: &RawObj, mut output: W) -> io::Result<()> {
+    let points = Labels::new(obj, obj.points.len(), |group| &group.points);
+    let lines = Labels::new(obj, obj.lines.len(), |group| &group.lines);
+    let polygons = Labels::new(obj, obj.polygons.len(), |group| &group.polygons);
+
+    // Elements without material can't follow the ones with material, because `usemtl` can't
+    // switch back to the unnamed material. Those are always at the beginning of each section
+    // when parsed, so they are written before everything else.
+    let unnamed = (points.unnamed(), lines.unnamed(), polygons.unnamed());
+    let ends = (obj.points.len(), obj.lines.len(), obj.polygons.len());
+
+    // The object of the first element is started before the vertex data, which don't belong to
+    // any object
+    let sections = [
+        (&points, 0..unnamed.0),
+        (&lines, 0..unnamed.1),
+        (&polygons, 0..unnamed.2),
+        (&points, unnamed.0..ends.0),
+        (&lines, unnamed.1..ends.1),
+        (&polygons, unnamed.2..ends.2),
+    ];
+    let first = sections
+        .iter()
+        .find(|(_, range)| !range.is_empty())
+        .and_then(|(labels, range)| labels.objects[range.start]);
+
+    let mut state = State::new();
+    if let Some(name) = first {
+        writeln!(output, "o {}", name)?;
+        state.object = Some(name);
+        state.named = true;
+    }
+    if !obj.material_libraries.is_empty() {
+        writeln!(output, "mtllib {}", obj.material_libraries.join(" "))?;
+    }
+
+    // Vertex data
+    for (i, &(x, y, z, w)) in obj.positions.iter().enumerate() {
+        write!(output, "v {} {} {}", x, y, z)?;
+        if w != 1.0 {
+            write!(output, " {}", w)?;
+        }
+        if let Some(&(r, g, b)) = obj.colors.get(i) {
+            write!(output, " {} {} {}", r, g, b)?;
+        }
+        writeln!(output)?;
+    }
+    for &(u, v, w) in &obj.tex_coords {
+        if w == 0.0 {
+            writeln!(output, "vt {} {}", u, v)?;
+        } else {
+            writeln!(output, "vt {} {} {}", u, v, w)?;
+        }
+    }
+    for &(x, y, z) in &obj.normals {
+        writeln!(output, "vn {} {} {}", x, y, z)?;
+    }
+    for &(u, v, w) in &obj.param_vertices {
+        if w == 1.0 {
+            writeln!(output, "vp {} {}", u, v)?;
+        } else {
+            writeln!(output, "vp {} {} {}", u, v, w)?;
+        }
+    }
+
+    // Elements, followed by the free forms with the same kind of material
+    let free_forms = obj
+        .free_forms
+        .iter()
+        .take_while(|free_form| free_form.material.is_empty())
+        .count();
+    let sections = [
+        ((0, 0, 0), unnamed, 0..free_forms),
+        (unnamed, ends, free_forms..obj.free_forms.len()),
+    ];
+    let mut attributes = Attributes::new(&obj.free_forms);
+    for (start, end, free_forms) in sections.iter().cloned() {
+        for i in start.0..end.0 {
+            state.update(&mut output, &points, i)?;
+            writeln!(output, "p {}", obj.points[i] + 1)?;
+        }
+        for i in start.1..end.1 {
+            state.update(&mut output, &lines, i)?;
+            write_line(&mut output, &obj.lines[i])?;
+        }
+        for i in start.2..end.2 {
+            state.update(&mut output, &polygons, i)?;
+            write_polygon(&mut output, &obj.polygons[i])?;
+        }
+        for free_form in &obj.free_forms[free_forms] {
+            if !free_form.material.is_empty() && free_form.material != state.mesh {
+                writeln!(output, "usemtl {}", free_form.material)?;
+                state.mesh = &free_form.material;
+            }
+            attributes.write(&mut output, free_form)?;
+        }
+    }
+    for connection in &obj.connections {
+        attributes.write_connection(&mut output, connection)?;
+    }
+
+    // The name of the object is the one of the last `o` statement
+    let name = obj.name.as_deref();
+    if state.named && state.object != name || !state.named && name.is_some() {
+        write_object(&mut output, name)?;
+    }
+
+    Ok(())
+}
+
+fn write_object<W: Write>(output: &mut W, name: Option<&str>) -> io::Result<()> {
+    match name {
+        Some(name) => writeln!(output, "o {}", name),
+        None => writeln!(output, "o"),
+    }
+}
+
+fn write_line<W: Write>(output: &mut W, line: &Line) -> io::Result<()> {
+    write!(output, "l")?;
+    match line {
+        Line::P(vec) => {
+            for &p in vec {
+                write!(output, " {}", p + 1)?;
+            }
+        }
+        Line::PT(vec) => {
+            for &(p, t) in vec {
+                write!(output, " {}/{}", p + 1, t + 1)?;
+            }
+        }
+    }
+    writeln!(output)
+}
+
+fn write_polygon<W: Write>(output: &mut W, polygon: &Polygon) -> io::Result<()> {
+    write!(output, "f")?;
+    match polygon {
+        Polygon::P(vec) => {
+            for &p in vec {
+                write!(output, " {}", p + 1)?;
+            }
+        }
+        Polygon::PT(vec) => {
+            for &(p, t) in vec {
+                write!(output, " {}/{}", p + 1, t + 1)?;
+            }
+        }
+        Polygon::PN(vec) => {
+            for &(p, n) in vec {
+                write!(output, " {}//{}", p + 1, n + 1)?;
+            }
+        }
+        Polygon::PTN(vec) => {
+            for &(p, t, n) in vec {
+                write!(output, " {}/{}/{}", p + 1, t + 1, n + 1)?;
+            }
+        }
+    }
+    writeln!(output)
+}
+
+/// Free-form attributes which have been written so far.
+struct Attributes<'a> {
+    /// 1-based indices of the `curv2` statements, which trimming curves refer to.
+    curves2: Vec<usize>,
+    /// 1-based indices of the `surf` statements, which connections refer to.
+    surfaces: Vec<usize>,
+    cstype: Option<(bool, Basis)>,
+    degree: Option<[usize; 2]>,
+    basis_matrix: [&'a [f32]; 2],
+    step: [usize; 2],
+    technique: Option<SurfaceTechnique>,
+    curve_technique: Option<CurveTechnique>,
+}
+
+impl<'a> Attributes<'a> {
+    /// Attributes at the beginning of a file, which has the given free forms.
+    fn new(free_forms: &[FreeForm]) -> Self {
+        let mut curves2 = vec![0; free_forms.len()];
+        let mut surfaces = vec![0; free_forms.len()];
+        let mut counts = (0, 0);
+        for (i, free_form) in free_forms.iter().enumerate() {
+            match free_form.geometry {
+                Geometry::Curve2 { .. } => {
+                    counts.0 += 1;
+                    curves2[i] = counts.0;
+                }
+                Geometry::Surface { .. } => {
+                    counts.1 += 1;
+                    surfaces[i] = counts.1;
+                }
+                Geometry::Curve { .. } => {}
+            }
+        }
+
+        Attributes {
+            curves2,
+            surfaces,
+            cstype: None,
+            degree: None,
+            basis_matrix: [&[], &[]],
+            step: [0, 0],
+            technique: None,
+            curve_technique: None,
+        }
+    }
+
+    /// Writes a free-form curve or surface, with the `cstype`, `deg`, `bmat`, `step`, `stech` and
+    /// `ctech` statements which change its attributes.
+    fn write<W: Write>(&mut self, output: &mut W, free_form: &'a FreeForm) -> io::Result<()> {
+        if self.cstype != Some((free_form.rational, free_form.basis)) {
+            if free_form.rational {
+                writeln!(output, "cstype rat {}", free_form.basis.keyword())?;
+            } else {
+                writeln!(output, "cstype {}", free_form.basis.keyword())?;
+            }
+            self.cstype = Some((free_form.rational, free_form.basis));
+        }
+        if self.degree != Some(free_form.degree) {
+            match free_form.degree {
+                [u, 0] => writeln!(output, "deg {}", u)?,
+                [u, v] => writeln!(output, "deg {} {}", u, v)?,
+            }
+            self.degree = Some(free_form.degree);
+        }
+        for (i, matrix) in free_form.basis_matrix.iter().enumerate() {
+            // Basis matrices can't be unset once they've been given
+            if !matrix.is_empty() && self.basis_matrix[i] != &matrix[..] {
+                writeln!(output, "bmat {} {}", DIRECTIONS[i], join(matrix))?;
+                self.basis_matrix[i] = matrix;
+            }
+        }
+        if self.step != free_form.step {
+            match free_form.step {
+                [u, 0] => writeln!(output, "step {}", u)?,
+                [u, v] => writeln!(output, "step {} {}", u, v)?,
+            }
+            self.step = free_form.step;
+        }
+        if free_form.technique.is_some() && self.technique != free_form.technique {
+            match free_form.technique {
+                Some(SurfaceTechnique::ParametricA { u, v }) => {
+                    writeln!(output, "stech cparma {} {}", u, v)?
+                }
+                Some(SurfaceTechnique::ParametricB(uv)) => writeln!(output, "stech cparmb {}", uv)?,
+                Some(SurfaceTechnique::Spatial(length)) => {
+                    writeln!(output, "stech cspace {}", length)?
+                }
+                Some(SurfaceTechnique::Curvature { distance, angle }) => {
+                    writeln!(output, "stech curv {} {}", distance, angle)?
+                }
+                None => {}
+            }
+            self.technique = free_form.technique;
+        }
+        if free_form.curve_technique.is_some() && self.curve_technique != free_form.curve_technique
+        {
+            match free_form.curve_technique {
+                Some(CurveTechnique::Parametric(resolution)) => {
+                    writeln!(output, "ctech cparm {}", resolution)?
+                }
+                Some(CurveTechnique::Spatial(length)) => {
+                    writeln!(output, "ctech cspace {}", length)?
+                }
+                Some(CurveTechnique::Curvature { distance, angle }) => {
+                    writeln!(output, "ctech curv {} {}", distance, angle)?
+                }
+                None => {}
+            }
+            self.curve_technique = free_form.curve_technique;
+        }
+
+        match &free_form.geometry {
+            Geometry::Curve {
+                start,
+                end,
+                control_points,
+            } => {
+                write!(output, "curv {} {}", start, end)?;
+                for &p in control_points {
+                    write!(output, " {}", p + 1)?;
+                }
+            }
+            Geometry::Curve2 { control_points } => {
+                write!(output, "curv2")?;
+                for &p in control_points {
+                    write!(output, " {}", p + 1)?;
+                }
+            }
+            Geometry::Surface {
+                s,
+                t,
+                control_points,
+            } => {
+                write!(output, "surf {} {} {} {}", s.0, s.1, t.0, t.1)?;
+                for &vertex in control_points {
+                    match vertex {
+                        (p, None, None) => write!(output, " {}", p + 1)?,
+                        (p, Some(t), None) => write!(output, " {}/{}", p + 1, t + 1)?,
+                        (p, None, Some(n)) => write!(output, " {}//{}", p + 1, n + 1)?,
+                        (p, Some(t), Some(n)) => write!(output, " {}/{}/{}", p + 1, t + 1, n + 1)?,
+                    }
+                }
+            }
+        }
+        writeln!(output)?;
+
+        for (i, values) in free_form.parameters.iter().enumerate() {
+            if !values.is_empty() {
+                writeln!(output, "parm {} {}", DIRECTIONS[i], join(values))?;
+            }
+        }
+        let loops = [
+            ("trim", &free_form.trims),
+            ("hole", &free_form.holes),
+            ("scrv", &free_form.special_curves),
+        ];
+        for &(stmt, loops) in &loops {
+            for curves in loops {
+                write!(output, "{}", stmt)?;
+                for curve in curves {
+                    let index = self.curves2[curve.curve];
+                    write!(output, " {} {} {}", curve.start, curve.end, index)?;
+                }
+                writeln!(output)?;
+            }
+        }
+        if !free_form.special_points.is_empty() {
+            write!(output, "sp")?;
+            for &p in &free_form.special_points {
+                write!(output, " {}", p + 1)?;
+            }
+            writeln!(output)?;
+        }
+        writeln!(output, "end")
+    }
+
+    /// Writes a `con` statement, after all the free forms.
+    fn write_connection<W: Write>(
+        &self,
+        output: &mut W,
+        connection: &Connection,
+    ) -> io::Result<()> {
+        write!(output, "con")?;
+        for (&surface, curve) in connection.surfaces.iter().zip(&connection.curves) {
+            let (surface, index) = (self.surfaces[surface], self.curves2[curve.curve]);
+            write!(
+                output,
+                " {} {} {} {}",
+                surface, curve.start, curve.end, index
+            )?;
+        }
+        writeln!(output)
+    }
+}
+
+/// Keywords of the directions, indexed by `Direction`.
+const DIRECTIONS: [&str; 2] = ["u", "v"];
+
+/// Joins numbers with spaces.
+fn join(values: &[f32]) -> String {
+    let values: Vec<String> = values.iter().map(f32::to_string).collect();
+    values.join(" ")
+}
+
+/// Groups which each element of one kind belongs to.
+struct Labels<'a> {
+    groups: Vec<Vec<&'a str>>,
+    meshes: Vec<Option<&'a str>>,
+    smoothing_groups: Vec<Option<usize>>,
+    merging_groups: Vec<Option<usize>>,
+    objects: Vec<Option<&'a str>>,
+    /// Groups whose range starts at each element, right where another range of them ends.
+    splits: Splits<'a>,
+}
+
+/// Groups which have two ranges next to each other at each element, so the writer has to end
+/// and start them again there.
+struct Splits<'a> {
+    groups: Vec<Vec<&'a str>>,
+    meshes: Vec<bool>,
+    smoothing_groups: Vec<bool>,
+    merging_groups: Vec<bool>,
+    objects: Vec<bool>,
+}
+
+impl<'a> Labels<'a> {
+    fn new(obj: &'a RawObj, len: usize, ranges: fn(&Group) -> &Vec<Range>) -> Self {
+        let name = |name: Option<&'a String>| name.map(String::as_str);
+        Labels {
+            groups: label_all(&obj.groups, len, ranges),
+            meshes: label(&obj.meshes, len, ranges)
+                .into_iter()
+                .map(name)
+                .collect(),
+            smoothing_groups: label(&obj.smoothing_groups, len, ranges)
+                .into_iter()
+                .map(|id| id.copied())
+                .collect(),
+            merging_groups: label(&obj.merging_groups, len, ranges)
+                .into_iter()
+                .map(|id| id.copied())
+                .collect(),
+            objects: label(&obj.objects, len, ranges)
+                .into_iter()
+                .map(name)
+                .collect(),
+            splits: Splits {
+                groups: splits(&obj.groups, len, ranges)
+                    .into_iter()
+                    .map(|keys| keys.into_iter().map(String::as_str).collect())
+                    .collect(),
+                meshes: split(&obj.meshes, len, ranges),
+                smoothing_groups: split(&obj.smoothing_groups, len, ranges),
+                merging_groups: split(&obj.merging_groups, len, ranges),
+                objects: split(&obj.objects, len, ranges),
+            },
+        }
+    }
+
+    /// Returns the number of leading elements which don't have any material.
+    fn unnamed(&self) -> usize {
+        self.meshes
+            .iter()
+            .take_while(|mesh| mesh.unwrap_or_default().is_empty())
+            .count()
+    }
+}
+
+/// Returns the key of the group which each of `len` elements belongs to.
+fn label<K>(
+    groups: &IndexMap<K, Group>,
+    len: usize,
+    ranges: fn(&Group) -> &Vec<Range>,
+) -> Vec<Option<&K>> {
+    let mut labels = vec![None; len];
+    for (key, group) in groups {
+        for range in ranges(group) {
+            for label in &mut labels[range.start..range.end] {
+                label.get_or_insert(key);
+            }
+        }
+    }
+    labels
+}
+
+/// Returns the names of all the groups which each of `len` elements belongs to, in the order of
+/// the groups.
+fn label_all(
+    groups: &IndexMap<String, Group>,
+    len: usize,
+    ranges: fn(&Group) -> &Vec<Range>,
+) -> Vec<Vec<&str>> {
+    let mut labels = vec![Vec::new(); len];
+    for (key, group) in groups {
+        for range in ranges(group) {
+            for label in &mut labels[range.start..range.end] {
+                label.push(key.as_str());
+            }
+        }
+    }
+    labels
+}
+
+/// Returns the keys of the groups which have a range starting at each of `len` elements, right
+/// where another range of them ends.
+fn splits<K>(
+    groups: &IndexMap<K, Group>,
+    len: usize,
+    ranges: fn(&Group) -> &Vec<Range>,
+) -> Vec<Vec<&K>> {
+    let mut splits = vec![Vec::new(); len];
+    for (key, group) in groups {
+        for pair in ranges(group).windows(2) {
+            if pair[0].end == pair[1].start && pair[1].start < len {
+                splits[pair[1].start].push(key);
+            }
+        }
+    }
+    splits
+}
+
+/// Returns whether any group has a range starting at each of `len` elements, right where another
+/// range of it ends.
+fn split<K>(
+    groups: &IndexMap<K, Group>,
+    len: usize,
+    ranges: fn(&Group) -> &Vec<Range>,
+) -> Vec<bool> {
+    splits(groups, len, ranges)
+        .into_iter()
+        .map(|keys| !keys.is_empty())
+        .collect()
+}
+
+/// Groups which have been started by the statements written so far.
+struct State<'a> {
+    groups: Vec<&'a str>,
+    mesh: &'a str,
+    smoothing_group: Option<usize>,
+    merging_group: Option<usize>,
+    object: Option<&'a str>,
+    /// Whether any `o` statement has been written.
+    named: bool,
+}
+
+impl<'a> State<'a> {
+    /// State at the beginning of a file.
+    fn new() -> Self {
+        State {
+            groups: vec!["default"],
+            mesh: "",
+            smoothing_group: None,
+            merging_group: None,
+            object: None,
+            named: false,
+        }
+    }
+
+    /// Writes the statements which start the groups of the `i`-th element.
+    fn update<W: Write>(
+        &mut self,
+        output: &mut W,
+        labels: &Labels<'a>,
+        i: usize,
+    ) -> io::Result<()> {
+        // Groups which have two ranges next to each other are ended before they are started again
+        let splits = &labels.splits;
+        if splits.objects[i] && self.object.is_some() && labels.objects[i] == self.object {
+            write_object(output, None)?;
+            self.object = None;
+        }
+        if labels.objects[i] != self.object {
+            write_object(output, labels.objects[i])?;
+            self.object = labels.objects[i];
+            self.named = true;
+        }
+
+        let groups = match &labels.groups[i][..] {
+            [] => &["default"][..],
+            groups => groups,
+        };
+        let split: Vec<_> = splits.groups[i]
+            .iter()
+            .filter(|&name| self.groups.contains(name) && groups.contains(name))
+            .collect();
+        if !split.is_empty() {
+            let rest: Vec<_> = self
+                .groups
+                .iter()
+                .copied()
+                .filter(|name| !split.contains(&name))
+                .collect();
+            if rest.is_empty() {
+                writeln!(output, "g {}", unused_name(&split))?;
+            } else {
+                writeln!(output, "g {}", rest.join(" "))?;
+            }
+            self.groups = rest;
+        }
+        if groups != &self.groups[..] {
+            writeln!(output, "g {}", groups.join(" "))?;
+            self.groups = groups.to_vec();
+        }
+
+        match labels.meshes[i] {
+            Some(mesh) if splits.meshes[i] && !mesh.is_empty() && mesh == self.mesh => {
+                writeln!(output, "usemtl {}", unused_name(&[&mesh]))?;
+                writeln!(output, "usemtl {}", mesh)?;
+            }
+            Some(mesh) if !mesh.is_empty() && mesh != self.mesh => {
+                writeln!(output, "usemtl {}", mesh)?;
+                self.mesh = mesh;
+            }
+            // The unnamed material can't be started again once another one has been used
+            _ => {}
+        }
+
+        if splits.smoothing_groups[i]
+            && self.smoothing_group.is_some()
+            && labels.smoothing_groups[i] == self.smoothing_group
+        {
+            writeln!(output, "s off")?;
+            self.smoothing_group = None;
+        }
+        if labels.smoothing_groups[i] != self.smoothing_group {
+            match labels.smoothing_groups[i] {
+                Some(id) => writeln!(output, "s {}", id)?,
+                None => writeln!(output, "s off")?,
+            }
+            self.smoothing_group = labels.smoothing_groups[i];
+        }
+
+        if splits.merging_groups[i]
+            && self.merging_group.is_some()
+            && labels.merging_groups[i] == self.merging_group
+        {
+            writeln!(output, "mg off")?;
+            self.merging_group = None;
+        }
+        if labels.merging_groups[i] != self.merging_group {
+            match labels.merging_groups[i] {
+                Some(id) => writeln!(output, "mg {}", id)?,
+                None => writeln!(output, "mg off")?,
+            }
+            self.merging_group = labels.merging_groups[i];
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a name which isn't any of `names`, for a group which is started only to end others.
+///
+/// The group doesn't get any element, so it's left out when the output is parsed.
+fn unused_name(names: &[&&str]) -> String {
+    let mut name = String::from("default");
+    while names.iter().any(|&&other| other == name) {
+        name.push('_');
+    }
+    name
+}
+
+/// Splits a string with '/'.
+fn split_vertex_group(input: &str) -> SmallVec<&str, 3> {
+    input.split('/').collect()
+}
+
+/// Helper for creating `groups`, `meshes`, `smoothing_groups`, `merging_groups` and `objects`
+/// member of `Obj`.
+///
+/// Every method takes the current count of parsed `(points, lines, polygons)`, where the ranges of
+/// the groups start or end.
+struct GroupBuilder<K> {
+    /// Keys of the groups which have been started. Only `groups` can have several of them.
+    current: Vec<K>,
+    result: IndexMap<K, Group>,
+    /// Group statements which are recorded in chunks, if any.
+    statements: Option<Statements<K>>,
+}
+
+impl<K> GroupBuilder<K>
+where
+    K: Clone + Eq + Hash,
+{
+    fn new() -> Self {
+        GroupBuilder {
+            current: Vec::with_capacity(1),
+            result: IndexMap::new(),
+            statements: None,
+        }
+    }
+
+    fn with_default(default: K) -> Self {
+        let mut result = IndexMap::with_capacity(1);
+        result.insert(default.clone(), Group::new((0, 0, 0)));
+
+        GroupBuilder {
+            current: vec![default],
+            result,
+            statements: None,
+        }
+    }
+
+    /// Starts a group whose name is `input`, and ends the other ones.
+    fn start(&mut self, input: K, count: (usize, usize, usize)) {
+        // Started same group twice, do nothing
+        if self.current.len() == 1 && self.current[0] == input {
+            return;
+        }
+        self.start_all(vec![input], count);
+    }
+
+    /// Starts the groups whose names are `inputs`, and ends the other ones.
+    fn start_all(&mut self, inputs: Vec<K>, count: (usize, usize, usize)) {
+        if let Some(statements) = &mut self.statements {
+            statements.push((inputs.clone(), count));
+        }
+
+        // Close the past groups which aren't started again
+        let past = mem::take(&mut self.current);
+        for key in &past {
+            if !inputs.contains(key) {
+                self.close(key.clone(), count);
+            }
+        }
+
+        for input in inputs {
+            // Named twice in the same statement
+            if self.current.contains(&input) {
+                continue;
+            }
+            // Groups which are already active just continue
+            if !past.contains(&input) {
+                self.result
+                    .entry(input.clone())
+                    .and_modify(|e| e.start(count))
+                    .or_insert_with(|| Group::new(count));
+            }
+            self.current.push(input);
+        }
+    }
+
+    /// Ends current groups.
+    fn end(&mut self, count: (usize, usize, usize)) {
+        if let Some(statements) = &mut self.statements {
+            statements.push((Vec::new(), count));
+        }
+        for current in mem::take(&mut self.current) {
+            self.close(current, count);
+        }
+    }
+
+    /// Closes the range of a group, and removes the group if it's empty.
+    fn close(&mut self, key: K, count: (usize, usize, usize)) {
+        match self.result.entry(key) {
+            Entry::Vacant(_) => unreachable!(),
+            Entry::Occupied(mut e) => {
+                let was_empty_group = e.get_mut().end(count);
+                // Remove the past group if the past group is empty, keeping the order of the others
+                if was_empty_group {
+                    e.shift_remove();
+                }
+            }
+        }
+    }
+}
+
+/// Constant which is used to represent undefined bound of range.
+const UNDEFINED: usize = usize::MAX;
+
+impl Group {
+    fn new(count: (usize, usize, usize)) -> Self {
+        let mut ret = Group {
+            points: Vec::with_capacity(1),
+            lines: Vec::with_capacity(1),
+            polygons: Vec::with_capacity(1),
+        };
+        ret.start(count);
+        ret
+    }
+
+    fn start(&mut self, count: (usize, usize, usize)) {
+        start(&mut self.points, count.0);
+        start(&mut self.lines, count.1);
+        start(&mut self.polygons, count.2);
+
+        fn start(vec: &mut Vec<Range>, start: usize) {
+            vec.push(Range {
+                start,
+                end: UNDEFINED,
+            });
+        }
+    }
+
+    /// Closes group, return true if self is empty
+    fn end(&mut self, count: (usize, usize, usize)) -> bool {
+        end(&mut self.points, count.0);
+        end(&mut self.lines, count.1);
+        end(&mut self.polygons, count.2);
+
+        fn end(vec: &mut Vec<Range>, end: usize) {
+            let last = vec.len() - 1;
+            assert_eq!(vec[last].end, UNDEFINED);
+            if vec[last].start != end {
+                vec[last].end = end;
+            } else {
+                vec.pop();
+            }
+        }
+
+        self.points.is_empty() && self.lines.is_empty() && self.polygons.is_empty()
+    }
+}
+
+/// Low-level Rust binding for `.obj` format.
+///
+/// Groups, meshes, smoothing groups, merging groups and objects are kept in the order of their
+/// first element, not in the order of their declaration. A group which is declared first but gets
+/// its first element after another group comes after it, and a group without any element is left
+/// out.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RawObj {
+    /// Name of the object.
+    pub name: Option<String>,
+    /// `.mtl` files which required by this object.
+    pub material_libraries: Vec<String>,
+
+    /// Position vectors of each vertex.
+    pub positions: Vec<(f32, f32, f32, f32)>,
+    /// Colors of each position, from `v x y z r g b` statements. It's empty if no position has a
+    /// color. Otherwise, positions without color are white.
+    pub colors: Vec<(f32, f32, f32)>,
+    /// Texture coordinates of each vertex.
+    pub tex_coords: Vec<(f32, f32, f32)>,
+    /// Normal vectors of each vertex.
+    pub normals: Vec<(f32, f32, f32)>,
+    /// Parametric vertices.
+    pub param_vertices: Vec<(f32, f32, f32)>,
+
+    /// Points which stores the index data of position vectors.
+    pub points: Vec<Point>,
+    /// Lines which store the index data of vectors.
+    pub lines: Vec<Line>,
+    /// Polygons which store the index data of vectors.
+    pub polygons: Vec<Polygon>,
+
+    /// Groups of multiple geometries.
+    pub groups: IndexMap<String, Group>,
+    /// Geometries which consist in a same material.
+    pub meshes: IndexMap<String, Group>,
+    /// Smoothing groups.
+    pub smoothing_groups: IndexMap<usize, Group>,
+    /// Merging groups.
+    pub merging_groups: IndexMap<usize, Group>,
+    /// Objects started with `o` statements. Elements before the first `o` statement, or after
+    /// an `o` statement without a name, don't belong to any object.
+    pub objects: IndexMap<String, Group>,
+
+    /// Free-form curves and surfaces, in the order of the file. They don't belong to any group.
+    pub free_forms: Vec<FreeForm>,
+    /// Edges which surfaces of `free_forms` share, given by `con` statements.
+    pub connections: Vec<Connection>,
+
+    /// Statements which have been skipped in lenient mode.
+    pub warnings: Vec<Warning>,
+}
+
+impl RawObj {
+    /// Extracts the elements of the given object into a `RawObj` of its own, or returns `None`
+    /// if there's no such object.
+    ///
+    /// Only the vertex data which the elements refer to are kept, in the order in which they are
+    /// first referred to. Groups are narrowed down to the elements of the object. Free-form curves
+    /// and surfaces, their connections, and warnings are not copied.
+    ///
+    /// ```rust
+    /// use obj::raw::parse_obj;
+    ///
+    /// let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\no first\np 1\no second\nl 2 3\n";
+    /// let raw = parse_obj(&input[..])?;
+    ///
+    /// let second = raw.object("second").unwrap();
+    /// assert_eq!(second.positions, [(1.0, 0.0, 0.0, 1.0), (0.0, 1.0, 0.0, 1.0)]);
+    /// assert_eq!(second.points.len(), 0);
+    /// assert_eq!(second.lines.len(), 1);
+    /// # Ok::<(), obj::ObjError>(())
+    /// ```
+    pub fn object(&self, name: &str) -> Option<RawObj> {
+        let object = self.objects.get(name)?;
+
+        let mut positions = Renumber::new(self.positions.len());
+        let mut tex_coords = Renumber::new(self.tex_coords.len());
+        let mut normals = Renumber::new(self.normals.len());
+
+        let points = select(&self.points, &object.points)
+            .map(|&p| positions.get(p))
+            .collect();
+        let lines = select(&self.lines, &object.lines)
+            .map(|line| match line {
+                Line::P(vec) => Line::P(vec.iter().map(|&p| positions.get(p)).collect()),
+                Line::PT(vec) => Line::PT(
+                    vec.iter()
+                        .map(|&(p, t)| (positions.get(p), tex_coords.get(t)))
+                        .collect(),
+                ),
+            })
+            .collect();
+        let polygons = select(&self.polygons, &object.polygons)
+            .map(|polygon| match polygon {
+                Polygon::P(vec) => Polygon::P(vec.iter().map(|&p| positions.get(p)).collect()),
+                Polygon::PT(vec) => Polygon::PT(
+                    vec.iter()
+                        .map(|&(p, t)| (positions.get(p), tex_coords.get(t)))
+                        .collect(),
+                ),
+                Polygon::PN(vec) => Polygon::PN(
+                    vec.iter()
+                        .map(|&(p, n)| (positions.get(p), normals.get(n)))
+                        .collect(),
+                ),
+                Polygon::PTN(vec) => Polygon::PTN(
+                    vec.iter()
+                        .map(|&(p, t, n)| (positions.get(p), tex_coords.get(t), normals.get(n)))
+                        .collect(),
+                ),
+            })
+            .collect();
+
+        Some(RawObj {
+            name: Some(name.to_string()),
+            material_libraries: self.material_libraries.clone(),
+
+            positions: positions.copy(&self.positions),
+            colors: positions.copy(&self.colors),
+            tex_coords: tex_coords.copy(&self.tex_coords),
+            normals: normals.copy(&self.normals),
+            param_vertices: Vec::new(),
+
+            points,
+            lines,
+            polygons,
+
+            groups: narrow_groups(&self.groups, object),
+            meshes: narrow_groups(&self.meshes, object),
+            smoothing_groups: narrow_groups(&self.smoothing_groups, object),
+            merging_groups: narrow_groups(&self.merging_groups, object),
+            objects: narrow_groups(&self.objects, object),
+
+            free_forms: Vec::new(),
+            connections: Vec::new(),
+
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Adds a range of elements to the mesh of the given material, which is created if needed.
+    /// `elements` selects the points, the lines or the polygons of the mesh.
+    pub(crate) fn extend_mesh(
+        &mut self,
+        material: &str,
+        elements: fn(&mut Group) -> &mut Vec<Range>,
+        range: Range,
+    ) {
+        if range.start == range.end {
+            return;
+        }
+        let group = self
+            .meshes
+            .entry(material.to_string())
+            .or_insert_with(|| Group {
+                points: Vec::new(),
+                lines: Vec::new(),
+                polygons: Vec::new(),
+            });
+        let ranges = elements(group);
+        match ranges.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => ranges.push(range),
+        }
+    }
+
+    /// Removes the free forms at the given indices, in increasing order, and renumbers the
+    /// trimming curves and the connections of the others. Connections of removed surfaces are
+    /// removed too.
+    pub(crate) fn remove_free_forms(&mut self, removed: &[usize]) {
+        let mut indices = vec![None; self.free_forms.len()];
+        let mut count = 0;
+        let mut removed = removed.iter().peekable();
+        for (i, index) in indices.iter_mut().enumerate() {
+            if removed.next_if_eq(&&i).is_none() {
+                *index = Some(count);
+                count += 1;
+            }
+        }
+
+        let free_forms = std::mem::take(&mut self.free_forms);
+        self.free_forms = free_forms
+            .into_iter()
+            .zip(&indices)
+            .filter(|(_, index)| index.is_some())
+            .map(|(mut free_form, _)| {
+                let loops = free_form
+                    .trims
+                    .iter_mut()
+                    .chain(&mut free_form.holes)
+                    .chain(&mut free_form.special_curves);
+                for curve in loops.flatten() {
+                    // Trimming curves are `curv2` curves, which are never removed
+                    curve.curve = indices[curve.curve].unwrap_or(curve.curve);
+                }
+                free_form
+            })
+            .collect();
+
+        let connections = std::mem::take(&mut self.connections);
+        self.connections = connections
+            .into_iter()
+            .filter_map(|mut connection| {
+                for surface in &mut connection.surfaces {
+                    *surface = indices[*surface]?;
+                }
+                for curve in &mut connection.curves {
+                    curve.curve = indices[curve.curve].unwrap_or(curve.curve);
+                }
+                Some(connection)
+            })
+            .collect();
+    }
+}
+
+/// Gives new indices to the vertex data which are referred to, in the order of the first
+/// reference.
+struct Renumber {
+    indices: Vec<Option<usize>>,
+    /// Old indices of the vertex data, in the new order.
+    order: Vec<usize>,
+}
+
+impl Renumber {
+    fn new(len: usize) -> Self {
+        Renumber {
+            indices: vec![None; len],
+            order: Vec::new(),
+        }
+    }
+
+    /// Returns the new index of the `i`-th vertex data.
+    fn get(&mut self, i: usize) -> usize {
+        match self.indices[i] {
+            Some(index) => index,
+            None => {
+                self.order.push(i);
+                self.indices[i] = Some(self.order.len() - 1);
+                self.order.len() - 1
+            }
+        }
+    }
+
+    /// Copies the vertex data which are referred to in the new order, or nothing if `source` is
+    /// empty, like `colors` of models without vertex color.
+    fn copy<T: Copy>(&self, source: &[T]) -> Vec<T> {
+        if source.is_empty() {
+            return Vec::new();
+        }
+        self.order.iter().map(|&i| source[i]).collect()
+    }
+}
+
+/// Iterates over the items in the ranges.
+fn select<'a, T>(items: &'a [T], ranges: &'a [Range]) -> impl Iterator<Item = &'a T> {
+    ranges
+        .iter()
+        .flat_map(move |range| &items[range.start..range.end])
+}
+
+/// Narrows down the groups to the elements of `object`, whose elements are numbered from 0.
+fn narrow_groups<K: Clone + Eq + Hash>(
+    groups: &IndexMap<K, Group>,
+    object: &Group,
+) -> IndexMap<K, Group> {
+    let mut result = IndexMap::new();
+    for (key, group) in groups {
+        let group = Group {
+            points: narrow(&group.points, &object.points),
+            lines: narrow(&group.lines, &object.lines),
+            polygons: narrow(&group.polygons, &object.polygons),
+        };
+        if !group.points.is_empty() || !group.lines.is_empty() || !group.polygons.is_empty() {
+            result.insert(key.clone(), group);
+        }
+    }
+    result
+}
+
+/// Returns the parts of `ranges` which overlap `object`, numbered from the start of `object`.
+fn narrow(ranges: &[Range], object: &[Range]) -> Vec<Range> {
+    let mut result: Vec<Range> = Vec::new();
+    let mut offset = 0;
+    for kept in object {
+        for range in ranges {
+            let start = range.start.max(kept.start);
+            let end = range.end.min(kept.end);
+            if start >= end {
+                continue;
+            }
+
+            let (start, end) = (start - kept.start + offset, end - kept.start + offset);
+            match result.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => result.push(Range { start, end }),
+            }
+        }
+        offset += kept.end - kept.start;
+    }
+    result
+}
+
+/// The `Point` type which stores the index of the position vector.
+pub type Point = usize;
+
+/// The `Line` type.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Line {
     /// A series of line segments which contain only the position data of each vertex
     P(Vec<usize>),
     /// A series of line segments which contain both position and texture coordinate
@@ -525,7 +2521,7 @@ pub enum Polygon {
 }
 
 /// A group which contains ranges of points, lines and polygons
-#[derive(Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Group {
     /// Multiple range of points
     pub points: Vec<Range>,
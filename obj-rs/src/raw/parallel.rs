@@ -0,0 +1,351 @@
+//! Parses large `.obj` files on multiple threads
+
+use std::iter::Peekable;
+use std::vec;
+
+use rayon::prelude::*;
+
+use crate::error::{ObjError, ObjResult};
+use crate::raw::chunk::continues;
+use crate::raw::lexer::{lex, Lexer};
+use crate::raw::object::{
+    group_names, group_number, material_name, object_name, parse_chunk, parse_obj_with,
+    vertex_kind, visit_vertex, Chunk, Context, Groups, ObjVisitor, RawObj, VertexData, VertexSink,
+};
+use crate::raw::ParseOptions;
+
+/// Chunks aren't made smaller than this, so that small files aren't split needlessly.
+const MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Parses a wavefront `.obj` format on multiple threads.
+///
+/// The result is exactly the same as the one of `parse_obj`. See `parse_obj_parallel_with` for
+/// the details.
+pub fn parse_obj_parallel(input: &[u8]) -> ObjResult<RawObj> {
+    parse_obj_parallel_with(input, &ParseOptions::default())
+}
+
+/// Parses a wavefront `.obj` format on multiple threads with the given options.
+///
+/// The input is split at line boundaries into chunks, which are parsed concurrently on the rayon
+/// thread pool. The whole file has to be in memory, e.g. read into a `Vec<u8>` or memory-mapped.
+///
+/// The result, including errors and warnings, is exactly the same as the one of
+/// `parse_obj_with`. Files with free-form curves or surfaces are parsed on a single thread after
+/// the first pass, since those statements depend on the ones before them.
+///
+/// ```rust
+/// use obj::raw::{parse_obj, parse_obj_parallel};
+///
+/// let input = std::fs::read("tests/fixtures/sponza.obj")?;
+/// let raw = parse_obj_parallel(&input)?;
+///
+/// assert_eq!(raw, parse_obj(&input[..])?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn parse_obj_parallel_with(input: &[u8], options: &ParseOptions) -> ObjResult<RawObj> {
+    let chunk_size = input.len() / (rayon::current_num_threads() * 4);
+    parse_chunks(input, options, chunk_size.max(MIN_CHUNK_SIZE))
+}
+
+/// Parses the input split into chunks of about `chunk_size` bytes.
+///
+/// Each chunk is parsed twice. The first pass parses vertex data, and finds out which groups are
+/// active at the end of the chunk. Then the second pass parses the elements, knowing the number
+/// of vertex data and the groups at the beginning of the chunk.
+fn parse_chunks(input: &[u8], options: &ParseOptions, chunk_size: usize) -> ObjResult<RawObj> {
+    let pieces = split(input, chunk_size);
+    let scans: Vec<Scan> = pieces.par_iter().map(Scan::new).collect();
+    // Free-form statements depend on the attributes and curves before them, which can't be known
+    // from the first pass alone
+    if scans.iter().any(|scan| scan.free_form) {
+        return parse_obj_with(input, options);
+    }
+
+    // Find out the state of the parser at the beginning of each chunk
+    let mut contexts = Vec::with_capacity(scans.len());
+    let mut context = Context::default();
+    for scan in &scans {
+        let next = scan.advance(&context);
+        contexts.push(context);
+        context = next;
+    }
+
+    let (vertices, replays): (Vec<_>, Vec<_>) = scans
+        .into_iter()
+        .map(|scan| (scan.vertices, Replay::new(scan.errors)))
+        .unzip();
+    let chunks: Vec<ObjResult<Chunk>> = pieces
+        .into_par_iter()
+        .zip(replays)
+        .zip(contexts)
+        .map(|((piece, mut replay), context)| {
+            let lexer = Lexer::after_lines(piece.input, piece.lines);
+            parse_chunk(lexer, options, &context, &mut replay)
+        })
+        .collect();
+
+    merge(vertices, chunks)
+}
+
+/// Part of the input which consists of whole logical lines.
+struct Piece<'a> {
+    input: &'a [u8],
+    /// Number of physical lines before the chunk.
+    lines: usize,
+}
+
+/// Splits the input into chunks of at least `chunk_size` bytes, at the ends of logical lines.
+fn split(input: &[u8], chunk_size: usize) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut lines = 0;
+    let mut start = 0;
+
+    while start < input.len() {
+        let mut end = (start + chunk_size).min(input.len());
+        loop {
+            match input[end..].iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    end += i + 1;
+                    if !continues(&input[start..end]) {
+                        break;
+                    }
+                }
+                None => {
+                    end = input.len();
+                    break;
+                }
+            }
+        }
+
+        let piece = &input[start..end];
+        pieces.push(Piece {
+            input: piece,
+            lines,
+        });
+        lines += piece.iter().filter(|&&b| b == b'\n').count();
+        start = end;
+    }
+
+    pieces
+}
+
+/// Result of the first pass over a chunk.
+struct Scan {
+    vertices: VertexData,
+    /// Errors of vertex data statements, and the lines where they occurred.
+    errors: Vec<(usize, ObjError)>,
+    /// Groups started in the chunk, `None` if the chunk doesn't change them.
+    groups: Option<Vec<String>>,
+    mesh: Option<String>,
+    smoothing_group: Option<Option<usize>>,
+    merging_group: Option<Option<usize>>,
+    object: Option<Option<String>>,
+    /// Whether the chunk has any free-form curve or surface statement.
+    free_form: bool,
+}
+
+impl Scan {
+    fn new(piece: &Piece<'_>) -> Self {
+        let mut scan = Scan {
+            vertices: VertexData::default(),
+            errors: Vec::new(),
+            groups: None,
+            mesh: None,
+            smoothing_group: None,
+            merging_group: None,
+            object: None,
+            free_form: false,
+        };
+
+        let lexer = Lexer::after_lines(piece.input, piece.lines);
+        // Errors of the lexer itself stop the second pass at the same line, so the rest of the
+        // chunk doesn't matter
+        let _ = lex(lexer, &ParseOptions::new(), |line, stmt, args| {
+            // Erroneous statements don't change anything, as in `parse_chunk`
+            match stmt {
+                "v" | "vt" | "vn" | "vp" => {
+                    if let Err(e) = visit_vertex(stmt, args, &mut scan.vertices) {
+                        scan.errors.push((line, e));
+                    }
+                }
+                "g" => {
+                    let names = group_names(args).iter().map(|name| name.to_string());
+                    scan.groups = Some(names.collect());
+                }
+                "usemtl" => {
+                    if let Ok(name) = material_name(args) {
+                        scan.mesh = Some(name.to_string());
+                    }
+                }
+                "s" => {
+                    if let Ok(id) = group_number(args) {
+                        scan.smoothing_group = Some(id);
+                    }
+                }
+                "mg" => {
+                    if let Ok(id) = group_number(args) {
+                        scan.merging_group = Some(id);
+                    }
+                }
+                "o" => scan.object = Some(object_name(args)),
+                "cstype" | "deg" | "bmat" | "step" | "curv" | "curv2" | "surf" | "parm"
+                | "stech" | "ctech" | "trim" | "hole" | "scrv" | "sp" | "end" | "con" => {
+                    scan.free_form = true
+                }
+                _ => {}
+            }
+            Ok(())
+        });
+
+        scan
+    }
+
+    /// Returns the state of the parser after the chunk, which starts with the given state.
+    fn advance(&self, context: &Context) -> Context {
+        let counts = self.vertices.counts();
+        let mut vertices = context.vertices;
+        for (total, count) in vertices.iter_mut().zip(&counts) {
+            *total += count;
+        }
+
+        Context {
+            vertices,
+            groups: self
+                .groups
+                .clone()
+                .unwrap_or_else(|| context.groups.clone()),
+            mesh: self.mesh.clone().unwrap_or_else(|| context.mesh.clone()),
+            smoothing_group: self.smoothing_group.unwrap_or(context.smoothing_group),
+            merging_group: self.merging_group.unwrap_or(context.merging_group),
+            object: self
+                .object
+                .clone()
+                .unwrap_or_else(|| context.object.clone()),
+            free_form: context.free_form.clone(),
+        }
+    }
+}
+
+/// `VertexSink` of the second pass, which counts the vertex data parsed by the first pass, and
+/// reports the same errors.
+struct Replay {
+    counts: [usize; 4],
+    errors: Peekable<vec::IntoIter<(usize, ObjError)>>,
+}
+
+impl Replay {
+    fn new(errors: Vec<(usize, ObjError)>) -> Self {
+        Replay {
+            counts: [0; 4],
+            errors: errors.into_iter().peekable(),
+        }
+    }
+}
+
+impl VertexSink for Replay {
+    fn vertex<V: ObjVisitor>(
+        &mut self,
+        line: usize,
+        stmt: &str,
+        _: &[&str],
+        _: &mut V,
+    ) -> ObjResult<()> {
+        if let Some((_, e)) = self.errors.next_if(|&(l, _)| l == line) {
+            return Err(e);
+        }
+
+        self.counts[vertex_kind(stmt)] += 1;
+        Ok(())
+    }
+
+    fn counts(&self) -> [usize; 4] {
+        self.counts
+    }
+}
+
+/// Concatenates the results of all chunks, or returns the first error.
+fn merge(vertices: Vec<VertexData>, chunks: Vec<ObjResult<Chunk>>) -> ObjResult<RawObj> {
+    let mut raw = RawObj::empty();
+    let mut groups = Groups::new();
+    for (mut vertices, chunk) in vertices.into_iter().zip(chunks) {
+        let mut chunk = chunk?;
+        vertices.pad_colors();
+        chunk.raw.positions = vertices.positions;
+        chunk.raw.colors = vertices.colors;
+        chunk.raw.tex_coords = vertices.tex_coords;
+        chunk.raw.normals = vertices.normals;
+        chunk.raw.param_vertices = vertices.param_vertices;
+        raw.append(chunk, &mut groups);
+    }
+    groups.finish(&mut raw);
+    Ok(raw)
+}
+
+#[test]
+fn test_parse_chunks() {
+    use std::fs;
+
+    fn check(input: &[u8], options: &ParseOptions) {
+        let expected = parse_obj_with(input, options);
+        // Chunks of a single line, of a few lines, and of the whole input
+        for &chunk_size in &[1, 7, 64, input.len()] {
+            let parsed = parse_chunks(input, options, chunk_size);
+            match (&parsed, &expected) {
+                (Ok(parsed), Ok(expected)) => {
+                    assert_eq!(parsed, expected, "chunk size {}", chunk_size);
+                    // Groups have to be in the same order too
+                    assert_eq!(format!("{:?}", parsed), format!("{:?}", expected));
+                }
+                (Err(e), Err(expected)) => assert_eq!(e.to_string(), expected.to_string()),
+                _ => panic!("Unexpected result {:?}, expected {:?}", parsed, expected),
+            }
+        }
+    }
+
+    let strict = ParseOptions::new();
+    let lenient = ParseOptions::new().lenient(true);
+
+    for entry in fs::read_dir("tests/fixtures").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() == Some("obj".as_ref()) && !path.ends_with("sponza.obj") {
+            let input = fs::read(&path).unwrap();
+            check(&input, &strict);
+            check(&input, &lenient);
+        }
+    }
+
+    let inputs: &[&[u8]] = &[
+        b"",
+        b"\n\n",
+        b"o first\nv 0 0 0\no\nv 1 1 1\np -1 -2\n",
+        b"o first\nv 0 0 0\no second\r\nv 1 1 1\r\np 1 2",
+        // Lines joined with backslashes
+        b"v 0 \\\n0 0\nv 1 1 \\\r\n1\nf 1 2 \\ # comment \\\n-1\nv 2 2 2 # \\\np 3",
+        b"v 0 0 0\np 1 \\",
+        // Groups which continue across chunks
+        b"v 0 0 0\ng a\nusemtl red\ns 1\nmg 2\no x\np 1\np 1\ng b\np 1\ng a\nl 1 1\nf 1 1 1\n\
+          s off\nf 1 1 1\nmg 0\ng a\nusemtl red\ns 1\np 1\n",
+        b"v 0 0 0\ng a\ng\ng b c\np 1\ng c a c\nl 1 1\nusemtl\ns 1 2\nmg x\nf 1 1 1\nv 1\np -1\n",
+        // Groups which are left and started again right away, whose ranges are kept apart
+        b"v 0 0 0\ng a\np 1\ng b\ng a\np 1\ng b\nl 1 1\ng a\nl 1 1\ng a b\np 1\ng b\np 1\n",
+        b"v 0 0 0\nusemtl x\nf 1 1 1\nusemtl y\nusemtl x\nf 1 1 1\ns 1\np 1\ns 2\ns 1\np 1\n",
+        // Vertex colors which start or stop in the middle
+        b"v 0 0 0\nv 1 1 1\nv 2 2 2 0.5 0.5 0.5\nv 3 3 3\nv 4 4 4 1 0 0 1\np 1 -1\n",
+        b"v 0 0 0 1 0 0\nv 1 1 1\nv 2 2 2\nv 3 3 3\np 1 -1\n",
+        // Free-form curves and surfaces, which are parsed sequentially
+        b"vp 0\nvp 1\ncstype bezier\ndeg 1\ncurv2 1 2\nend\nv 0 0 0\np 1\ncurv2 2 1\ntrim 0 1 1\nend\n",
+        b"v 0 0 0\ng a\np 1\nend\ncstype rat taylor\ng b\np 1\n",
+        b"vp 0\nvp 1\nv 0 0 0\ncstype bezier\ndeg 1\ncurv2 1 2\nend\ndeg 1 1\n\
+          surf 0 1 0 1 1 1 1 1\nend\ng a\np 1\ncon 1 0 1 1 1 1 0 1\n",
+        // Erroneous vertex data
+        b"v 0 0 0\nv 1 1\nvt x\nvn 1 2 3 4\nvp\nv 2 2 2\np -1 2 1\np 3\n",
+        b"v 0 0 0\nfoo bar\nv 1 1 1\np 2\n",
+        b"v 0 0 0\np 2\nv 1 1 1\np 2\n",
+        b"v 0 0 0\np 1\n\xff\np 1\n",
+    ];
+    for input in inputs {
+        check(input, &strict);
+        check(input, &lenient);
+    }
+}
@@ -0,0 +1,365 @@
+//! Basis functions of free-form curves and surfaces, which are shared by their approximations
+
+use std::f32::consts::PI;
+
+use crate::error::ObjResult;
+use crate::raw::freeform::{Basis, FreeForm};
+use crate::raw::normals::angle;
+
+/// Upper bound of the number of segments of a knot span, so that a tiny tolerance doesn't
+/// exhaust the memory.
+pub(crate) const MAX_SEGMENTS: usize = 256;
+
+/// Number of segments of a knot span which are measured to pick the number of segments of
+/// spatial and curvature techniques.
+pub(crate) const MEASURED_SEGMENTS: usize = 8;
+
+/// Basis functions of a direction of a curve or a surface.
+pub(crate) enum Spline<'a> {
+    BSpline(Knots<'a>),
+    Polynomial(Polynomial<'a>),
+}
+
+impl<'a> Spline<'a> {
+    /// Basis functions of the given direction, where `0` is `u`. `count` is the number of control
+    /// points in the direction, which only polynomial bases need since knots determine it.
+    pub fn new(free_form: &'a FreeForm, direction: usize, count: usize) -> ObjResult<Self> {
+        let values = &free_form.parameters[direction];
+        let degree = free_form.degree[direction];
+        match free_form.basis {
+            Basis::BSpline => Ok(Spline::BSpline(Knots::new(values, degree)?)),
+            _ => Ok(Spline::Polynomial(Polynomial::new(
+                free_form, direction, count,
+            )?)),
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        match self {
+            Spline::BSpline(knots) => knots.degree,
+            Spline::Polynomial(polynomial) => polynomial.degree,
+        }
+    }
+
+    /// Number of control points in the direction.
+    pub fn count(&self) -> usize {
+        match self {
+            Spline::BSpline(knots) => knots.count,
+            Spline::Polynomial(polynomial) => polynomial.count,
+        }
+    }
+
+    /// Range of the parameter where the basis functions are defined.
+    pub fn domain(&self) -> (f32, f32) {
+        match self {
+            Spline::BSpline(knots) => (knots.values[knots.degree], knots.values[knots.count]),
+            Spline::Polynomial(polynomial) => (
+                polynomial.parameters[0],
+                polynomial.parameters[polynomial.parameters.len() - 1],
+            ),
+        }
+    }
+
+    /// Narrows down the parameter range of a `curv` or `surf` statement to the domain.
+    pub fn clamp(&self, (start, end): (f32, f32)) -> ObjResult<(f32, f32)> {
+        if !start.is_finite() || !end.is_finite() {
+            make_error!(InsufficientData, "Expected a finite parameter range");
+        }
+        let (first, last) = self.domain();
+        let (start, end) = (start.max(first), end.min(last));
+        if start >= end {
+            make_error!(
+                InsufficientData,
+                "Expected a parameter range which overlaps the knots"
+            );
+        }
+        Ok((start, end))
+    }
+
+    /// Returns the start and the end of every span in the range.
+    pub fn breaks(&self, (start, end): (f32, f32)) -> Vec<f32> {
+        let values = match self {
+            Spline::BSpline(knots) => knots.values,
+            Spline::Polynomial(polynomial) => polynomial.parameters,
+        };
+        let mut breaks = vec![start];
+        for &value in values {
+            if value > *breaks.last().unwrap() && value < end {
+                breaks.push(value);
+            }
+        }
+        breaks.push(end);
+        breaks
+    }
+
+    /// Returns the index of the first basis function which isn't zero at `t`, and the values and
+    /// the derivatives of the `degree + 1` basis functions from it.
+    pub fn basis(&self, t: f32) -> (usize, Vec<f32>, Vec<f32>) {
+        match self {
+            Spline::BSpline(knots) => knots.basis(t),
+            Spline::Polynomial(polynomial) => polynomial.basis(t),
+        }
+    }
+}
+
+/// Knot vector of a direction of a B-spline curve or surface.
+pub(crate) struct Knots<'a> {
+    values: &'a [f32],
+    degree: usize,
+    /// Number of control points in the direction.
+    count: usize,
+}
+
+impl<'a> Knots<'a> {
+    pub fn new(values: &'a [f32], degree: usize) -> ObjResult<Self> {
+        let count = match values.len().checked_sub(degree) {
+            Some(count) if count > 1 => count - 1,
+            _ => make_error!(
+                InsufficientData,
+                "Expected more knots than the degree plus 1"
+            ),
+        };
+        if values.iter().any(|k| !k.is_finite()) {
+            make_error!(InsufficientData, "Expected finite knots");
+        }
+        if values.windows(2).any(|pair| pair[0] > pair[1]) {
+            make_error!(InsufficientData, "Expected knots in increasing order");
+        }
+        if values[degree] >= values[count] {
+            make_error!(InsufficientData, "Expected knots which span a range");
+        }
+
+        Ok(Knots {
+            values,
+            degree,
+            count,
+        })
+    }
+
+    fn basis(&self, t: f32) -> (usize, Vec<f32>, Vec<f32>) {
+        let (knots, p) = (self.values, self.degree);
+        let n = self.count;
+
+        // Span `i` such that `knots[i] <= t < knots[i + 1]`, or the last non-empty span at the end
+        let t = t.max(knots[p]).min(knots[n]);
+        let i = if t < knots[n] {
+            knots[..=n].partition_point(|&k| k <= t) - 1
+        } else {
+            knots[..=n].partition_point(|&k| k < t) - 1
+        };
+
+        // Triangular table of the Cox-de Boor recursion. The upper triangle has the basis
+        // functions of each degree, and the lower one has the differences of the knots.
+        let mut ndu = vec![vec![0.0f32; p + 1]; p + 1];
+        let mut left = vec![0.0f32; p + 1];
+        let mut right = vec![0.0f32; p + 1];
+        ndu[0][0] = 1.0;
+        for j in 1..=p {
+            left[j] = t - knots[i + 1 - j];
+            right[j] = knots[i + j] - t;
+            let mut saved = 0.0;
+            for r in 0..j {
+                ndu[j][r] = right[r + 1] + left[j - r];
+                let temp = ndu[r][j - 1] / ndu[j][r];
+                ndu[r][j] = saved + right[r + 1] * temp;
+                saved = left[j - r] * temp;
+            }
+            ndu[j][j] = saved;
+        }
+
+        let values = (0..=p).map(|r| ndu[r][p]).collect();
+        let derivatives = (0..=p)
+            .map(|r| {
+                if p == 0 {
+                    return 0.0;
+                }
+                let mut d = 0.0;
+                if r >= 1 {
+                    d += ndu[r - 1][p - 1] / ndu[p][r - 1];
+                }
+                if r < p {
+                    d -= ndu[r][p - 1] / ndu[p][r];
+                }
+                d * p as f32
+            })
+            .collect();
+        (i - p, values, derivatives)
+    }
+}
+
+/// Basis of Bézier, Cardinal, Taylor and basis matrix curves and surfaces, which consist of
+/// polynomial segments between the global parameters of `parm` statements.
+pub(crate) struct Polynomial<'a> {
+    /// Basis matrix, whose rows have the coefficients of each basis function in increasing powers.
+    matrix: Vec<f32>,
+    degree: usize,
+    /// Number of control points between the first control points of adjacent segments.
+    step: usize,
+    parameters: &'a [f32],
+    count: usize,
+}
+
+impl<'a> Polynomial<'a> {
+    fn new(free_form: &'a FreeForm, direction: usize, count: usize) -> ObjResult<Self> {
+        let degree = free_form.degree[direction];
+        // Basis matrices have `(degree + 1)²` coefficients, so a huge degree is rejected before
+        // making any of them
+        if count <= degree {
+            make_error!(
+                InsufficientData,
+                "The number of control points doesn't match the degree and the step"
+            );
+        }
+        let order = degree + 1;
+        let (matrix, step) = match free_form.basis {
+            Basis::Bezier => (bernstein(degree), degree.max(1)),
+            Basis::Cardinal if degree == 3 => (
+                vec![
+                    0.0, -0.5, 1.0, -0.5, //
+                    1.0, 0.0, -2.5, 1.5, //
+                    0.0, 0.5, 2.0, -1.5, //
+                    0.0, 0.0, -0.5, 0.5,
+                ],
+                1,
+            ),
+            Basis::Cardinal => {
+                make_error!(InsufficientData, "Expected cardinal splines of degree 3")
+            }
+            Basis::Taylor => {
+                let mut matrix = vec![0.0; order * order];
+                for i in 0..order {
+                    matrix[i * order + i] = 1.0;
+                }
+                (matrix, order)
+            }
+            Basis::BasisMatrix => {
+                let matrix = &free_form.basis_matrix[direction];
+                if matrix.len() != order * order {
+                    make_error!(
+                        InsufficientData,
+                        "Expected a basis matrix of the size of the degree plus 1"
+                    );
+                }
+                if free_form.step[direction] == 0 {
+                    make_error!(InsufficientData, "Expected a step for the basis matrix");
+                }
+                (matrix.clone(), free_form.step[direction])
+            }
+            Basis::BSpline => unreachable!(),
+        };
+
+        // Every segment takes `degree + 1` control points, from every `step`th one
+        let segments = count.saturating_sub(order) / step + 1;
+        if count < order || (segments - 1) * step + order != count {
+            make_error!(
+                InsufficientData,
+                "The number of control points doesn't match the degree and the step"
+            );
+        }
+        let parameters = &free_form.parameters[direction][..];
+        if parameters.len() != segments + 1 {
+            make_error!(
+                InsufficientData,
+                "Expected a parameter value at both ends of every segment"
+            );
+        }
+        if parameters.iter().any(|p| !p.is_finite()) {
+            make_error!(InsufficientData, "Expected finite parameters");
+        }
+        if parameters.windows(2).any(|pair| pair[0] >= pair[1]) {
+            make_error!(InsufficientData, "Expected parameters in increasing order");
+        }
+
+        Ok(Polynomial {
+            matrix,
+            degree,
+            step,
+            parameters,
+            count,
+        })
+    }
+
+    fn basis(&self, t: f32) -> (usize, Vec<f32>, Vec<f32>) {
+        let last = self.parameters.len() - 2;
+        let segment = self.parameters[1..=last].partition_point(|&p| p <= t);
+        let (start, end) = (self.parameters[segment], self.parameters[segment + 1]);
+        let s = ((t - start) / (end - start)).clamp(0.0, 1.0);
+
+        let order = self.degree + 1;
+        let (mut values, mut derivatives) = (vec![0.0; order], vec![0.0; order]);
+        for (r, row) in self.matrix.chunks_exact(order).enumerate() {
+            // Horner's method for the polynomial and its derivative
+            for (j, &coefficient) in row.iter().enumerate().rev() {
+                values[r] = values[r] * s + coefficient;
+                if j > 0 {
+                    derivatives[r] = derivatives[r] * s + coefficient * j as f32;
+                }
+            }
+            derivatives[r] /= end - start;
+        }
+        (segment * self.step, values, derivatives)
+    }
+}
+
+/// Basis matrix of the Bernstein polynomials of the given degree.
+fn bernstein(degree: usize) -> Vec<f32> {
+    let order = degree + 1;
+    let binomial =
+        |n: usize, k: usize| (0..k).fold(1.0, |c, i| c * (n - i) as f32 / (i + 1) as f32);
+
+    // `(n choose i) t^i (1 - t)^(n - i)` expands to the sum of `(n choose i) (n - i choose j - i)
+    // (-1)^(j - i) t^j` over `j`
+    let mut matrix = vec![0.0; order * order];
+    for i in 0..order {
+        for j in i..order {
+            let sign = if (j - i) & 1 == 0 { 1.0 } else { -1.0 };
+            matrix[i * order + j] = sign * binomial(degree, i) * binomial(degree - i, j - i);
+        }
+    }
+    matrix
+}
+
+/// Returns the number of segments of a span which are enough for the given estimate.
+pub(crate) fn segments(estimate: f32) -> usize {
+    if estimate.is_nan() {
+        return 1;
+    }
+    (estimate.ceil() as usize).clamp(1, MAX_SEGMENTS)
+}
+
+/// Estimates the number of segments of a span, such that the segments are at most `distance`
+/// away from the span and turn at most `angle` degrees.
+pub(crate) fn by_curvature((length, turn): (f32, f32), distance: f32, angle: f32) -> f32 {
+    // A circular arc which turns `turn / n` over `length / n` is `length * turn / 8n²` away from
+    // its chord
+    let by_distance = (length * turn / (8.0 * distance)).sqrt();
+    (turn / angle.to_radians()).max(by_distance)
+}
+
+/// Returns the length of a polyline of `MEASURED_SEGMENTS` segments, and the angle by which it
+/// turns, as if it was as stretched and as bent everywhere as it is at its worst.
+pub(crate) fn measure(points: &[[f32; 3]]) -> (f32, f32) {
+    // Coincident points don't have any direction
+    let mut distinct: Vec<[f32; 3]> = Vec::with_capacity(points.len());
+    for &point in points {
+        if distinct.last() != Some(&point) {
+            distinct.push(point);
+        }
+    }
+
+    let longest = distinct
+        .windows(2)
+        .map(|pair| distance(pair[0], pair[1]))
+        .fold(0.0, f32::max);
+    let sharpest = distinct
+        .windows(3)
+        .map(|corner| PI - angle(corner[0], corner[1], corner[2]))
+        .fold(0.0, f32::max);
+    let scale = MEASURED_SEGMENTS as f32;
+    (longest * scale, sharpest * scale)
+}
+
+pub(crate) fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+}
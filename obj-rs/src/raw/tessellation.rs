@@ -0,0 +1,701 @@
+//! Approximates free-form surfaces of `RawObj` with triangles
+
+use std::collections::HashMap;
+
+use crate::error::ObjResult;
+use crate::raw::curve::Curve;
+use crate::raw::freeform::{
+    Basis, CurveTechnique, FreeForm, Geometry, SurfaceTechnique, TrimCurve,
+};
+use crate::raw::normals::normalize;
+use crate::raw::object::{Polygon, Range, RawObj, DEFAULT_COLOR};
+use crate::raw::spline::{self, Spline, MEASURED_SEGMENTS};
+use crate::raw::trimming::Trims;
+
+/// Method used to approximate free-form surfaces with triangles.
+#[derive(Copy, PartialEq, Clone, Debug)]
+pub enum Tessellation {
+    /// Uses the `stech` technique of each surface, or the given one for surfaces without `stech`.
+    Stech(SurfaceTechnique),
+    /// Uses the given technique for every surface, ignoring `stech` statements.
+    ///
+    /// A tolerance is given with `SurfaceTechnique::Spatial` or `SurfaceTechnique::Curvature`.
+    Override(SurfaceTechnique),
+}
+
+impl RawObj {
+    /// Approximates B-spline and NURBS surfaces of `free_forms` with triangles.
+    ///
+    /// Each surface is evaluated over the range of its `surf` statement, with the knot vectors
+    /// of its `parm u` and `parm v` statements. Rational surfaces use `w` of their control points
+    /// as weights. The triangles are appended to `polygons` as `Polygon::PTN`, with new positions,
+    /// the normal vectors of the surface, and texture coordinates which go from 0 to 1 across the
+    /// surface. They belong to the mesh of the material of the surface, and to no other group.
+    ///
+    /// Surfaces with `trim` statements only keep the parts inside their outer trimming loops,
+    /// and surfaces with `hole` statements lose the parts inside their inner trimming loops.
+    /// Special curves of `scrv` statements are made of edges of the triangles, and special points
+    /// of `sp` statements are vertices of the triangles. Trimming curves are approximated with the
+    /// technique of their `ctech` statement, or as finely as the surface otherwise.
+    ///
+    /// Edges which surfaces share by `con` statements are sampled at the same places on both
+    /// surfaces, as finely as either of them needs, and the triangles of both surfaces share the
+    /// positions there, so that there's no crack between them. Connected curves which aren't
+    /// trimming curves are made of edges of the triangles, like special curves.
+    ///
+    /// Tessellated surfaces are removed from `free_forms` along with their connections, while
+    /// curves and surfaces of other bases are kept.
+    ///
+    /// Fails with `InsufficientData` if the knot vectors of a surface don't match its control
+    /// points, or if its trimming curves or connected curves can't be evaluated, in which case
+    /// `self` is left untouched.
+    ///
+    /// ```rust
+    /// use obj::raw::freeform::SurfaceTechnique;
+    /// use obj::raw::{parse_obj, Tessellation};
+    ///
+    /// let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 1\ncstype bspline\ndeg 1 1\n\
+    ///               surf 0 1 0 1 1 2 3 4\nparm u 0 0 1 1\nparm v 0 0 1 1\nend\n";
+    /// let mut raw = parse_obj(&input[..])?;
+    /// raw.tessellate(Tessellation::Stech(SurfaceTechnique::ParametricA { u: 2.0, v: 2.0 }))?;
+    ///
+    /// assert_eq!(raw.polygons.len(), 2 * 2 * 2);
+    /// assert!(raw.free_forms.is_empty());
+    /// # Ok::<(), obj::ObjError>(())
+    /// ```
+    pub fn tessellate(&mut self, method: Tessellation) -> ObjResult<()> {
+        let mut surfaces = Vec::new();
+        for (i, free_form) in self.free_forms.iter().enumerate() {
+            if free_form.basis != Basis::BSpline {
+                continue;
+            }
+            if let Geometry::Surface { .. } = free_form.geometry {
+                let technique = match method {
+                    Tessellation::Stech(default) => free_form.technique.unwrap_or(default),
+                    Tessellation::Override(technique) => technique,
+                };
+                let surface = Surface::new(&self.positions, free_form)?;
+                let samples = [surface.samples(0, technique), surface.samples(1, technique)];
+                surfaces.push((i, surface, samples));
+            }
+        }
+
+        let seams = self.seams(&surfaces)?;
+        let mut meshes = Vec::with_capacity(surfaces.len());
+        for (i, surface, [us, vs]) in &surfaces {
+            let sides: Vec<_> = seams
+                .iter()
+                .enumerate()
+                .flat_map(|(j, seam)| seam.iter().map(move |side| (j, side)))
+                .filter(|(_, side)| side.surface == *i)
+                .collect();
+            meshes.push((*i, surface.tessellate(self, us, vs, &sides)?));
+        }
+
+        let mut shared = HashMap::new();
+        for (i, mesh) in &meshes {
+            let material = self.free_forms[*i].material.clone();
+            self.append_mesh(mesh, &material, &mut shared);
+        }
+
+        let removed: Vec<_> = meshes.iter().map(|&(i, _)| i).collect();
+        self.remove_free_forms(&removed);
+        Ok(())
+    }
+
+    /// Samples the curves of the connections between the surfaces which are tessellated, at the
+    /// same places along the edge on both surfaces. Each surface comes with its samples in the
+    /// `u` and `v` directions.
+    fn seams(&self, surfaces: &[(usize, Surface, [Vec<f32>; 2])]) -> ObjResult<Vec<[Side; 2]>> {
+        let mut seams = Vec::new();
+        for connection in &self.connections {
+            let found = connection
+                .surfaces
+                .map(|surface| surfaces.iter().find(|(i, ..)| *i == surface));
+            let sampled = match found {
+                [Some(first), Some(second)] => [first, second],
+                // Surfaces which aren't tessellated don't have any edge
+                _ => continue,
+            };
+
+            // Both parts are sampled as finely as they would be on their own
+            let mut fractions = Vec::new();
+            for (piece, (_, _, [us, vs])) in connection.curves.iter().zip(&sampled) {
+                if piece.start == piece.end {
+                    make_error!(
+                        InsufficientData,
+                        "Expected connected curves which span a range"
+                    );
+                }
+                let (curve, technique) = trimming_curve(self, piece, us, vs)?;
+                let parameters = curve.parameters_between((piece.start, piece.end), technique);
+                let length = piece.end - piece.start;
+                fractions.extend(parameters.iter().map(|t| (t - piece.start) / length));
+            }
+            fractions.sort_by(f32::total_cmp);
+            // Samples which are almost at the same place would only make slivers
+            fractions.dedup_by(|later, earlier| {
+                let close = *later - *earlier < 1e-5;
+                // Both ends are kept
+                if close && *later >= 1.0 {
+                    *earlier = *later;
+                }
+                close
+            });
+
+            let side = |k: usize| {
+                let piece = connection.curves[k];
+                let parameter = |fraction: f32| {
+                    if fraction >= 1.0 {
+                        piece.end
+                    } else {
+                        piece.start + fraction * (piece.end - piece.start)
+                    }
+                };
+                Side {
+                    surface: connection.surfaces[k],
+                    piece,
+                    parameters: fractions.iter().map(|&f| parameter(f)).collect(),
+                }
+            };
+            seams.push([side(0), side(1)]);
+        }
+        Ok(seams)
+    }
+
+    /// Appends the triangles of a tessellated surface, which use the given material.
+    ///
+    /// `shared` has the positions of the samples of the seams which have been appended, by the
+    /// index of the seam and of the sample. Vertices at those samples use the same positions.
+    fn append_mesh(
+        &mut self,
+        mesh: &Mesh,
+        material: &str,
+        shared: &mut HashMap<(usize, usize), usize>,
+    ) {
+        let offset = (self.tex_coords.len(), self.normals.len());
+        let start = self.polygons.len();
+
+        let mut samples: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        for &(sample, vertex) in &mesh.seams {
+            samples.entry(vertex).or_default().push(sample);
+        }
+        let mut positions = Vec::with_capacity(mesh.positions.len());
+        for (vertex, &[x, y, z]) in mesh.positions.iter().enumerate() {
+            let samples = samples.get(&vertex).map_or(&[][..], Vec::as_slice);
+            let position = match samples.iter().find_map(|sample| shared.get(sample)) {
+                Some(&position) => position,
+                None => {
+                    self.positions.push((x, y, z, 1.0));
+                    self.positions.len() - 1
+                }
+            };
+            for &sample in samples {
+                shared.entry(sample).or_insert(position);
+            }
+            positions.push(position);
+        }
+        if !self.colors.is_empty() {
+            self.colors.resize(self.positions.len(), DEFAULT_COLOR);
+        }
+        for &[u, v] in &mesh.tex_coords {
+            self.tex_coords.push((u, v, 0.0));
+        }
+        for &[x, y, z] in &mesh.normals {
+            self.normals.push((x, y, z));
+        }
+        for triangle in &mesh.triangles {
+            let corners = triangle
+                .iter()
+                .map(|&i| (positions[i], offset.0 + i, offset.1 + i));
+            self.polygons.push(Polygon::PTN(corners.collect()));
+        }
+
+        let end = self.polygons.len();
+        self.extend_mesh(material, |group| &mut group.polygons, Range { start, end });
+    }
+}
+
+/// Triangles which approximate a surface.
+struct Mesh {
+    positions: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    /// Indices of the corners, which are the same in all three vectors.
+    triangles: Vec<[usize; 3]>,
+    /// Samples of seams, by the index of the seam and of the sample, and the vertices at them.
+    seams: Vec<((usize, usize), usize)>,
+}
+
+/// Part of a `curv2` curve along an edge which a surface shares with another surface, which is
+/// sampled at the same places along the edge as the part on the other surface.
+struct Side {
+    /// Index of the surface in `RawObj::free_forms`.
+    surface: usize,
+    piece: TrimCurve,
+    /// Parameters of the samples, from the start of the part to its end.
+    parameters: Vec<f32>,
+}
+
+/// B-spline surface, whose control points are in homogeneous coordinates.
+struct Surface<'a> {
+    free_form: &'a FreeForm,
+    knots: [Spline<'a>; 2],
+    /// Control points, varying in the `u` direction first.
+    points: Vec<[f32; 4]>,
+    /// Parameter ranges in the `u` and `v` directions.
+    range: [(f32, f32); 2],
+}
+
+impl<'a> Surface<'a> {
+    fn new(positions: &[(f32, f32, f32, f32)], free_form: &'a FreeForm) -> ObjResult<Self> {
+        let (s, t, control_points) = match &free_form.geometry {
+            Geometry::Surface {
+                s,
+                t,
+                control_points,
+            } => (*s, *t, control_points),
+            _ => unreachable!(),
+        };
+
+        let u = Spline::new(free_form, 0, 0)?;
+        let v = Spline::new(free_form, 1, 0)?;
+        if u.count() * v.count() != control_points.len() {
+            make_error!(
+                InsufficientData,
+                "The number of control points doesn't match the knots"
+            );
+        }
+
+        let mut points = Vec::with_capacity(control_points.len());
+        for &(p, _, _) in control_points {
+            let (x, y, z, w) = positions[p];
+            let w = if free_form.rational { w } else { 1.0 };
+            if w <= 0.0 {
+                make_error!(InsufficientData, "Expected positive weights");
+            }
+            points.push([x * w, y * w, z * w, w]);
+        }
+
+        let range = [u.clamp(s)?, v.clamp(t)?];
+        Ok(Surface {
+            free_form,
+            knots: [u, v],
+            points,
+            range,
+        })
+    }
+
+    /// Returns the position, and the partial derivatives in the `u` and `v` directions.
+    fn evaluate(&self, u: f32, v: f32) -> [[f32; 3]; 3] {
+        let (first_u, values_u, derivatives_u) = self.knots[0].basis(u);
+        let (first_v, values_v, derivatives_v) = self.knots[1].basis(v);
+
+        let mut sums = [[0.0f32; 4]; 3];
+        for (j, (&nv, &dv)) in values_v.iter().zip(&derivatives_v).enumerate() {
+            let row = (first_v + j) * self.knots[0].count();
+            for (i, (&nu, &du)) in values_u.iter().zip(&derivatives_u).enumerate() {
+                let point = self.points[row + first_u + i];
+                for k in 0..4 {
+                    sums[0][k] += nu * nv * point[k];
+                    sums[1][k] += du * nv * point[k];
+                    sums[2][k] += nu * dv * point[k];
+                }
+            }
+        }
+
+        // Quotient rule of the homogeneous coordinates
+        let [a, a_u, a_v] = sums;
+        let position = [a[0] / a[3], a[1] / a[3], a[2] / a[3]];
+        let derivative = |d: [f32; 4]| {
+            let mut result = [0.0; 3];
+            for k in 0..3 {
+                result[k] = (d[k] - d[3] * position[k]) / a[3];
+            }
+            result
+        };
+        [position, derivative(a_u), derivative(a_v)]
+    }
+
+    /// Returns the unit normal vector at `(u, v)`.
+    fn normal(&self, u: f32, v: f32) -> [f32; 3] {
+        let normal = normalize(cross(self.evaluate(u, v)));
+        if normal != [0.0; 3] {
+            return normal;
+        }
+
+        // The surface is degenerate at poles, so the normal vector is taken a bit inside
+        let [(u0, u1), (v0, v1)] = self.range;
+        let u = u + (0.5 * (u0 + u1) - u) * 1e-3;
+        let v = v + (0.5 * (v0 + v1) - v) * 1e-3;
+        normalize(cross(self.evaluate(u, v)))
+    }
+
+    /// Approximates the surface with triangles, whose vertices are at the parameters `us` and
+    /// `vs` away from the trimming curves. `sides` are the parts of the seams on the surface,
+    /// with the indices of the seams.
+    fn tessellate(
+        &self,
+        raw: &RawObj,
+        us: &[f32],
+        vs: &[f32],
+        sides: &[(usize, &Side)],
+    ) -> ObjResult<Mesh> {
+        let free_form = self.free_form;
+        if free_form.trims.is_empty()
+            && free_form.holes.is_empty()
+            && free_form.special_curves.is_empty()
+            && free_form.special_points.is_empty()
+            && sides.is_empty()
+        {
+            return Ok(self.grid(us, vs));
+        }
+
+        let [(u0, u1), (v0, v1)] = self.range;
+        let grid: Vec<_> = vs
+            .iter()
+            .flat_map(|&v| us.iter().map(move |&u| self.normalize(u, v)))
+            .collect();
+        let trims = self.trims(raw, us, vs, sides)?;
+        let spacing = gap(us) / (u1 - u0);
+        let spacing = spacing.min(gap(vs) / (v1 - v0));
+        let (vertices, triangles) = trims.triangulate(&grid, f64::from(spacing))?;
+
+        let mut mesh = Mesh {
+            positions: Vec::with_capacity(vertices.len()),
+            tex_coords: Vec::with_capacity(vertices.len()),
+            normals: Vec::with_capacity(vertices.len()),
+            triangles,
+            seams: Vec::new(),
+        };
+        for &[x, y] in &vertices {
+            let (x, y) = (x as f32, y as f32);
+            let (u, v) = (u0 + x * (u1 - u0), v0 + y * (v1 - v0));
+            mesh.positions.push(self.evaluate(u, v)[0]);
+            mesh.tex_coords.push([x, y]);
+            mesh.normals.push(self.normal(u, v));
+        }
+
+        // Samples are vertices unless they're trimmed away, though pieces of a loop may have
+        // met at a slightly different point
+        let indices: HashMap<_, _> = vertices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.map(f64::to_bits), i))
+            .collect();
+        for &(j, side) in sides {
+            let (curve, _) = trimming_curve(raw, &side.piece, us, vs)?;
+            for (n, &t) in side.parameters.iter().enumerate() {
+                let [u, v, _] = curve.evaluate(t);
+                let point = self.normalize(u, v);
+                let vertex = match indices.get(&point.map(f64::to_bits)) {
+                    Some(&vertex) => Some(vertex),
+                    None => vertices.iter().position(|&p| same(p, point)),
+                };
+                if let Some(vertex) = vertex {
+                    mesh.seams.push(((j, n), vertex));
+                }
+            }
+        }
+        Ok(mesh)
+    }
+
+    /// Approximates the surface with the triangles of a grid.
+    fn grid(&self, us: &[f32], vs: &[f32]) -> Mesh {
+        let [(u0, u1), (v0, v1)] = self.range;
+        let mut mesh = Mesh {
+            positions: Vec::with_capacity(us.len() * vs.len()),
+            tex_coords: Vec::with_capacity(us.len() * vs.len()),
+            normals: Vec::with_capacity(us.len() * vs.len()),
+            triangles: Vec::with_capacity((us.len() - 1) * (vs.len() - 1) * 2),
+            seams: Vec::new(),
+        };
+        for &v in vs {
+            for &u in us {
+                mesh.positions.push(self.evaluate(u, v)[0]);
+                mesh.tex_coords
+                    .push([(u - u0) / (u1 - u0), (v - v0) / (v1 - v0)]);
+                mesh.normals.push(self.normal(u, v));
+            }
+        }
+
+        // Corners of each cell go counterclockwise in the parameter space
+        let columns = us.len();
+        for j in 0..vs.len() - 1 {
+            for i in 0..columns - 1 {
+                let a = j * columns + i;
+                let (b, c, d) = (a + 1, a + columns + 1, a + columns);
+                mesh.triangles.push([a, b, c]);
+                mesh.triangles.push([a, c, d]);
+            }
+        }
+        mesh
+    }
+
+    /// Maps the parameter range to the unit square.
+    fn normalize(&self, u: f32, v: f32) -> [f64; 2] {
+        let [(u0, u1), (v0, v1)] = self.range.map(|(a, b)| (f64::from(a), f64::from(b)));
+        [
+            (f64::from(u) - u0) / (u1 - u0),
+            (f64::from(v) - v0) / (v1 - v0),
+        ]
+    }
+
+    /// Approximates the trimming loops and the special curves of the surface with polylines in
+    /// the normalized parameter space, where the surface is sampled at `us` and `vs`. Parts of
+    /// the seams are sampled at their own samples, and the ones which don't go along any loop,
+    /// special curve or the boundary of an untrimmed surface are special curves.
+    fn trims(
+        &self,
+        raw: &RawObj,
+        us: &[f32],
+        vs: &[f32],
+        sides: &[(usize, &Side)],
+    ) -> ObjResult<Trims> {
+        let mut along = vec![false; sides.len()];
+        let mut polyline = |curves: &[TrimCurve]| -> ObjResult<Vec<[f64; 2]>> {
+            let mut points: Vec<[f64; 2]> = Vec::new();
+            for piece in curves {
+                let (curve, technique) = trimming_curve(raw, piece, us, vs)?;
+                let mut parameters = curve.parameters_between((piece.start, piece.end), technique);
+                let (low, high) = (piece.start.min(piece.end), piece.start.max(piece.end));
+                for (k, &(_, side)) in sides.iter().enumerate() {
+                    let (start, end) = (side.piece.start, side.piece.end);
+                    let (first, last) = (start.min(end), start.max(end));
+                    if side.piece.curve == piece.curve && low <= first && last <= high {
+                        parameters.retain(|&t| t < first || t > last);
+                        parameters.extend_from_slice(&side.parameters);
+                        along[k] = true;
+                    }
+                }
+                if piece.start <= piece.end {
+                    parameters.sort_by(f32::total_cmp);
+                } else {
+                    parameters.sort_by(|a, b| b.total_cmp(a));
+                }
+
+                for t in parameters {
+                    let [u, v, _] = curve.evaluate(t);
+                    let point = self.normalize(u, v);
+                    // Pieces of a loop meet at their ends
+                    match points.last() {
+                        Some(&last) if same(last, point) => {}
+                        _ => points.push(point),
+                    }
+                }
+            }
+            Ok(points)
+        };
+        let mut loops = |loops: &[Vec<TrimCurve>]| -> ObjResult<Vec<Vec<[f64; 2]>>> {
+            let mut polylines = Vec::with_capacity(loops.len());
+            for curves in loops {
+                let mut points = polyline(curves)?;
+                if points.len() > 1 && same(points[0], points[points.len() - 1]) {
+                    points.pop();
+                }
+                if points.len() > 2 {
+                    polylines.push(points);
+                }
+            }
+            Ok(polylines)
+        };
+
+        let free_form = self.free_form;
+        let mut outer = loops(&free_form.trims)?;
+        let holes = loops(&free_form.holes)?;
+        let mut curves = Vec::with_capacity(free_form.special_curves.len());
+        for special_curve in &free_form.special_curves {
+            curves.push(polyline(special_curve)?);
+        }
+        let mut seams = Vec::new();
+        for (&(_, side), along) in sides.iter().zip(along) {
+            if !along {
+                let (curve, _) = trimming_curve(raw, &side.piece, us, vs)?;
+                let points = side.parameters.iter().map(|&t| {
+                    let [u, v, _] = curve.evaluate(t);
+                    self.normalize(u, v)
+                });
+                seams.push(points.collect());
+            }
+        }
+        if free_form.trims.is_empty() {
+            outer.push(self.boundary(us, vs, &mut seams));
+        }
+        curves.extend(seams);
+        let mut points = Vec::with_capacity(free_form.special_points.len());
+        for &i in &free_form.special_points {
+            match raw.param_vertices.get(i) {
+                Some(&(u, v, _)) => points.push(self.normalize(u, v)),
+                None => make_error!(IndexOutOfRange, "Expected a parameter vertex"),
+            }
+        }
+
+        Ok(Trims {
+            outer,
+            holes,
+            curves,
+            points,
+        })
+    }
+
+    /// Returns the boundary of the normalized parameter space, through the samples on it. The
+    /// seams which go along it are taken out of `seams`, and their points replace the samples.
+    fn boundary(&self, us: &[f32], vs: &[f32], seams: &mut Vec<Vec<[f64; 2]>>) -> Vec<[f64; 2]> {
+        let last = (us.len() - 1, vs.len() - 1);
+        let bottom = us.iter().map(|&u| (u, vs[0]));
+        let right = vs.iter().map(|&v| (us[last.0], v));
+        let top = us.iter().rev().map(|&u| (u, vs[last.1]));
+        let left = vs.iter().rev().map(|&v| (us[0], v));
+        let sample = |(u, v)| (self.normalize(u, v), false);
+        let mut edges: [Vec<_>; 4] = [
+            bottom.map(sample).collect(),
+            right.map(sample).collect(),
+            top.map(sample).collect(),
+            left.map(sample).collect(),
+        ];
+
+        // Each edge goes along one coordinate, while the other one is fixed
+        let near = |a: f64, b: f64| (a - b).abs() <= 1e-6;
+        seams.retain(|seam| {
+            let edge = (0..4).find(|&k| {
+                let (fixed, value) = (1 - k % 2, [0.0, 1.0, 1.0, 0.0][k]);
+                seam.iter().all(|p| near(p[fixed], value))
+            });
+            let k = match edge {
+                Some(k) => k,
+                None => return true,
+            };
+            let along = k % 2;
+            let low = seam.iter().map(|p| p[along]).fold(f64::INFINITY, f64::min);
+            let high = seam
+                .iter()
+                .map(|p| p[along])
+                .fold(f64::NEG_INFINITY, f64::max);
+            let edge = &mut edges[k];
+            edge.retain(|(p, _)| p[along] < low - 1e-6 || p[along] > high + 1e-6);
+            edge.extend(seam.iter().map(|&p| (p, true)));
+            match k {
+                0 | 1 => edge.sort_by(|a, b| a.0[along].total_cmp(&b.0[along])),
+                _ => edge.sort_by(|a, b| b.0[along].total_cmp(&a.0[along])),
+            }
+            false
+        });
+
+        // Edges meet at the corners, where the points of the seams are kept
+        let mut boundary: Vec<([f64; 2], bool)> = Vec::new();
+        for point in edges.iter().flatten() {
+            match boundary.last_mut() {
+                Some(last) if near(last.0[0], point.0[0]) && near(last.0[1], point.0[1]) => {
+                    if point.1 {
+                        *last = *point;
+                    }
+                }
+                _ => boundary.push(*point),
+            }
+        }
+        if boundary.len() > 1 {
+            let (first, last) = (boundary[0], boundary[boundary.len() - 1]);
+            if near(first.0[0], last.0[0]) && near(first.0[1], last.0[1]) {
+                boundary.pop();
+                if last.1 {
+                    boundary[0] = last;
+                }
+            }
+        }
+        boundary.into_iter().map(|(point, _)| point).collect()
+    }
+
+    /// Returns the parameter values of the vertices in the given direction, where `0` is `u`.
+    fn samples(&self, direction: usize, technique: SurfaceTechnique) -> Vec<f32> {
+        let breaks = self.knots[direction].breaks(self.range[direction]);
+        let mut samples = vec![breaks[0]];
+        for span in breaks.windows(2) {
+            let segments = self.segments(direction, (span[0], span[1]), technique);
+            for k in 1..segments {
+                samples.push(span[0] + (span[1] - span[0]) * k as f32 / segments as f32);
+            }
+            samples.push(span[1]);
+        }
+        samples
+    }
+
+    /// Returns the number of segments of a knot span in the given direction.
+    fn segments(&self, direction: usize, span: (f32, f32), technique: SurfaceTechnique) -> usize {
+        let degree = self.knots[direction].degree() as f32;
+        let segments = match technique {
+            SurfaceTechnique::ParametricA { u, v } => [u, v][direction] * degree,
+            SurfaceTechnique::ParametricB(uv) => uv * degree,
+            SurfaceTechnique::Spatial(length) => self.measure(direction, span).0 / length,
+            SurfaceTechnique::Curvature { distance, angle } => {
+                spline::by_curvature(self.measure(direction, span), distance, angle)
+            }
+        };
+        spline::segments(segments)
+    }
+
+    /// Returns the largest length of the span, and the largest angle by which the surface turns
+    /// within the span, along the curves of constant parameter in the other direction. Both are
+    /// measured where the span is stretched or bent the most, as if it was uniform.
+    fn measure(&self, direction: usize, (start, end): (f32, f32)) -> (f32, f32) {
+        let other = 1 - direction;
+        let breaks = self.knots[other].breaks(self.range[other]);
+        let mut across = breaks.clone();
+        across.extend(breaks.windows(2).map(|span| 0.5 * (span[0] + span[1])));
+
+        let (mut length, mut turn) = (0.0f32, 0.0f32);
+        for &w in &across {
+            let points: Vec<_> = (0..=MEASURED_SEGMENTS)
+                .map(|k| {
+                    let t = start + (end - start) * k as f32 / MEASURED_SEGMENTS as f32;
+                    let (u, v) = if direction == 0 { (t, w) } else { (w, t) };
+                    self.evaluate(u, v)[0]
+                })
+                .collect();
+            let measured = spline::measure(&points);
+            length = length.max(measured.0);
+            turn = turn.max(measured.1);
+        }
+        (length, turn)
+    }
+}
+
+/// Cross product of the partial derivatives.
+fn cross([_, a, b]: [[f32; 3]; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Returns a trimming curve of `raw`, and the technique which approximates it as finely as the
+/// surface sampled at `us` and `vs`, unless the curve has its own technique.
+fn trimming_curve<'a>(
+    raw: &'a RawObj,
+    piece: &TrimCurve,
+    us: &[f32],
+    vs: &[f32],
+) -> ObjResult<(Curve<'a>, CurveTechnique)> {
+    let free_form = match raw.free_forms.get(piece.curve) {
+        Some(free_form) if free_form.is_curve2() => free_form,
+        _ => make_error!(IndexOutOfRange, "Expected a `curv2` curve"),
+    };
+    let spacing = gap(us).min(gap(vs));
+    let technique = free_form
+        .curve_technique
+        .unwrap_or(CurveTechnique::Spatial(spacing));
+    Ok((Curve::new(raw, free_form)?, technique))
+}
+
+/// Returns the smallest difference between adjacent values.
+fn gap(values: &[f32]) -> f32 {
+    values
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Returns whether two points of the normalized parameter space are the same.
+fn same(a: [f64; 2], b: [f64; 2]) -> bool {
+    (a[0] - b[0]).hypot(a[1] - b[1]) <= 1e-9
+}
@@ -0,0 +1,240 @@
+//! Splits polygons of `RawObj` into triangles
+
+use std::hash::Hash;
+
+use indexmap::IndexMap;
+
+use crate::raw::normals::face_normal;
+use crate::raw::object::{Group, Polygon, Range, RawObj};
+
+/// Method used to split polygons with more than 3 vertices into triangles.
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum Triangulation {
+    /// Connects the first vertex to every other edge of the polygon. It is fast, but it produces
+    /// correct results only for convex polygons.
+    Fan,
+    /// Repeatedly cuts off "ears" of the polygon. It handles concave planar polygons too.
+    EarClipping,
+}
+
+impl RawObj {
+    /// Splits every polygon with more than 3 vertices into triangles.
+    ///
+    /// Ranges of `groups`, `meshes`, `smoothing_groups`, `merging_groups` and `objects` are
+    /// updated so that they cover the triangles made from the polygons they used to cover.
+    pub fn triangulate(&mut self, method: Triangulation) {
+        let polygons = std::mem::take(&mut self.polygons);
+
+        // offsets[i] is the index of the first triangle made from the i-th polygon
+        let mut offsets = Vec::with_capacity(polygons.len() + 1);
+        let mut triangles = Vec::with_capacity(polygons.len());
+        for polygon in polygons {
+            offsets.push(triangles.len());
+
+            let corners = match polygon {
+                Polygon::P(ref vec) => self.split(vec, |&p| p, method),
+                Polygon::PT(ref vec) | Polygon::PN(ref vec) => self.split(vec, |&(p, _)| p, method),
+                Polygon::PTN(ref vec) => self.split(vec, |&(p, _, _)| p, method),
+            };
+            let corners = match corners {
+                // Triangles are kept as they are
+                None => {
+                    triangles.push(polygon);
+                    continue;
+                }
+                Some(corners) => corners,
+            };
+
+            match polygon {
+                Polygon::P(vec) => triangles.extend(pick(&vec, &corners).map(Polygon::P)),
+                Polygon::PT(vec) => triangles.extend(pick(&vec, &corners).map(Polygon::PT)),
+                Polygon::PN(vec) => triangles.extend(pick(&vec, &corners).map(Polygon::PN)),
+                Polygon::PTN(vec) => triangles.extend(pick(&vec, &corners).map(Polygon::PTN)),
+            }
+        }
+        offsets.push(triangles.len());
+        self.polygons = triangles;
+
+        remap(&mut self.groups, &offsets);
+        remap(&mut self.meshes, &offsets);
+        remap(&mut self.smoothing_groups, &offsets);
+        remap(&mut self.merging_groups, &offsets);
+        remap(&mut self.objects, &offsets);
+    }
+
+    /// Returns corners of the triangles which make up the polygon, or `None` if the polygon is
+    /// already a triangle.
+    fn split<T, F>(
+        &self,
+        vertices: &[T],
+        position: F,
+        method: Triangulation,
+    ) -> Option<Vec<[usize; 3]>>
+    where
+        F: Fn(&T) -> usize,
+    {
+        if vertices.len() == 3 {
+            return None;
+        }
+
+        Some(match method {
+            Triangulation::Fan => fan(vertices.len()),
+            Triangulation::EarClipping => {
+                let positions: Vec<_> = vertices
+                    .iter()
+                    .map(|v| {
+                        let p = self.positions[position(v)];
+                        [p.0, p.1, p.2]
+                    })
+                    .collect();
+                ear_clipping(&positions)
+            }
+        })
+    }
+}
+
+/// Picks the vertices of each triangle.
+fn pick<'a, T: Copy>(
+    vertices: &'a [T],
+    corners: &'a [[usize; 3]],
+) -> impl Iterator<Item = Vec<T>> + 'a {
+    corners
+        .iter()
+        .map(move |&[a, b, c]| vec![vertices[a], vertices[b], vertices[c]])
+}
+
+/// Moves the polygon ranges of every group to the triangles made from the polygons.
+fn remap<K: Eq + Hash>(groups: &mut IndexMap<K, Group>, offsets: &[usize]) {
+    for group in groups.values_mut() {
+        for range in &mut group.polygons {
+            *range = Range {
+                start: offsets[range.start],
+                end: offsets[range.end],
+            };
+        }
+        group.polygons.retain(|range| range.start != range.end);
+    }
+}
+
+/// Triangulates a convex polygon with `len` vertices.
+fn fan(len: usize) -> Vec<[usize; 3]> {
+    (1..len.saturating_sub(1)).map(|i| [0, i, i + 1]).collect()
+}
+
+/// Triangulates a planar polygon which may be concave.
+///
+/// The polygon is projected onto the coordinate plane which it is the most parallel to, and ears
+/// are cut off from it until only a triangle is left. If the polygon is degenerate and there is
+/// no ear, a vertex is cut off anyway, so the result always has `len - 2` triangles.
+pub(crate) fn ear_clipping(positions: &[[f32; 3]]) -> Vec<[usize; 3]> {
+    let normal = face_normal(positions);
+
+    // Drop the dominant axis of the normal vector
+    let (x, y) = if normal[0].abs() >= normal[1].abs() && normal[0].abs() >= normal[2].abs() {
+        (1, 2)
+    } else if normal[1].abs() >= normal[2].abs() {
+        (2, 0)
+    } else {
+        (0, 1)
+    };
+    let points: Vec<[f32; 2]> = positions.iter().map(|p| [p[x], p[y]]).collect();
+
+    ear_clipping_2d(&points)
+}
+
+/// Triangulates a simple polygon on a plane.
+pub(crate) fn ear_clipping_2d(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    if points.len() < 3 {
+        return triangles;
+    }
+
+    // Sign of the winding order, so that convex corners always have a positive cross product
+    let area: f32 = (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum();
+    let sign = if area < 0.0 { -1.0 } else { 1.0 };
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    while remaining.len() > 3 {
+        // Start from the second vertex, so that convex polygons are split just like a fan
+        let len = remaining.len();
+        let ear = (1..=len)
+            .map(|i| i % len)
+            .find(|&i| {
+                let (a, b, c) = (
+                    remaining[(i + len - 1) % len],
+                    remaining[i],
+                    remaining[(i + 1) % len],
+                );
+                let (pa, pb, pc) = (points[a], points[b], points[c]);
+                if sign * cross(pa, pb, pc) <= 0.0 {
+                    // Reflex or degenerate corner
+                    return false;
+                }
+
+                // No other vertex may lie inside of the ear
+                remaining.iter().all(|&v| {
+                    let p = points[v];
+                    v == a
+                        || v == b
+                        || v == c
+                        || p == pa
+                        || p == pb
+                        || p == pc
+                        || !inside_triangle(p, pa, pb, pc, sign)
+                })
+            })
+            .unwrap_or(1);
+
+        triangles.push([
+            remaining[(ear + len - 1) % len],
+            remaining[ear],
+            remaining[(ear + 1) % len],
+        ]);
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+/// Z component of `(b - a) × (c - b)`.
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0])
+}
+
+/// Returns whether `p` lies inside of, or on the boundary of, the triangle `abc`.
+fn inside_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2], sign: f32) -> bool {
+    sign * cross(a, b, p) >= 0.0 && sign * cross(b, c, p) >= 0.0 && sign * cross(c, a, p) >= 0.0
+}
+
+#[test]
+fn test_ear_clipping() {
+    // A concave polygon whose 4th vertex is reflex. The fan triangulation from the first vertex
+    // would produce a flipped triangle.
+    //
+    //   4       2
+    //   |\     /|
+    //   | \   / |
+    //   |  \ /  |
+    //   |   3   |
+    //   0-------1
+    let points = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [2.0, 1.0], [0.0, 4.0]];
+    let triangles = ear_clipping_2d(&points);
+    assert_eq!(triangles.len(), 3);
+
+    // Every triangle keeps the winding order of the polygon and the areas sum up
+    let total: f32 = triangles
+        .iter()
+        .map(|&[a, b, c]| {
+            let area = cross(points[a], points[b], points[c]) / 2.0;
+            assert!(area > 0.0);
+            area
+        })
+        .sum();
+    assert!((total - 10.0).abs() < 1e-6);
+}
@@ -0,0 +1,597 @@
+//! Triangulates trimmed surfaces in their parameter space
+//!
+//! Points are inserted into a Delaunay triangulation with edge flips, and the edges of trimming
+//! loops and special curves are forced into it by flipping the edges which cross them. The
+//! triangles which are left in the trimmed region are kept.
+
+use std::collections::VecDeque;
+
+use crate::error::ObjResult;
+
+/// Point in the parameter space of a surface.
+pub(crate) type Point = [f64; 2];
+
+/// Marks a missing neighbor, across the outer edges of the triangulation.
+const NONE: usize = usize::MAX;
+
+/// Distance under which points are considered the same, in the normalized parameter space.
+const EPSILON: f64 = 1e-9;
+
+/// Parts of the parameter space of a surface, normalized to the unit square.
+pub(crate) struct Trims {
+    /// Outer trimming loops, or the boundary of the unit square if there are none.
+    pub outer: Vec<Vec<Point>>,
+    /// Inner trimming loops.
+    pub holes: Vec<Vec<Point>>,
+    /// Special curves, which are open polylines.
+    pub curves: Vec<Vec<Point>>,
+    /// Special points.
+    pub points: Vec<Point>,
+}
+
+impl Trims {
+    /// Returns whether a point is inside an odd number of outer loops, and an even number of
+    /// holes.
+    pub fn contains(&self, p: Point) -> bool {
+        let inside = |loops: &[Vec<Point>]| loops.iter().filter(|l| winds(l, p)).count() & 1 == 1;
+        inside(&self.outer) && !inside(&self.holes)
+    }
+
+    /// Returns the distance from a point to the nearest loop or special curve.
+    fn distance(&self, p: Point) -> f64 {
+        let loops = self.outer.iter().chain(&self.holes).flat_map(|l| closed(l));
+        let curves = self
+            .curves
+            .iter()
+            .flat_map(|c| c.windows(2).map(|w| (w[0], w[1])));
+        loops
+            .chain(curves)
+            .map(|(a, b)| segment_distance(p, a, b))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Triangulates the trimmed region, with the vertices of the loops, of the special curves and
+    /// the special points, and the given points which are in the region but not too close to the
+    /// loops. Returns the vertices and the triangles, whose corners go counterclockwise.
+    pub fn triangulate(
+        &self,
+        grid: &[Point],
+        spacing: f64,
+    ) -> ObjResult<(Vec<Point>, Vec<[usize; 3]>)> {
+        let mut delaunay = Delaunay::new();
+
+        let mut constraints = Vec::new();
+        let polylines = self.outer.iter().chain(&self.holes);
+        for polyline in polylines {
+            let indices: Vec<_> = polyline.iter().map(|&p| delaunay.insert(p)).collect();
+            for i in 0..indices.len() {
+                constraints.push((indices[i], indices[(i + 1) % indices.len()]));
+            }
+        }
+        for polyline in &self.curves {
+            let indices: Vec<_> = polyline.iter().map(|&p| delaunay.insert(p)).collect();
+            constraints.extend(indices.windows(2).map(|pair| (pair[0], pair[1])));
+        }
+        for &point in &self.points {
+            delaunay.insert(point);
+        }
+
+        // Points which are very close to the loops would only make slivers
+        for &point in grid {
+            if self.contains(point) && self.distance(point) > 0.25 * spacing {
+                delaunay.insert(point);
+            }
+        }
+
+        for (a, b) in constraints {
+            delaunay.constrain(a, b)?;
+        }
+
+        // Every triangle is either inside or outside, since the loops are edges of the triangles
+        let mut indices = vec![NONE; delaunay.points.len()];
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        for triangle in &delaunay.triangles {
+            let corners = triangle.vertices;
+            if corners.iter().any(|&v| v < SUPER_VERTICES) {
+                continue;
+            }
+            let [a, b, c] = corners.map(|v| delaunay.points[v]);
+            let centroid = [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0];
+            if !self.contains(centroid) {
+                continue;
+            }
+            triangles.push(corners.map(|v| {
+                if indices[v] == NONE {
+                    indices[v] = vertices.len();
+                    vertices.push(delaunay.points[v]);
+                }
+                indices[v]
+            }));
+        }
+        Ok((vertices, triangles))
+    }
+}
+
+/// Returns the edges of a closed polyline.
+fn closed(polyline: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    (0..polyline.len()).map(move |i| (polyline[i], polyline[(i + 1) % polyline.len()]))
+}
+
+/// Returns whether a ray from the point in the `+u` direction crosses a closed polyline an odd
+/// number of times.
+fn winds(polyline: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for (a, b) in closed(polyline) {
+        if (a[1] > p[1]) != (b[1] > p[1]) {
+            let u = a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+            if p[0] < u {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let length = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length > 0.0 {
+        (((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p[0] - a[0] - t * ab[0]).hypot(p[1] - a[1] - t * ab[1])
+}
+
+/// Twice the signed area of the triangle, which is positive if the corners go counterclockwise.
+fn orient(a: Point, b: Point, c: Point) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Returns whether `c` is on the line through `a` and `b`.
+fn collinear(a: Point, b: Point, c: Point) -> bool {
+    orient(a, b, c).abs() <= EPSILON * (b[0] - a[0]).hypot(b[1] - a[1])
+}
+
+/// Returns whether `d` is inside the circumcircle of the counterclockwise triangle `abc`.
+fn in_circle(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let [ax, ay] = [a[0] - d[0], a[1] - d[1]];
+    let [bx, by] = [b[0] - d[0], b[1] - d[1]];
+    let [cx, cy] = [c[0] - d[0], c[1] - d[1]];
+    let determinant = (ax * ax + ay * ay) * (bx * cy - cx * by)
+        - (bx * bx + by * by) * (ax * cy - cx * ay)
+        + (cx * cx + cy * cy) * (ax * by - bx * ay);
+    determinant > EPSILON * EPSILON
+}
+
+/// Number of the vertices of the triangle which encloses every point.
+const SUPER_VERTICES: usize = 3;
+
+#[derive(Copy, Clone, Debug)]
+struct Triangle {
+    /// Corners, which go counterclockwise.
+    vertices: [usize; 3],
+    /// Triangles across the edges opposite to each corner.
+    neighbors: [usize; 3],
+    /// Whether the edges opposite to each corner are constrained.
+    constrained: [bool; 3],
+}
+
+impl Triangle {
+    /// Index of the corner, which has to be one of the corners.
+    fn corner(&self, vertex: usize) -> usize {
+        self.vertices.iter().position(|&v| v == vertex).unwrap()
+    }
+
+    /// Index of the corner opposite to the edge to the given neighbor.
+    fn across(&self, neighbor: usize) -> usize {
+        self.neighbors.iter().position(|&n| n == neighbor).unwrap()
+    }
+}
+
+/// Constrained Delaunay triangulation in the normalized parameter space.
+struct Delaunay {
+    points: Vec<Point>,
+    triangles: Vec<Triangle>,
+    /// A triangle which has each point as a corner.
+    incident: Vec<usize>,
+    /// Triangle where point location starts, which is near the last inserted point.
+    last: usize,
+}
+
+impl Delaunay {
+    fn new() -> Self {
+        Delaunay {
+            points: vec![[-10.0, -10.0], [30.0, -10.0], [-10.0, 30.0]],
+            triangles: vec![Triangle {
+                vertices: [0, 1, 2],
+                neighbors: [NONE; 3],
+                constrained: [false; 3],
+            }],
+            incident: vec![0; 3],
+            last: 0,
+        }
+    }
+
+    fn set(&mut self, t: usize, triangle: Triangle) {
+        for &v in &triangle.vertices {
+            self.incident[v] = t;
+        }
+        if t == self.triangles.len() {
+            self.triangles.push(triangle);
+        } else {
+            self.triangles[t] = triangle;
+        }
+    }
+
+    /// Makes the neighbor `t`, which was next to `old`, next to `new` instead.
+    fn relink(&mut self, t: usize, old: usize, new: usize) {
+        if t != NONE {
+            let i = self.triangles[t].across(old);
+            self.triangles[t].neighbors[i] = new;
+        }
+    }
+
+    fn point(&self, t: usize, i: usize) -> Point {
+        self.points[self.triangles[t].vertices[i % 3]]
+    }
+
+    /// Returns the triangle which contains the point.
+    fn locate(&self, p: Point) -> usize {
+        // Walks toward the point, which always arrives in Delaunay triangulations
+        let mut t = self.last;
+        for _ in 0..self.triangles.len() {
+            let next =
+                (0..3).find(|&i| orient(self.point(t, i + 1), self.point(t, i + 2), p) < 0.0);
+            match next.map(|i| self.triangles[t].neighbors[i]) {
+                Some(NONE) | None => return t,
+                Some(n) => t = n,
+            }
+        }
+
+        // Constrained edges may lead the walk in circles
+        (0..self.triangles.len())
+            .find(|&t| (0..3).all(|i| orient(self.point(t, i + 1), self.point(t, i + 2), p) >= 0.0))
+            .unwrap_or(self.last)
+    }
+
+    /// Inserts a point, and returns its index. Points which are already there aren't duplicated.
+    fn insert(&mut self, p: Point) -> usize {
+        let t = self.locate(p);
+        for i in 0..3 {
+            let v = self.triangles[t].vertices[i];
+            let q = self.points[v];
+            if (p[0] - q[0]).hypot(p[1] - q[1]) <= EPSILON {
+                return v;
+            }
+        }
+
+        let v = self.points.len();
+        self.points.push(p);
+        self.incident.push(t);
+        let on_edge = (0..3).find(|&i| collinear(self.point(t, i + 1), self.point(t, i + 2), p));
+        match on_edge {
+            Some(i) if self.triangles[t].neighbors[i] != NONE => self.split_edge(t, i, v),
+            _ => self.split_triangle(t, v),
+        }
+        v
+    }
+
+    fn split_triangle(&mut self, t: usize, p: usize) {
+        let Triangle {
+            vertices: [a, b, c],
+            neighbors: [na, nb, nc],
+            constrained: [ca, cb, cc],
+        } = self.triangles[t];
+        let (t1, t2) = (self.triangles.len(), self.triangles.len() + 1);
+
+        self.set(
+            t,
+            Triangle {
+                vertices: [a, b, p],
+                neighbors: [t1, t2, nc],
+                constrained: [false, false, cc],
+            },
+        );
+        self.set(
+            t1,
+            Triangle {
+                vertices: [b, c, p],
+                neighbors: [t2, t, na],
+                constrained: [false, false, ca],
+            },
+        );
+        self.set(
+            t2,
+            Triangle {
+                vertices: [c, a, p],
+                neighbors: [t, t1, nb],
+                constrained: [false, false, cb],
+            },
+        );
+        self.relink(na, t, t1);
+        self.relink(nb, t, t2);
+
+        self.last = t;
+        self.legalize(&mut vec![(t, 2), (t1, 2), (t2, 2)]);
+    }
+
+    /// Splits the edge opposite to the corner `i` of `t`, and the triangle on the other side.
+    fn split_edge(&mut self, t: usize, i: usize, p: usize) {
+        let triangle = self.triangles[t];
+        let [a, b, c] = [0, 1, 2].map(|k| triangle.vertices[(i + k) % 3]);
+        let [_, nb, nc] = [0, 1, 2].map(|k| triangle.neighbors[(i + k) % 3]);
+        let [cons, cb, cc] = [0, 1, 2].map(|k| triangle.constrained[(i + k) % 3]);
+
+        let u = triangle.neighbors[i];
+        let other = self.triangles[u];
+        let j = other.across(t);
+        let d = other.vertices[j];
+        // The other triangle goes `d, c, b`
+        let nub = other.neighbors[(j + 2) % 3];
+        let nuc = other.neighbors[(j + 1) % 3];
+        let cub = other.constrained[(j + 2) % 3];
+        let cuc = other.constrained[(j + 1) % 3];
+
+        let (t1, u1) = (self.triangles.len(), self.triangles.len() + 1);
+        self.set(
+            t,
+            Triangle {
+                vertices: [a, b, p],
+                neighbors: [u1, t1, nc],
+                constrained: [cons, false, cc],
+            },
+        );
+        self.set(
+            t1,
+            Triangle {
+                vertices: [a, p, c],
+                neighbors: [u, nb, t],
+                constrained: [cons, cb, false],
+            },
+        );
+        self.set(
+            u,
+            Triangle {
+                vertices: [d, c, p],
+                neighbors: [t1, u1, nub],
+                constrained: [cons, false, cub],
+            },
+        );
+        self.set(
+            u1,
+            Triangle {
+                vertices: [d, p, b],
+                neighbors: [t, nuc, u],
+                constrained: [cons, cuc, false],
+            },
+        );
+        self.relink(nb, t, t1);
+        self.relink(nuc, u, u1);
+
+        self.last = t;
+        self.legalize(&mut vec![(t, 2), (t1, 1), (u, 2), (u1, 1)]);
+    }
+
+    /// Flips the edges opposite to the given corners, where the new point is, until the
+    /// triangulation is Delaunay again.
+    fn legalize(&mut self, stack: &mut Vec<(usize, usize)>) {
+        while let Some((t, i)) = stack.pop() {
+            let triangle = self.triangles[t];
+            let n = triangle.neighbors[i];
+            if n == NONE || triangle.constrained[i] {
+                continue;
+            }
+            let other = self.triangles[n];
+            let q = self.points[other.vertices[other.across(t)]];
+            if in_circle(self.point(t, 0), self.point(t, 1), self.point(t, 2), q) {
+                let (t, n) = self.flip(t, i);
+                // The new point is the first corner of `t` and the last one of `n`
+                stack.push((t, 0));
+                stack.push((n, 2));
+            }
+        }
+    }
+
+    /// Flips the edge opposite to the corner `i` of `t`. Returns the two triangles, which become
+    /// `p, b, q` and `q, c, p` where `p, b, c` was `t` and `q` is the far corner of the other.
+    fn flip(&mut self, t: usize, i: usize) -> (usize, usize) {
+        let triangle = self.triangles[t];
+        let [p, b, c] = [0, 1, 2].map(|k| triangle.vertices[(i + k) % 3]);
+        let [_, ntb, ntc] = [0, 1, 2].map(|k| triangle.neighbors[(i + k) % 3]);
+        let [_, ctb, ctc] = [0, 1, 2].map(|k| triangle.constrained[(i + k) % 3]);
+
+        let n = triangle.neighbors[i];
+        let other = self.triangles[n];
+        let j = other.across(t);
+        let q = other.vertices[j];
+        // The other triangle goes `q, c, b`
+        let nnc = other.neighbors[(j + 1) % 3];
+        let nnb = other.neighbors[(j + 2) % 3];
+        let cnc = other.constrained[(j + 1) % 3];
+        let cnb = other.constrained[(j + 2) % 3];
+
+        self.set(
+            t,
+            Triangle {
+                vertices: [p, b, q],
+                neighbors: [nnc, n, ntc],
+                constrained: [cnc, false, ctc],
+            },
+        );
+        self.set(
+            n,
+            Triangle {
+                vertices: [q, c, p],
+                neighbors: [ntb, t, nnb],
+                constrained: [ctb, false, cnb],
+            },
+        );
+        self.relink(nnc, n, t);
+        self.relink(ntb, t, n);
+        (t, n)
+    }
+
+    /// Returns the triangles around a vertex, in counterclockwise order.
+    fn fan(&self, v: usize) -> Vec<usize> {
+        let start = self.incident[v];
+        let mut fan = vec![start];
+        let mut t = start;
+        loop {
+            let triangle = self.triangles[t];
+            // The edge from `v` to the previous corner leads counterclockwise
+            let next = triangle.neighbors[(triangle.corner(v) + 1) % 3];
+            if next == NONE || next == start || fan.len() > self.triangles.len() {
+                break;
+            }
+            fan.push(next);
+            t = next;
+        }
+        fan
+    }
+
+    /// Returns the triangle with the edge from `a` to `b` counterclockwise, and the index of the
+    /// corner opposite to it.
+    fn edge(&self, a: usize, b: usize) -> Option<(usize, usize)> {
+        self.fan(a).into_iter().find_map(|t| {
+            let triangle = self.triangles[t];
+            let i = triangle.corner(a);
+            (triangle.vertices[(i + 1) % 3] == b).then_some((t, (i + 2) % 3))
+        })
+    }
+
+    /// Marks the edge between `a` and `b` as constrained on both sides, if there's such an edge.
+    fn mark(&mut self, a: usize, b: usize) -> bool {
+        let mut found = false;
+        for (x, y) in [(a, b), (b, a)] {
+            if let Some((t, i)) = self.edge(x, y) {
+                self.triangles[t].constrained[i] = true;
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Forces the segment between `a` and `b` to be made of edges, which won't be flipped.
+    fn constrain(&mut self, mut a: usize, b: usize) -> ObjResult<()> {
+        // Every pass ends at a vertex on the segment, which is closer to `b`
+        for _ in 0..self.points.len() {
+            if a == b || self.mark(a, b) {
+                return Ok(());
+            }
+            let (end, crossed) = self.crossings(a, b);
+            self.remove(a, end, crossed)?;
+            if !self.mark(a, end) {
+                break;
+            }
+            a = end;
+        }
+        make_error!(InsufficientData, "Couldn't insert the trimming curves")
+    }
+
+    /// Returns the first vertex after `a` on the segment to `b`, and the edges which the segment
+    /// crosses until there. Constrained edges which are crossed are split at the crossing.
+    fn crossings(&mut self, a: usize, b: usize) -> (usize, Vec<(usize, usize)>) {
+        let (pa, pb) = (self.points[a], self.points[b]);
+
+        // Finds the corner at `a` which the segment leaves through
+        let mut start = None;
+        for t in self.fan(a) {
+            let triangle = self.triangles[t];
+            let i = triangle.corner(a);
+            let (v1, v2) = (
+                triangle.vertices[(i + 1) % 3],
+                triangle.vertices[(i + 2) % 3],
+            );
+            for v in [v1, v2] {
+                let p = self.points[v];
+                let ahead = (p[0] - pa[0]) * (pb[0] - pa[0]) + (p[1] - pa[1]) * (pb[1] - pa[1]);
+                if collinear(pa, pb, p) && ahead > 0.0 {
+                    return (v, Vec::new());
+                }
+            }
+            if orient(pa, self.points[v1], pb) > 0.0 && orient(pa, pb, self.points[v2]) > 0.0 {
+                start = Some((t, v1, v2));
+                break;
+            }
+        }
+        let (mut t, mut right, mut left) = match start {
+            Some(start) => start,
+            None => return (b, Vec::new()),
+        };
+
+        let mut crossed = Vec::new();
+        loop {
+            // The segment leaves `t` through the edge from `right` to `left`
+            let triangle = self.triangles[t];
+            let i = (triangle.corner(left) + 1) % 3;
+            if triangle.constrained[i] {
+                // Crossing constraints meet at a new vertex
+                let (pr, pl) = (self.points[right], self.points[left]);
+                let s = orient(pr, pl, pa) / (orient(pr, pl, pa) - orient(pr, pl, pb));
+                let p = [pa[0] + s * (pb[0] - pa[0]), pa[1] + s * (pb[1] - pa[1])];
+                let v = self.points.len();
+                self.points.push(p);
+                self.incident.push(t);
+                self.split_edge(t, i, v);
+                return self.crossings(a, v);
+            }
+            crossed.push((right, left));
+
+            let n = triangle.neighbors[i];
+            let other = self.triangles[n];
+            let w = other.vertices[other.across(t)];
+            let pw = self.points[w];
+            if w == b || collinear(pa, pb, pw) {
+                return (w, crossed);
+            }
+            if orient(pa, pb, pw) > 0.0 {
+                left = w;
+            } else {
+                right = w;
+            }
+            t = n;
+        }
+    }
+
+    /// Flips the crossed edges away from the segment between `a` and `b`.
+    fn remove(&mut self, a: usize, b: usize, crossed: Vec<(usize, usize)>) -> ObjResult<()> {
+        let (pa, pb) = (self.points[a], self.points[b]);
+        let crosses = |p: Point, q: Point| {
+            orient(pa, pb, p) * orient(pa, pb, q) < 0.0 && orient(p, q, pa) * orient(p, q, pb) < 0.0
+        };
+
+        // Every crossed edge is flipped away after a quadratic number of attempts at most
+        let mut attempts = 16 * crossed.len() * crossed.len() + 1024;
+        let mut queue: VecDeque<_> = crossed.into();
+        while let Some((x, y)) = queue.pop_front() {
+            attempts -= 1;
+            if attempts == 0 {
+                make_error!(InsufficientData, "Couldn't insert the trimming curves");
+            }
+
+            let (t, i) = match self.edge(x, y) {
+                Some(edge) => edge,
+                None => continue,
+            };
+            let n = self.triangles[t].neighbors[i];
+            let other = self.triangles[n];
+            let p = self.triangles[t].vertices[i];
+            let q = other.vertices[other.across(t)];
+            let (pp, pq) = (self.points[p], self.points[q]);
+
+            // Only edges of convex quadrilaterals can be flipped
+            let (px, py) = (self.points[x], self.points[y]);
+            if orient(pp, pq, px) * orient(pp, pq, py) >= 0.0 {
+                queue.push_back((x, y));
+                continue;
+            }
+            self.flip(t, i);
+            if crosses(pp, pq) {
+                queue.push_back((p, q));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,83 @@
-/// Parses &[&str] into Vec<f32>.
-pub fn parse_args(args: &[&str]) -> Result<Vec<f32>, std::num::ParseFloatError> {
-    args.iter().map(|arg| arg.parse()).collect()
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use crate::error::{ObjError, ObjResult};
+
+/// Parses &[&str] into numbers, without allocating unless there are more than 8 of them.
+pub fn parse_args(args: &[&str]) -> ObjResult<SmallVec<f32, 8>> {
+    args.iter().map(|arg| parse_token(arg)).collect()
+}
+
+/// Parses a single token, recording the token in the error on failure.
+pub fn parse_token<T>(token: &str) -> ObjResult<T>
+where
+    T: FromStr,
+    ObjError: From<T::Err>,
+{
+    token
+        .parse()
+        .map_err(|e| ObjError::from(e).with_token(token))
+}
+
+/// Vector which stores up to `N` items inline, and moves them to the heap only if more items are
+/// pushed.
+pub struct SmallVec<T, const N: usize> {
+    inline: [T; N],
+    len: usize,
+    heap: Vec<T>,
+}
+
+impl<T: Copy + Default, const N: usize> SmallVec<T, N> {
+    pub fn new() -> Self {
+        SmallVec {
+            inline: [T::default(); N],
+            len: 0,
+            heap: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.len < N {
+            self.inline[self.len] = item;
+        } else {
+            if self.len == N {
+                self.heap.extend_from_slice(&self.inline);
+            }
+            self.heap.push(item);
+        }
+        self.len += 1;
+    }
+}
+
+impl<T, const N: usize> Deref for SmallVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        if self.len <= N {
+            &self.inline[..self.len]
+        } else {
+            &self.heap
+        }
+    }
+}
+
+impl<T: Copy + Default, const N: usize> FromIterator<T> for SmallVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = SmallVec::new();
+        for item in iter {
+            vec.push(item);
+        }
+        vec
+    }
+}
+
+#[test]
+fn test_small_vec() {
+    let inline: SmallVec<u8, 4> = (0..3).collect();
+    assert_eq!(*inline, [0, 1, 2]);
+    assert!(inline.heap.is_empty());
+
+    let spilled: SmallVec<u8, 4> = (0..6).collect();
+    assert_eq!(*spilled, [0, 1, 2, 3, 4, 5]);
 }
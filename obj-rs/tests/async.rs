@@ -0,0 +1,142 @@
+#![cfg(feature = "tokio")]
+
+use obj::raw::{
+    parse_mtl, parse_mtl_async, parse_mtl_async_with, parse_obj, parse_obj_async,
+    parse_obj_async_with, parse_obj_with, ParseOptions,
+};
+use obj::{
+    load_obj, load_obj_async, load_obj_async_with, LoadOptions, Obj, Position, Triangulation,
+};
+use std::fs;
+use tokio::io::BufReader;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+#[tokio::test]
+async fn fixtures() -> TestResult {
+    for name in &[
+        "cube.obj",
+        "dome.obj",
+        "group.obj",
+        "sponza.obj",
+        "untitled.obj",
+    ] {
+        let input = fs::read(format!("tests/fixtures/{}", name))?;
+        let raw = parse_obj_async(&input[..]).await?;
+        assert_eq!(
+            raw,
+            parse_obj(&input[..])?,
+            "{} is parsed differently",
+            name
+        );
+    }
+
+    for name in &["cube.mtl", "untitled.mtl"] {
+        let input = fs::read(format!("tests/fixtures/{}", name))?;
+        // A tiny buffer makes the lines arrive in pieces
+        let mtl = parse_mtl_async(BufReader::with_capacity(3, &input[..])).await?;
+        assert_eq!(
+            mtl,
+            parse_mtl(&input[..])?,
+            "{} is parsed differently",
+            name
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn large_lenient() -> TestResult {
+    // Big enough to be read in several chunks, with negative indices and groups which span chunks
+    let mut input = String::from("o large\nmtllib a.mtl\n");
+    for i in 0..20000 {
+        input.push_str("v 0 0 0\nv 1 0 0 \\\n\nv 1 1 0\n");
+        if i % 7 == 0 {
+            input.push_str(&format!("g group{}\nusemtl material{}\n", i % 3, i % 5));
+        }
+        if i % 11 == 0 {
+            input.push_str("v 1 x 1\n");
+        }
+        input.push_str("f -1 -2 -3\ns 1\nf 1 2 -1\ns off\n");
+    }
+    input.push_str("o\n");
+
+    let options = ParseOptions::new().lenient(true);
+    let reader = BufReader::with_capacity(1000, input.as_bytes());
+    let raw = parse_obj_async_with(reader, &options).await?;
+    assert_eq!(raw, parse_obj_with(input.as_bytes(), &options)?);
+    assert_eq!(raw.name, None);
+
+    assert_eq!(
+        parse_obj_async(input.as_bytes())
+            .await
+            .unwrap_err()
+            .to_string(),
+        parse_obj(input.as_bytes()).unwrap_err().to_string()
+    );
+
+    let mtl = "newmtl a\nKd 1 0 0\nKm 1\nnewmtl b\nKd 0 1 0\n";
+    let parsed = parse_mtl_async_with(mtl.as_bytes(), &options).await?;
+    assert_eq!(parsed.warnings[0].line, 3);
+    assert_eq!(parsed.materials.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn free_forms() -> TestResult {
+    // Curves whose bodies span chunks, and trims which refer to curves of previous chunks
+    let mut input = String::from("vp 0 0\nvp 1 0\nvp 1 1\ncstype bspline\ndeg 1\n");
+    for i in 0..5000 {
+        input.push_str("curv2 1 2 3 1\nparm u 0 0 1 2 3 3\n");
+        input.push_str("end\n");
+        if i % 13 == 0 {
+            input.push_str(
+                "deg 1 1\nv 0 0 0\nsurf 0 1 0 1 -1 -1 -1 -1\ntrim 0 3 1 0 3 -1\nend\ndeg 1\n",
+            );
+        }
+    }
+    let reader = BufReader::with_capacity(1000, input.as_bytes());
+    let raw = parse_obj_async(reader).await?;
+    assert_eq!(raw, parse_obj(input.as_bytes())?);
+    assert_eq!(raw.free_forms.len(), 5000 + 5000 / 13 + 1);
+
+    // A curve whose `end` is missing at the end of the file
+    input.push_str("curv2 1 2\n");
+    let reader = BufReader::with_capacity(1000, input.as_bytes());
+    assert!(parse_obj_async(reader).await.is_err());
+    let options = ParseOptions::new().lenient(true);
+    let reader = BufReader::with_capacity(1000, input.as_bytes());
+    let raw = parse_obj_async_with(reader, &options).await?;
+    assert_eq!(raw, parse_obj_with(input.as_bytes(), &options)?);
+    assert_eq!(raw.warnings.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn load() -> TestResult {
+    let input = fs::read("tests/fixtures/dome.obj")?;
+    let dome: Obj<Position> = load_obj_async(&input[..]).await?;
+    let expected: Obj<Position> = load_obj(&input[..])?;
+    assert_eq!(dome.vertices, expected.vertices);
+    assert_eq!(dome.indices, expected.indices);
+
+    let input = fs::read("tests/fixtures/cube.obj")?;
+    let options = LoadOptions::new().triangulate(Triangulation::Fan);
+    let cube: Obj<Position> = load_obj_async_with(&input[..], &options).await?;
+    assert_eq!(cube.indices.len(), 6 * 2 * 3);
+
+    Ok(())
+}
+
+#[test]
+fn futures_are_send() {
+    fn assert_send<T: Send>(_: T) {}
+
+    let input: &[u8] = b"";
+    assert_send(parse_obj_async(input));
+    assert_send(parse_mtl_async(input));
+    assert_send(load_obj_async::<obj::Vertex, _, u16>(input));
+}
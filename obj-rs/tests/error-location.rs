@@ -0,0 +1,91 @@
+use obj::raw::{parse_mtl, parse_obj};
+use obj::{LoadErrorKind, Location, ObjError};
+use std::io::Cursor;
+
+fn obj_error(input: &str) -> ObjError {
+    match parse_obj(Cursor::new(input)) {
+        Ok(_) => panic!("Shouldn't success"),
+        Err(e) => e,
+    }
+}
+
+fn location(line: usize, statement: &str, token: Option<&str>) -> Location {
+    Location {
+        file: None,
+        line: Some(line),
+        statement: Some(statement.to_string()),
+        token: token.map(String::from),
+    }
+}
+
+#[test]
+fn load_error() {
+    let e = obj_error("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n");
+    match e {
+        ObjError::Load(ref e) => {
+            assert_eq!(*e.kind(), LoadErrorKind::IndexOutOfRange);
+            assert_eq!(*e.location(), location(4, "f", Some("3")));
+        }
+        _ => panic!("Wrong error type"),
+    }
+    assert_eq!(
+        e.to_string(),
+        "Received index value out of range: Too big index value (line 4, statement `f`, token `3`)"
+    );
+}
+
+#[test]
+fn parse_float_error() {
+    let e = obj_error("# comment\nv 0 0 0\nvn 0 1.0.0 0\n");
+    match e {
+        ObjError::ParseFloat(_, ref loc) => assert_eq!(*loc, location(3, "vn", Some("1.0.0"))),
+        _ => panic!("Wrong error type"),
+    }
+}
+
+#[test]
+fn parse_int_error() {
+    let e = obj_error("s 1\ns x\n");
+    match e {
+        ObjError::ParseInt(_, ref loc) => assert_eq!(*loc, location(2, "s", Some("x"))),
+        _ => panic!("Wrong error type"),
+    }
+}
+
+#[test]
+fn unknown_statement() {
+    let e = obj_error("v 0 0 0\nfoo bar\n");
+    assert_eq!(e.location(), Some(&location(2, "foo", None)));
+}
+
+#[test]
+fn backslash_continuation() {
+    // The statement starts on the second physical line and spans three lines
+    let e = obj_error("v 0 0 0\nf 1 \\\n  1 \\\n  1/2/3\n");
+    assert_eq!(e.location(), Some(&location(2, "f", Some("1/2/3"))));
+
+    // Line numbers after a continued statement are still physical line numbers
+    let e = obj_error("v 0 0 \\\n 0\nv 0 0 0\nfoo\n");
+    assert_eq!(e.location(), Some(&location(4, "foo", None)));
+}
+
+#[test]
+fn backslash_at_eof() {
+    let e = obj_error("v 0 0 0\nv 1 0 \\");
+    match e {
+        ObjError::Load(ref e) => {
+            assert_eq!(*e.kind(), LoadErrorKind::BackslashAtEOF);
+            assert_eq!(e.location().line, Some(2));
+        }
+        _ => panic!("Wrong error type"),
+    }
+}
+
+#[test]
+fn mtl_error() {
+    let e = match parse_mtl(Cursor::new("newmtl a\nKd 1 1 1\nNs abc\n")) {
+        Ok(_) => panic!("Shouldn't success"),
+        Err(e) => e,
+    };
+    assert_eq!(e.location(), Some(&location(3, "Ns", Some("abc"))));
+}
@@ -9,11 +9,11 @@ fn issue_34() -> TestResult {
     let result: Result<Obj, _> = load_obj(cursor);
 
     match result {
-        Ok(_) => assert!(false, "Shouldn't success"),
+        Ok(_) => panic!("Shouldn't success"),
         Err(ObjError::Load(e @ LoadError { .. })) => {
             assert_eq!(*e.kind(), LoadErrorKind::IndexOutOfRange)
         }
-        Err(_) => assert!(false, "Wrong error type"),
+        Err(_) => panic!("Wrong error type"),
     }
 
     Ok(())
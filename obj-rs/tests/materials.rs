@@ -0,0 +1,202 @@
+use obj::raw::material::MtlColor;
+use obj::{
+    load_obj_with_materials, load_obj_with_materials_from, LoadOptions, MaterialObj, ObjError,
+    ParseOptions, Position, Triangulation,
+};
+use std::collections::HashMap;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+fn options() -> LoadOptions {
+    LoadOptions::new().triangulate(Triangulation::Fan)
+}
+
+/// Serves files from memory, and records which paths have been opened.
+fn in_memory<'a>(
+    files: &'a HashMap<&str, &str>,
+    opened: &'a mut Vec<PathBuf>,
+) -> impl FnMut(&Path) -> io::Result<Cursor<&'a [u8]>> {
+    move |path| {
+        opened.push(path.to_owned());
+        match files.get(path.to_str().unwrap()) {
+            Some(content) => Ok(Cursor::new(content.as_bytes())),
+            None => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+}
+
+#[test]
+fn filesystem() -> TestResult {
+    let cube: MaterialObj<Position> =
+        load_obj_with_materials("tests/fixtures/cube.obj", &options())?;
+    assert_eq!(cube.sub_meshes.len(), 1);
+
+    let (sub_mesh, material) = cube.sub_meshes().next().unwrap();
+    assert_eq!(sub_mesh.material.as_deref(), Some("Material"));
+    let material = material.unwrap();
+    assert_eq!(material.diffuse, Some(MtlColor::Rgb(0.64, 0.64, 0.64)));
+    assert_eq!(
+        material.diffuse_map.as_ref().unwrap().file,
+        "cube-uv-num.png"
+    );
+
+    let untitled: MaterialObj<Position> =
+        load_obj_with_materials("tests/fixtures/untitled.obj", &options())?;
+    let materials: Vec<_> = untitled
+        .sub_meshes()
+        .map(|(sub_mesh, material)| {
+            (
+                sub_mesh.material.as_deref(),
+                material.unwrap().diffuse.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        materials,
+        [
+            (Some("None"), Some(MtlColor::Rgb(0.8, 0.8, 0.8))),
+            (Some("Material"), Some(MtlColor::Rgb(0.64, 0.64, 0.64))),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn missing_library() {
+    // dome.mtl doesn't exist
+    match load_obj_with_materials::<Position, u16, _>("tests/fixtures/dome.obj", &options()) {
+        Err(ObjError::Io(e)) => {
+            assert_eq!(e.kind(), io::ErrorKind::NotFound);
+            assert!(e.to_string().contains("dome.mtl"));
+        }
+        _ => panic!("A missing material library should be reported"),
+    }
+}
+
+#[test]
+fn merge_libraries() -> TestResult {
+    let mut files = HashMap::new();
+    files.insert(
+        "models/scene.obj",
+        "mtllib ../shared/common.mtl local.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\nusemtl missing\nf 1 2 3\n",
+    );
+    files.insert(
+        "models/../shared/common.mtl",
+        "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n",
+    );
+    files.insert(
+        "models/local.mtl",
+        "newmtl blue\nKd 0 0 0.5\nnewmtl green\nKd 0 1 0\n",
+    );
+
+    let mut opened = Vec::new();
+    let scene: MaterialObj<Position> = load_obj_with_materials_from(
+        &mut in_memory(&files, &mut opened),
+        "models/scene.obj",
+        &LoadOptions::new(),
+    )?;
+
+    // Libraries are resolved relative to the directory of the OBJ file
+    assert_eq!(
+        opened,
+        [
+            Path::new("models/scene.obj"),
+            Path::new("models/../shared/common.mtl"),
+            Path::new("models/local.mtl"),
+        ]
+    );
+
+    // Materials of all libraries are merged, the ones listed first win
+    assert_eq!(scene.library.materials.len(), 3);
+    let diffuse: Vec<_> = scene
+        .sub_meshes()
+        .map(|(_, material)| material.and_then(|m| m.diffuse.clone()))
+        .collect();
+    assert_eq!(
+        diffuse,
+        [
+            Some(MtlColor::Rgb(1.0, 0.0, 0.0)),
+            Some(MtlColor::Rgb(0.0, 0.0, 1.0)),
+            None,
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn lenient_libraries() -> TestResult {
+    let mut files = HashMap::new();
+    files.insert(
+        "scene.obj",
+        "mtllib scene.mtl\nv 0 0 0\nusemtl red\nf 1 1 1\n",
+    );
+    files.insert("scene.mtl", "newmtl red\nKd 1 0 0\nKm 1\n");
+
+    let mut opened = Vec::new();
+    let mut provider = in_memory(&files, &mut opened);
+    match load_obj_with_materials_from::<_, Position, u16, _>(
+        &mut provider,
+        "scene.obj",
+        &LoadOptions::new(),
+    ) {
+        Err(e) => {
+            let location = e.location().unwrap();
+            assert_eq!(location.file.as_deref(), Some(Path::new("scene.mtl")));
+            assert_eq!(location.line, Some(3));
+        }
+        Ok(_) => panic!("An unsupported statement should be reported"),
+    }
+
+    let options = LoadOptions::new().parse_options(ParseOptions::new().lenient(true));
+    let scene: MaterialObj<Position> =
+        load_obj_with_materials_from(&mut provider, "scene.obj", &options)?;
+    assert_eq!(scene.library.warnings.len(), 1);
+    assert_eq!(scene.library.warnings[0].line, 3);
+    assert!(scene.library.materials.contains_key("red"));
+
+    Ok(())
+}
+
+#[test]
+fn obj_errors() {
+    let mut files = HashMap::new();
+    files.insert("broken.obj", "v 0 0 0\nf 1 x 1\n");
+
+    let mut opened = Vec::new();
+    let mut provider = in_memory(&files, &mut opened);
+    match load_obj_with_materials_from::<_, Position, u16, _>(
+        &mut provider,
+        "broken.obj",
+        &options(),
+    ) {
+        Err(e) => {
+            let location = e.location().unwrap();
+            assert_eq!(location.file.as_deref(), Some(Path::new("broken.obj")));
+            assert_eq!(location.line, Some(2));
+        }
+        Ok(_) => panic!("A malformed face should be reported"),
+    }
+
+    match load_obj_with_materials_from::<_, Position, u16, _>(
+        &mut provider,
+        "missing.obj",
+        &options(),
+    ) {
+        Err(ObjError::Io(e)) => {
+            assert_eq!(e.kind(), io::ErrorKind::NotFound);
+            assert!(e.to_string().contains("missing.obj"));
+        }
+        _ => panic!("A missing OBJ file should be reported"),
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    fn assert_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+    assert_serde::<MaterialObj<Position>>();
+}
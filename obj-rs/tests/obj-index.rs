@@ -0,0 +1,122 @@
+use obj::raw::object::Polygon;
+use obj::{
+    load_obj, load_obj_auto, AutoObj, FromRawVertex, Indices, LoadErrorKind, LoadOptions, Obj,
+    ObjError, ObjResult, Position, Vertex,
+};
+
+/// Model with `count` positions, and a triangle which uses the last one.
+fn positions(count: usize) -> String {
+    let mut input = String::new();
+    for i in 0..count {
+        input.push_str(&format!("v {} 0 0\n", i));
+    }
+    input.push_str(&format!("f 1 2 {}\n", count));
+    input
+}
+
+/// Model with `count` triangles, each with its own vertices.
+fn triangles(count: usize) -> String {
+    let mut input = String::from("v 0 0 0\n");
+    for i in 0..count * 3 {
+        input.push_str(&format!("vn {} 0 1\n", i));
+    }
+    for i in 0..count {
+        let n = i * 3 + 1;
+        input.push_str(&format!("f 1//{} 1//{} 1//{}\n", n, n + 1, n + 2));
+    }
+    input
+}
+
+fn assert_overflow<T>(result: ObjResult<T>) {
+    match result {
+        Err(ObjError::Load(e)) => assert_eq!(*e.kind(), LoadErrorKind::IndexOverflow),
+        Err(e) => panic!("Unexpected error {}", e),
+        Ok(_) => panic!("Expected an overflow"),
+    }
+}
+
+#[test]
+fn overflow() -> ObjResult<()> {
+    // Loading too many vertices with `u16` indices fails instead of panicking
+    let input = positions(70_000);
+    assert_overflow(load_obj::<Position, _, u16>(input.as_bytes()));
+    let model: Obj<Position, u32> = load_obj(input.as_bytes())?;
+    assert_eq!(model.indices, [0, 1, 69_999]);
+
+    let input = triangles(22_000);
+    assert_overflow(load_obj::<Vertex, _, u16>(input.as_bytes()));
+    let model: Obj<Vertex, u32> = load_obj(input.as_bytes())?;
+    assert_eq!(model.vertices.len(), 66_000);
+
+    // 65,536 vertices still fit
+    let model: Obj<Position, u16> = load_obj(positions(65_536).as_bytes())?;
+    assert_eq!(model.indices, [0, 1, 65_535]);
+
+    Ok(())
+}
+
+#[test]
+fn auto() -> ObjResult<()> {
+    let options = LoadOptions::new();
+
+    let model: AutoObj<Position> = load_obj_auto(positions(65_536).as_bytes(), &options)?;
+    assert!(matches!(model, AutoObj::U16(_)));
+    assert_eq!(model.indices(), Indices::U16(&[0, 1, 65_535]));
+    assert_eq!(model.vertices().len(), 65_536);
+
+    let model: AutoObj<Position> = load_obj_auto(positions(65_537).as_bytes(), &options)?;
+    assert!(matches!(model, AutoObj::U32(_)));
+    assert_eq!(model.indices(), Indices::U32(&[0, 1, 65_536]));
+
+    let model: AutoObj = load_obj_auto(triangles(22_000).as_bytes(), &options)?;
+    let indices = model.indices();
+    assert_eq!(indices.len(), 66_000);
+    assert_eq!(indices.get(65_999), Some(65_999));
+    assert!(indices.iter().eq(0..66_000));
+
+    Ok(())
+}
+
+/// Vertex type whose indices are offset by 70,000, as if they referred to a shared buffer.
+struct Offset;
+
+impl FromRawVertex<u32> for Offset {
+    fn process(
+        _: Vec<(f32, f32, f32, f32)>,
+        _: Vec<(f32, f32, f32)>,
+        _: Vec<(f32, f32, f32)>,
+        polygons: Vec<Polygon>,
+    ) -> ObjResult<(Vec<Self>, Vec<u32>)> {
+        let mut indices = Vec::new();
+        for polygon in polygons {
+            if let Polygon::P(vec) = polygon {
+                indices.extend(vec.iter().map(|&i| 70_000 + i as u32));
+            }
+        }
+        Ok((vec![Offset], indices))
+    }
+}
+
+#[test]
+fn auto_large_index() -> ObjResult<()> {
+    // A single vertex, but indices which don't fit in `u16` aren't truncated
+    let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+    let model: AutoObj<Offset> = load_obj_auto(&input[..], &LoadOptions::new())?;
+    assert_eq!(model.indices(), Indices::U32(&[70_000, 70_001, 70_002]));
+
+    Ok(())
+}
+
+#[test]
+fn auto_iter() -> ObjResult<()> {
+    let input =
+        b"o quad\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nusemtl a\nf 1 2 3\nusemtl b\nf 2 4 3\n";
+    let model: AutoObj<Position> = load_obj_auto(&input[..], &LoadOptions::new())?;
+
+    assert_eq!(model.name(), Some("quad"));
+    let indices = model.indices();
+    assert_eq!(indices.get(3), Some(1));
+    assert!(indices.iter().eq(vec![0, 1, 2, 1, 3, 2]));
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use obj::{
+    load_meshed_obj, load_obj, load_obj_with, LoadOptions, MeshedObj, NormalGeneration, Obj,
+    Position, SubMesh, TexturedVertex, Triangulation, Vertex,
+};
+use std::fs::File;
+use std::io::BufReader;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+fn fixture(name: &str) -> std::io::Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(format!(
+        "tests/fixtures/{}",
+        name
+    ))?))
+}
+
+/// Vertices of every triangle, in the order of the index buffer.
+fn triangles<V: Copy>(obj: &Obj<V>) -> Vec<V> {
+    obj.indices
+        .iter()
+        .map(|&i| obj.vertices[i as usize])
+        .collect()
+}
+
+#[test]
+fn round_trip() -> TestResult {
+    let options = LoadOptions::new()
+        .triangulate(Triangulation::Fan)
+        .generate_normals(NormalGeneration::Flat);
+
+    let cube: Obj<TexturedVertex> = load_obj_with(fixture("cube.obj")?, &options)?;
+    let mut output = Vec::new();
+    cube.write_obj(&mut output)?;
+    let written: Obj<TexturedVertex> = load_obj(&output[..])?;
+    assert_eq!(triangles(&written), triangles(&cube));
+
+    // Flat normal vectors are generated for each triangle, but equal vertices are written once
+    assert_eq!(cube.vertices.len(), 36);
+    assert!(written.vertices.len() < 36);
+
+    let untitled: MeshedObj<Vertex> = load_meshed_obj(fixture("untitled.obj")?, &options)?;
+    let mut output = Vec::new();
+    untitled.write_obj(&mut output)?;
+    let written: MeshedObj<Vertex> = load_meshed_obj(&output[..], &LoadOptions::new())?;
+    assert_eq!(triangles(&written.obj), triangles(&untitled.obj));
+    assert_eq!(written.sub_meshes, untitled.sub_meshes);
+
+    // Positions which no triangle refers to are dropped
+    let options = LoadOptions::new().triangulate(Triangulation::Fan);
+    let sponza: MeshedObj<Position> = load_meshed_obj(fixture("sponza.obj")?, &options)?;
+    let mut output = Vec::new();
+    sponza.write_obj(&mut output)?;
+    let written: MeshedObj<Position> = load_meshed_obj(&output[..], &LoadOptions::new())?;
+    assert_eq!(triangles(&written.obj), triangles(&sponza.obj));
+    assert_eq!(written.sub_meshes, sponza.sub_meshes);
+
+    Ok(())
+}
+
+#[test]
+fn deduplicate() -> TestResult {
+    // Two triangles whose vertices differ only by their texture coordinates
+    let quad = MeshedObj {
+        obj: Obj {
+            name: Some("quad".to_string()),
+            vertices: vec![
+                TexturedVertex {
+                    position: [0.0, 0.0, 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                    texture: [0.0, 0.0, 0.0],
+                },
+                TexturedVertex {
+                    position: [1.0, 0.0, 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                    texture: [1.0, 0.0, 0.0],
+                },
+                TexturedVertex {
+                    position: [0.0, 1.0, 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                    texture: [0.0, 1.0, 0.0],
+                },
+                TexturedVertex {
+                    position: [0.0, 0.0, 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                    texture: [0.5, 0.5, 0.0],
+                },
+            ],
+            indices: vec![0u16, 1, 2, 3, 1, 2],
+        },
+        sub_meshes: vec![
+            SubMesh {
+                material: None,
+                start: 0,
+                end: 3,
+            },
+            SubMesh {
+                material: Some("red".to_string()),
+                start: 3,
+                end: 6,
+            },
+        ],
+    };
+
+    let mut output = Vec::new();
+    quad.write_obj(&mut output)?;
+    assert_eq!(
+        String::from_utf8(output)?,
+        "o quad
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+vt 0.5 0.5
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+usemtl red
+f 1/4/1 2/2/1 3/3/1
+"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn invalid_indices() {
+    let obj = Obj {
+        name: None,
+        vertices: vec![Position {
+            position: [0.0, 0.0, 0.0],
+        }],
+        indices: vec![0u16, 0, 1],
+    };
+    assert!(obj.to_raw().is_err());
+
+    let obj = Obj {
+        indices: vec![0u16, 0],
+        ..obj
+    };
+    assert!(obj.to_raw().is_err());
+}
@@ -1,6 +1,12 @@
-use obj::{load_obj, Obj, ObjResult, Position, TexturedVertex, Vertex};
+#![allow(clippy::approx_constant)]
+
+use obj::raw::object::Polygon;
+use obj::{
+    load_meshed_obj, load_obj, FromRawVertex, LoadOptions, MeshedObj, Obj, ObjResult, Position,
+    SubMesh, TexturedVertex, Triangulation, Vertex,
+};
 use std::fs::File;
-use std::io::{BufReader, Error};
+use std::io::{BufReader, Cursor, Error};
 
 fn fixture(name: &str) -> Result<BufReader<File>, Error> {
     let file = File::open(format!("tests/fixtures/{}", name))?;
@@ -125,3 +131,116 @@ fn textured_cube() -> ObjResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn sub_meshes() -> ObjResult<()> {
+    let input = r#"
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 4
+usemtl a
+f 1 2 3
+usemtl b
+f 1 3 4
+usemtl a
+f 2 3 4
+"#;
+    let model: MeshedObj<Position> = load_meshed_obj(Cursor::new(input), &LoadOptions::new())?;
+    let obj = &model.obj;
+
+    // Polygons are kept in their order, so `a` is split into two sub-meshes
+    assert_eq!(obj.indices, [0, 1, 3, 0, 1, 2, 0, 2, 3, 1, 2, 3]);
+    let sub_mesh = |material: Option<&str>, start, end| SubMesh {
+        material: material.map(String::from),
+        start,
+        end,
+    };
+    assert_eq!(
+        model.sub_meshes,
+        vec![
+            sub_mesh(None, 0, 3),
+            sub_mesh(Some("a"), 3, 6),
+            sub_mesh(Some("b"), 6, 9),
+            sub_mesh(Some("a"), 9, 12),
+        ]
+    );
+    assert_eq!(obj.sub_mesh_indices(&model.sub_meshes[1]), &[0, 1, 2]);
+    assert_eq!(obj.sub_mesh_indices(&model.sub_meshes[3]), &[1, 2, 3]);
+
+    // `load_obj` loads the same buffers
+    let plain: Obj<Position> = load_obj(Cursor::new(input))?;
+    assert_eq!(plain.indices, obj.indices);
+
+    Ok(())
+}
+
+/// Vertex type which draws the edges of every polygon as a line list, with 2 indices per edge.
+struct Edge;
+
+impl FromRawVertex<u16> for Edge {
+    fn process(
+        _: Vec<(f32, f32, f32, f32)>,
+        _: Vec<(f32, f32, f32)>,
+        _: Vec<(f32, f32, f32)>,
+        polygons: Vec<Polygon>,
+    ) -> ObjResult<(Vec<Self>, Vec<u16>)> {
+        let mut indices = Vec::new();
+        for polygon in polygons {
+            let corners = match polygon {
+                Polygon::P(vec) => vec,
+                _ => unreachable!(),
+            };
+            for (i, &corner) in corners.iter().enumerate() {
+                indices.push(corner as u16);
+                indices.push(corners[(i + 1) % corners.len()] as u16);
+            }
+        }
+        Ok((Vec::new(), indices))
+    }
+
+    fn index_count(polygon: &Polygon) -> usize {
+        match polygon {
+            Polygon::P(vec) => 2 * vec.len(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn sub_meshes_of_any_size() -> ObjResult<()> {
+    let input =
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nusemtl a\nf 1 2 3\nusemtl b\nf 1 2 3 4\nf 1 3 4\n";
+    let model: MeshedObj<Edge> = load_meshed_obj(Cursor::new(input), &LoadOptions::new())?;
+
+    // Sub-meshes follow the number of indices emitted for each polygon
+    assert_eq!(model.obj.indices.len(), 20);
+    let ranges: Vec<_> = model.sub_meshes.iter().map(|m| (m.start, m.end)).collect();
+    assert_eq!(ranges, [(0, 6), (6, 20)]);
+
+    Ok(())
+}
+
+#[test]
+fn sponza_sub_meshes() -> ObjResult<()> {
+    let options = LoadOptions::new().triangulate(Triangulation::Fan);
+    let model: MeshedObj<Position, u32> = load_meshed_obj(fixture("sponza.obj")?, &options)?;
+
+    // Sub-meshes cover the whole index buffer without overlapping
+    let mut end = 0;
+    for sub_mesh in &model.sub_meshes {
+        assert_eq!(sub_mesh.start, end);
+        assert!(sub_mesh.start < sub_mesh.end);
+        assert!(sub_mesh.material.is_some());
+        end = sub_mesh.end;
+    }
+    assert_eq!(end, model.obj.indices.len());
+
+    // Adjacent sub-meshes use different materials
+    for pair in model.sub_meshes.windows(2) {
+        assert_ne!(pair[0].material, pair[1].material);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use obj::raw::freeform::SurfaceTechnique;
+use obj::raw::object::Polygon;
+use obj::raw::{parse_obj, RawObj, Tessellation};
+use obj::{LoadErrorKind, ObjError};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Two flat patches side by side, which are sampled differently. The 1st and the 2nd `curv2`
+/// curves go around the parameter spaces of the 1st and the 2nd patches.
+const PATCHES: &str = r#"
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+v 2 0 0
+v 2 1 0
+vp 0 0
+vp 1 0
+vp 1 1
+vp 0 1
+cstype bezier
+deg 1
+ctech cparm 2
+curv2 1 2 3 4 1
+parm u 0 1 2 3 4
+end
+ctech cparm 3
+curv2 1 2 3 4 1
+parm u 0 1 2 3 4
+end
+cstype bspline
+deg 1 1
+stech cparmb 2
+surf 0 1 0 1 1 2 3 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+TRIM 1
+end
+stech cparmb 3
+surf 0 1 0 1 2 5 4 6
+parm u 0 0 1 1
+parm v 0 0 1 1
+TRIM 2
+end
+"#;
+
+/// Returns `PATCHES`, where each patch is trimmed with its own curve or isn't trimmed.
+fn patches(trimmed: bool) -> String {
+    let trim = |curve: &str| match trimmed {
+        true => format!("trim 0 4 {}", curve),
+        false => String::new(),
+    };
+    PATCHES
+        .replace("TRIM 1", &trim("1"))
+        .replace("TRIM 2", &trim("2"))
+}
+
+fn tessellated(input: &str) -> Result<RawObj, ObjError> {
+    let mut raw = parse_obj(input.as_bytes())?;
+    raw.tessellate(Tessellation::Stech(SurfaceTechnique::ParametricB(1.0)))?;
+    Ok(raw)
+}
+
+/// Returns the edges of the triangles whose both ends satisfy the predicate, as pairs of
+/// position indices, and how many triangles have each of them.
+fn edge_counts(
+    raw: &RawObj,
+    on: impl Fn((f32, f32, f32)) -> bool,
+) -> HashMap<(usize, usize), usize> {
+    let mut edges = HashMap::new();
+    for polygon in &raw.polygons {
+        let corners: Vec<_> = match polygon {
+            Polygon::PTN(vec) => vec.iter().map(|&(p, _, _)| p).collect(),
+            polygon => panic!("Unexpected polygon {:?}", polygon),
+        };
+        for i in 0..corners.len() {
+            let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+            let position = |p: usize| {
+                let (x, y, z, _) = raw.positions[p];
+                (x, y, z)
+            };
+            if on(position(a)) && on(position(b)) {
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+    }
+    edges
+}
+
+#[test]
+fn seam() -> TestResult {
+    let seam = |p: (f32, f32, f32)| (p.0 - 1.0).abs() < 1e-6;
+    for &trimmed in &[true, false] {
+        let input = patches(trimmed);
+
+        // Without the connection, the patches have different vertices along the edge
+        let raw = tessellated(&input)?;
+        let edges = edge_counts(&raw, seam);
+        assert_eq!(edges.len(), 2 + 3);
+        assert!(edges.values().all(|&count| count == 1));
+
+        // With it, both of them have the edges of either of them
+        let raw = tessellated(&format!("{}con 1 1 2 1 2 4 3 2\n", input))?;
+        let edges = edge_counts(&raw, seam);
+        assert_eq!(edges.len(), 4, "{:?}", trimmed);
+        assert!(edges.values().all(|&count| count == 2));
+        assert!(raw.connections.is_empty());
+
+        let area: f32 = raw
+            .polygons
+            .iter()
+            .map(|polygon| match polygon {
+                Polygon::PTN(vec) => {
+                    let corner = |i: usize| raw.positions[vec[i].0];
+                    let (a, b, c) = (corner(0), corner(1), corner(2));
+                    0.5 * ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0))
+                }
+                _ => unreachable!(),
+            })
+            .sum();
+        assert!((area - 2.0).abs() < 1e-5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn reversed() -> TestResult {
+    // The second part goes the other way along the curve, but the same way along the edge
+    let input = patches(true);
+    let raw = tessellated(&format!("{}con 2 4 3 2 1 1 2 1\n", input))?;
+    let edges = edge_counts(&raw, |p| (p.0 - 1.0).abs() < 1e-6);
+    assert_eq!(edges.len(), 4);
+    assert!(edges.values().all(|&count| count == 2));
+
+    Ok(())
+}
+
+#[test]
+fn closed() -> TestResult {
+    // Cylinder whose ends in the `u` direction meet each other
+    let circle = |z: f32| {
+        let points = [
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+        ];
+        let weights = [1.0, 0.70710677];
+        let vertices = points
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| format!("v {} {} {} {}\n", x, y, z, weights[i % 2]));
+        vertices.collect::<String>()
+    };
+    let input = format!(
+        "{}{}vp 0 0\nvp 0 1\nvp 4 0\nvp 4 1\n\
+         cstype bezier\ndeg 1\ncurv2 1 2\nparm u 0 1\nend\ncurv2 3 4\nparm u 0 1\nend\n\
+         cstype rat bspline\ndeg 2 1\n\
+         surf 0 4 0 1 1 2 3 4 5 6 7 8 1 9 10 11 12 13 14 15 16 9\n\
+         parm u 0 0 0 1 1 2 2 3 3 4 4 4\nparm v 0 0 1 1\nend\n\
+         con 1 0 1 1 1 0 1 2\n",
+        circle(0.0),
+        circle(1.0)
+    );
+    let mut raw = parse_obj(input.as_bytes())?;
+    raw.tessellate(Tessellation::Stech(SurfaceTechnique::ParametricB(2.0)))?;
+
+    // Every edge is shared by two triangles, except the ones around the ends
+    let edges = edge_counts(&raw, |_| true);
+    let open = |(a, b): (usize, usize)| {
+        let (za, zb) = (raw.positions[a].2, raw.positions[b].2);
+        za == zb && (za.abs() < 1e-6 || (za - 1.0).abs() < 1e-6)
+    };
+    for (&edge, &count) in &edges {
+        assert_eq!(count, if open(edge) { 1 } else { 2 }, "{:?}", edge);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn kept() -> TestResult {
+    // Connections of the surfaces which aren't tessellated are renumbered
+    let input = patches(false);
+    let bezier = "cstype bezier\ndeg 1 1\nsurf 0 1 0 1 1 2 3 4\nparm u 0 1\nparm v 0 1\nend\n";
+    let input = format!(
+        "{}{}{}con 3 0 1 1 4 1 0 1\ncon 1 0 1 1 3 0 1 1\n",
+        input, bezier, bezier
+    );
+    let raw = tessellated(&input)?;
+
+    assert_eq!(raw.free_forms.len(), 4);
+    assert_eq!(raw.connections.len(), 1);
+    assert_eq!(raw.connections[0].surfaces, [2, 3]);
+    assert_eq!(raw.connections[0].curves[0].curve, 0);
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> TestResult {
+    let input = patches(true);
+    let test_cases = [
+        ("con 1 1 1 1 2 4 3 2", LoadErrorKind::InsufficientData),
+        // The curve doesn't have enough parameters
+        (
+            "cstype bezier\ndeg 1\ncurv2 1 2 3\nparm u 0 1\nend\ncon 1 0 1 3 2 4 3 2",
+            LoadErrorKind::InsufficientData,
+        ),
+    ];
+
+    for (connection, expected) in &test_cases {
+        let input = format!("{}{}\n", input, connection);
+        let mut raw = parse_obj(input.as_bytes())?;
+        let unchanged = raw.clone();
+        let method = Tessellation::Stech(SurfaceTechnique::ParametricB(1.0));
+        match raw.tessellate(method) {
+            Err(ObjError::Load(e)) => assert_eq!(e.kind(), expected, "{}", connection),
+            _ => panic!("Expected an error from {:?}", connection),
+        }
+        assert_eq!(raw, unchanged);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use obj::raw::freeform::CurveTechnique;
+use obj::raw::object::{write_obj, Line, Range};
+use obj::raw::{parse_obj, Curve, CurveApproximation, RawObj};
+use obj::{LoadErrorKind, ObjError};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Control points which are shared by the curves of the tests.
+const POINTS: &str = r#"
+v 0 0 0
+v 1 2 0
+v 2 2 0
+v 3 0 0
+v 4 -2 0
+v 5 -2 0
+v 6 0 0
+"#;
+
+fn curve(statements: &str) -> Result<RawObj, ObjError> {
+    parse_obj(format!("{}{}\nend\n", POINTS, statements).as_bytes())
+}
+
+fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+    for axis in 0..3 {
+        assert!(
+            (actual[axis] - expected[axis]).abs() < 1e-5,
+            "{:?} should be {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn bezier() -> TestResult {
+    // Two cubic segments which share the 4th control point
+    let raw = curve("cstype bezier\ndeg 3\ncurv 0 2 1 2 3 4 5 6 7\nparm u 0 1 2")?;
+    let curve = Curve::new(&raw, &raw.free_forms[0])?;
+
+    assert_eq!(curve.range(), (0.0, 2.0));
+    assert_close(curve.evaluate(0.0), [0.0, 0.0, 0.0]);
+    assert_close(curve.evaluate(0.5), [1.5, 1.5, 0.0]);
+    assert_close(curve.evaluate(1.0), [3.0, 0.0, 0.0]);
+    assert_close(curve.evaluate(1.5), [4.5, -1.5, 0.0]);
+    assert_close(curve.evaluate(2.0), [6.0, 0.0, 0.0]);
+
+    // Parameters are clamped to the range
+    assert_close(curve.evaluate(3.0), [6.0, 0.0, 0.0]);
+
+    Ok(())
+}
+
+#[test]
+fn basis_matrix() -> TestResult {
+    // The basis matrix of cubic Bézier curves
+    let raw = curve(
+        "cstype bmatrix\ndeg 3\nbmat u 1 -3 3 -1 0 3 -6 3 0 0 3 -3 0 0 0 1\nstep 3\n\
+         curv 0 2 1 2 3 4 5 6 7\nparm u 0 1 2",
+    )?;
+    let matrix = Curve::new(&raw, &raw.free_forms[0])?;
+    let raw = curve("cstype bezier\ndeg 3\ncurv 0 2 1 2 3 4 5 6 7\nparm u 0 1 2")?;
+    let bezier = Curve::new(&raw, &raw.free_forms[0])?;
+
+    for k in 0..=8 {
+        let t = k as f32 / 4.0;
+        assert_close(matrix.evaluate(t), bezier.evaluate(t));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cardinal() -> TestResult {
+    // Every segment goes between its 2nd and 3rd control points
+    let raw = curve("cstype cardinal\ndeg 3\ncurv 0 4 1 2 3 4 5 6 7\nparm u 0 1 2 3 4")?;
+    let curve = Curve::new(&raw, &raw.free_forms[0])?;
+
+    assert_close(curve.evaluate(0.0), [1.0, 2.0, 0.0]);
+    assert_close(curve.evaluate(1.0), [2.0, 2.0, 0.0]);
+    assert_close(curve.evaluate(2.0), [3.0, 0.0, 0.0]);
+    assert_close(curve.evaluate(4.0), [5.0, -2.0, 0.0]);
+    assert_close(curve.evaluate(0.5), [1.5, 2.25, 0.0]);
+
+    Ok(())
+}
+
+#[test]
+fn taylor() -> TestResult {
+    // `(0, 0, 0) + (1, 2, 0) s + (2, 2, 0) s²`, where `s` goes from 0 to 1 between 0 and 2
+    let raw = curve("cstype taylor\ndeg 2\ncurv 0 2 1 2 3\nparm u 0 2")?;
+    let curve = Curve::new(&raw, &raw.free_forms[0])?;
+
+    assert_close(curve.evaluate(1.0), [1.0, 1.5, 0.0]);
+    assert_close(curve.evaluate(2.0), [3.0, 4.0, 0.0]);
+
+    Ok(())
+}
+
+#[test]
+fn rational() -> TestResult {
+    // Quarter of the unit circle, as a NURBS curve
+    let input = "v 1 0 0\nv 1 1 0 0.70710677\nv 0 1 0\n\
+                 cstype rat bspline\ndeg 2\ncurv 0 1 1 2 3\nparm u 0 0 0 1 1 1\nend\n";
+    let raw = parse_obj(input.as_bytes())?;
+    let curve = Curve::new(&raw, &raw.free_forms[0])?;
+
+    for k in 0..=10 {
+        let [x, y, _] = curve.evaluate(k as f32 / 10.0);
+        assert!((x.hypot(y) - 1.0).abs() < 1e-5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn curve2() -> TestResult {
+    let input = "vp 0 0\nvp 1 1\nvp 2 0\ncstype bezier\ndeg 2\ncurv2 1 2 3\nparm u 0 1\nend\n";
+    let raw = parse_obj(input.as_bytes())?;
+    let curve = Curve::new(&raw, &raw.free_forms[0])?;
+
+    // The range of `curv2` curves comes from their parameters
+    assert_eq!(curve.range(), (0.0, 1.0));
+    assert_close(curve.evaluate(0.5), [1.0, 0.5, 0.0]);
+
+    Ok(())
+}
+
+#[test]
+fn techniques() -> TestResult {
+    let raw = curve("cstype bezier\ndeg 3\ncurv 0 2 1 2 3 4 5 6 7\nparm u 0 1 2")?;
+    let curve = Curve::new(&raw, &raw.free_forms[0])?;
+
+    // 2 segments of degree 3, each of which is split into 2 × 3 line segments
+    let polyline = curve.polyline(CurveTechnique::Parametric(2.0));
+    assert_eq!(polyline.len(), 2 * 6 + 1);
+    assert_close(polyline[6], [3.0, 0.0, 0.0]);
+
+    let polyline = curve.polyline(CurveTechnique::Spatial(0.25));
+    for pair in polyline.windows(2) {
+        let d = (pair[1][0] - pair[0][0]).hypot(pair[1][1] - pair[0][1]);
+        assert!(d <= 0.25, "{:?}", pair);
+    }
+
+    // Tighter tolerances take more line segments
+    let fine = curve.polyline(CurveTechnique::Curvature {
+        distance: 0.01,
+        angle: 10.0,
+    });
+    let coarse = curve.polyline(CurveTechnique::Curvature {
+        distance: 1.0,
+        angle: 90.0,
+    });
+    assert!(coarse.len() < fine.len());
+
+    Ok(())
+}
+
+#[test]
+fn ctech() -> TestResult {
+    let input = "v 0 0 0\nv 1 0 0\ncstype bezier\ndeg 1\n\
+                 ctech cparm 2\ncurv 0 1 1 2\nparm u 0 1\nend\n\
+                 ctech cspace 0.5\ncurv 0 1 2 1\nparm u 0 1\nend\n\
+                 ctech curv 0.1 5\ncurv 0 1 1 2\nparm u 0 1\nend\n";
+    let raw = parse_obj(input.as_bytes())?;
+    let techniques: Vec<_> = raw
+        .free_forms
+        .iter()
+        .map(|free_form| free_form.curve_technique)
+        .collect();
+    assert_eq!(
+        techniques,
+        [
+            Some(CurveTechnique::Parametric(2.0)),
+            Some(CurveTechnique::Spatial(0.5)),
+            Some(CurveTechnique::Curvature {
+                distance: 0.1,
+                angle: 5.0
+            }),
+        ]
+    );
+
+    let mut output = Vec::new();
+    write_obj(&raw, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, input);
+
+    for (input, expected) in &[
+        ("ctech", LoadErrorKind::WrongNumberOfArguments),
+        ("ctech cparm", LoadErrorKind::WrongNumberOfArguments),
+        ("ctech cspace 1 2", LoadErrorKind::WrongNumberOfArguments),
+        ("ctech curv 1", LoadErrorKind::WrongNumberOfArguments),
+        ("ctech cparma 1 1", LoadErrorKind::WrongTypeOfArguments),
+    ] {
+        match parse_obj(input.as_bytes()) {
+            Err(ObjError::Load(e)) => assert_eq!(e.kind(), expected, "{}", input),
+            _ => panic!("Expected an error from {:?}", input),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn approximate() -> TestResult {
+    let input = format!(
+        "{}v 0 0 0 1 0 0\nl 1 2\nusemtl rail\n\
+         cstype bezier\ndeg 1\nctech cparm 3\ncurv 0 1 1 2\nparm u 0 1\nend\n\
+         vp 0 0\nvp 1 1\ncurv2 1 2\nparm u 0 1\nend\n\
+         curv 0 2 2 3 4\nparm u 0 1 2\nend\n\
+         cstype bspline\ndeg 1 1\nsurf 0 1 0 1 1 2 3 4\nparm u 0 0 1 1\nparm v 0 0 1 1\n\
+         trim 0 1 1\nend\n",
+        POINTS
+    );
+    let mut raw = parse_obj(input.as_bytes())?;
+    let positions = raw.positions.len();
+    let method = CurveApproximation::Ctech(CurveTechnique::Parametric(1.0));
+    raw.approximate_curves(method)?;
+
+    // `ctech` of the first curve is kept by the second one
+    assert_eq!(raw.lines.len(), 3);
+    assert_eq!(raw.lines[1], Line::P((positions..positions + 4).collect()));
+    assert_eq!(
+        raw.lines[2],
+        Line::P((positions + 4..positions + 11).collect())
+    );
+    assert_eq!(raw.positions.len(), positions + 4 + 7);
+    let (x, y, z, _) = raw.positions[positions + 1];
+    assert_close([x, y, z], [1.0 / 3.0, 2.0 / 3.0, 0.0]);
+
+    // Colors are padded with white
+    assert_eq!(raw.colors.len(), raw.positions.len());
+    assert_eq!(raw.colors[positions], (1.0, 1.0, 1.0));
+
+    assert_eq!(raw.meshes["rail"].lines, [Range { start: 1, end: 3 }]);
+    assert_eq!(raw.groups["default"].lines, [Range { start: 0, end: 1 }]);
+
+    // `curv2` curves and surfaces are kept, and trimming curves are renumbered
+    assert_eq!(raw.free_forms.len(), 2);
+    assert!(raw.free_forms[0].is_curve2());
+    assert_eq!(raw.free_forms[1].trims[0][0].curve, 0);
+
+    // Overridden techniques don't care about `ctech`
+    let mut raw = parse_obj(input.as_bytes())?;
+    let method = CurveApproximation::Override(CurveTechnique::Parametric(1.0));
+    raw.approximate_curves(method)?;
+    assert_eq!(raw.lines[1], Line::P((positions..positions + 2).collect()));
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> TestResult {
+    let test_cases = [
+        // 6 control points don't make whole cubic segments
+        "cstype bezier\ndeg 3\ncurv 0 2 1 2 3 4 5 6\nparm u 0 1 2",
+        "cstype bezier\ndeg 3\ncurv 0 2 1 2 3 4 5 6 7\nparm u 0 2",
+        "cstype bezier\ndeg 3\ncurv 0 2 1 2 3 4 5 6 7\nparm u 0 1 1",
+        "cstype bezier\ndeg 3\ncurv 3 4 1 2 3 4 5 6 7\nparm u 0 1 2",
+        "cstype bezier\ndeg 3\ncurv -inf 2 1 2 3 4 5 6 7\nparm u 0 1 2",
+        "cstype bspline\ndeg 1\ncurv 0 1 1 2\nparm u -inf -inf 1 1",
+        // Huge degrees are rejected before allocating anything of their size
+        "cstype bezier\ndeg 4000000000\ncurv 0 1 1 2\nparm u 0 1",
+        "cstype taylor\ndeg 18446744073709551615\ncurv 0 1 1 2\nparm u 0 1",
+        "cstype bspline\ndeg 18446744073709551615\ncurv 0 1 1 2\nparm u 0 0 1 1",
+        "cstype cardinal\ndeg 2\ncurv 0 1 1 2 3\nparm u 0 1",
+        "cstype bmatrix\ndeg 1\nbmat u 1 0 0\nstep 1\ncurv 0 1 1 2\nparm u 0 1",
+        "cstype bmatrix\ndeg 1\nbmat u 1 -1 0 1\ncurv 0 1 1 2\nparm u 0 1",
+        "cstype bspline\ndeg 2\ncurv 0 1 1 2\nparm u 0 0 0 1 1 1",
+        "cstype rat bezier\ndeg 1\ncurv 0 1 1 2\nparm u 0 1\nend\nv 1 1 1 -1\ncurv 0 1 1 8\nparm u 0 1",
+        "cstype bspline\ndeg 1 1\nsurf 0 1 0 1 1 2 3 4\nparm u 0 0 1 1\nparm v 0 0 1 1",
+    ];
+
+    let method = CurveApproximation::Ctech(CurveTechnique::Parametric(1.0));
+    for input in &test_cases {
+        let mut raw = curve(input)?;
+        let free_form = raw.free_forms.last().unwrap();
+        match Curve::new(&raw, free_form) {
+            Err(ObjError::Load(e)) => {
+                assert_eq!(e.kind(), &LoadErrorKind::InsufficientData, "{}", input)
+            }
+            _ => panic!("Expected an error from {:?}", input),
+        }
+
+        let expected = raw.clone();
+        if raw.approximate_curves(method).is_ok() {
+            // Surfaces are left as they are
+            assert!(input.contains("surf"));
+        }
+        assert_eq!(raw, expected);
+    }
+
+    Ok(())
+}
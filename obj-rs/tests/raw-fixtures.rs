@@ -0,0 +1,204 @@
+//! Parses every fixture and checks the consistency of the result.
+//!
+//! The fixtures are embedded with `include_bytes!`, so that this suite also runs under Miri
+//! without file system access:
+//!
+//! ```sh
+//! cargo +nightly miri test -p obj-rs --test raw-fixtures
+//! ```
+
+use obj::raw::object::{Group, Line, Polygon, Range};
+use obj::raw::IndexMap;
+use obj::raw::{parse_mtl, parse_obj, visit_obj, ObjVisitor, RawObj, Triangulation};
+use obj::ObjResult;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Returns the ranges of one kind of elements.
+type Ranges = fn(&Group) -> &Vec<Range>;
+
+const OBJ_FIXTURES: &[(&str, &[u8])] = &[
+    ("cube.obj", include_bytes!("fixtures/cube.obj")),
+    ("dome.obj", include_bytes!("fixtures/dome.obj")),
+    ("empty.obj", include_bytes!("fixtures/empty.obj")),
+    ("group.obj", include_bytes!("fixtures/group.obj")),
+    (
+        "lines_points.obj",
+        include_bytes!("fixtures/lines_points.obj"),
+    ),
+    (
+        "normal-cone.obj",
+        include_bytes!("fixtures/normal-cone.obj"),
+    ),
+    (
+        "textured-cube.obj",
+        include_bytes!("fixtures/textured-cube.obj"),
+    ),
+    ("untitled.obj", include_bytes!("fixtures/untitled.obj")),
+];
+
+const MTL_FIXTURES: &[(&str, &[u8])] = &[
+    ("cube.mtl", include_bytes!("fixtures/cube.mtl")),
+    ("untitled.mtl", include_bytes!("fixtures/untitled.mtl")),
+];
+
+/// Checks that the ranges of every group are sorted, disjoint, not empty and within `len`
+/// elements.
+fn check_ranges(name: &str, ranges: &[Range], len: usize) {
+    let mut previous_end = None;
+    for range in ranges {
+        assert!(range.start < range.end, "{}: empty range {:?}", name, range);
+        assert!(range.end <= len, "{}: {:?} out of {}", name, range, len);
+        if let Some(end) = previous_end {
+            assert!(
+                end < range.start,
+                "{}: {:?} isn't after {}",
+                name,
+                range,
+                end
+            );
+        }
+        previous_end = Some(range.end);
+    }
+}
+
+fn check_groups<K: Debug>(name: &str, groups: &IndexMap<K, Group>, raw: &RawObj) {
+    for (key, group) in groups {
+        let name = format!("{} {:?}", name, key);
+        check_ranges(&name, &group.points, raw.points.len());
+        check_ranges(&name, &group.lines, raw.lines.len());
+        check_ranges(&name, &group.polygons, raw.polygons.len());
+    }
+}
+
+/// Checks that every element refers to existing vertex data, and that every group is consistent.
+fn check(name: &str, raw: &RawObj) {
+    let positions = raw.positions.len();
+    let tex_coords = raw.tex_coords.len();
+    let normals = raw.normals.len();
+
+    for &p in &raw.points {
+        assert!(p < positions, "{}: point {} out of bounds", name, p);
+    }
+    for line in &raw.lines {
+        let in_bounds = match line {
+            Line::P(vec) => vec.iter().all(|&p| p < positions),
+            Line::PT(vec) => vec.iter().all(|&(p, t)| p < positions && t < tex_coords),
+        };
+        assert!(in_bounds, "{}: line {:?} out of bounds", name, line);
+    }
+    for polygon in &raw.polygons {
+        let in_bounds = match polygon {
+            Polygon::P(vec) => vec.iter().all(|&p| p < positions),
+            Polygon::PT(vec) => vec.iter().all(|&(p, t)| p < positions && t < tex_coords),
+            Polygon::PN(vec) => vec.iter().all(|&(p, n)| p < positions && n < normals),
+            Polygon::PTN(vec) => vec
+                .iter()
+                .all(|&(p, t, n)| p < positions && t < tex_coords && n < normals),
+        };
+        assert!(in_bounds, "{}: polygon {:?} out of bounds", name, polygon);
+    }
+
+    check_groups(name, &raw.groups, raw);
+    check_groups(name, &raw.meshes, raw);
+    check_groups(name, &raw.smoothing_groups, raw);
+    check_groups(name, &raw.merging_groups, raw);
+    check_groups(name, &raw.objects, raw);
+
+    // Every element has a material, even if it's the unnamed one
+    let kinds: [(usize, Ranges); 3] = [
+        (raw.points.len(), |group| &group.points),
+        (raw.lines.len(), |group| &group.lines),
+        (raw.polygons.len(), |group| &group.polygons),
+    ];
+    for &(len, ranges) in &kinds {
+        let covered: usize = raw
+            .meshes
+            .values()
+            .flat_map(ranges)
+            .map(|range| range.end - range.start)
+            .sum();
+        assert_eq!(covered, len, "{}: elements without material", name);
+    }
+}
+
+#[test]
+fn obj_fixtures() -> TestResult {
+    for &(name, input) in OBJ_FIXTURES {
+        let mut raw = parse_obj(input)?;
+        check(name, &raw);
+
+        raw.triangulate(Triangulation::EarClipping);
+        check(name, &raw);
+    }
+
+    Ok(())
+}
+
+/// Counts the statements of each kind.
+#[derive(Default)]
+struct Statements(HashMap<&'static str, usize>);
+
+impl Statements {
+    fn add(&mut self, kind: &'static str) -> ObjResult<()> {
+        *self.0.entry(kind).or_default() += 1;
+        Ok(())
+    }
+}
+
+impl ObjVisitor for Statements {
+    fn position(&mut self, _: f32, _: f32, _: f32, _: f32) -> ObjResult<()> {
+        self.add("v")
+    }
+
+    fn point(&mut self, _: usize) -> ObjResult<()> {
+        self.add("p")
+    }
+
+    fn line(&mut self, _: Line) -> ObjResult<()> {
+        self.add("l")
+    }
+
+    fn face(&mut self, _: Polygon) -> ObjResult<()> {
+        self.add("f")
+    }
+}
+
+#[test]
+fn visit_fixtures() -> TestResult {
+    for &(name, input) in OBJ_FIXTURES {
+        let raw = parse_obj(input)?;
+        let mut statements = Statements::default();
+        visit_obj(input, &mut statements)?;
+
+        let count = |kind| statements.0.get(kind).copied().unwrap_or_default();
+        assert_eq!(count("v"), raw.positions.len(), "{}", name);
+        assert_eq!(count("p"), raw.points.len(), "{}", name);
+        assert_eq!(count("l"), raw.lines.len(), "{}", name);
+        assert_eq!(count("f"), raw.polygons.len(), "{}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn mtl_fixtures() -> TestResult {
+    for &(name, input) in MTL_FIXTURES {
+        let mtl = parse_mtl(input)?;
+        assert!(!mtl.materials.is_empty(), "{} has no material", name);
+    }
+
+    Ok(())
+}
+
+/// The large fixture takes too long under Miri.
+#[test]
+#[cfg_attr(miri, ignore)]
+fn sponza() -> TestResult {
+    let raw = parse_obj(&include_bytes!("fixtures/sponza.obj")[..])?;
+    check("sponza.obj", &raw);
+
+    Ok(())
+}
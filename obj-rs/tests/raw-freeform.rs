@@ -0,0 +1,454 @@
+use obj::raw::freeform::{
+    Basis, Connection, Direction, FreeForm, Geometry, SurfaceTechnique, TrimCurve,
+};
+use obj::raw::object::{visit_obj, write_obj, ObjVisitor};
+use obj::raw::{parse_obj, parse_obj_with, ParseOptions};
+use obj::{LoadErrorKind, ObjError, ObjResult};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const SURFACE: &str = r#"
+v -1 -1 0
+v 1 -1 0
+v -1 1 0
+v 1 1 0 2
+vt 0 0
+vn 0 0 1
+vp 0 0
+vp 1 0
+vp 1 1
+vp 0 1
+vp 0.5 0.5
+
+cstype bezier
+deg 3
+curv 0 1 1 2 3 4
+end
+
+cstype bspline
+deg 1
+curv2 1 2 3 4 1
+parm u 0 0 1 2 3 4 4
+end
+curv2 -5 -4
+parm u 0 0 1 1
+end
+
+cstype rat bspline
+deg 1 1
+step 1 1
+surf 0 1 0 1 1/1/1 2//1 3/1 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+trim 0 4 1
+hole 0 1 -1
+scrv 0 1 2 0 0.5 1
+sp 5
+end
+"#;
+
+#[test]
+fn parse() -> TestResult {
+    let raw = parse_obj(SURFACE.as_bytes())?;
+    assert_eq!(raw.free_forms.len(), 4);
+
+    let curve = &raw.free_forms[0];
+    assert_eq!(
+        curve.geometry,
+        Geometry::Curve {
+            start: 0.0,
+            end: 1.0,
+            control_points: vec![0, 1, 2, 3],
+        }
+    );
+    assert_eq!((curve.rational, curve.basis), (false, Basis::Bezier));
+    assert_eq!(curve.degree, [3, 0]);
+    assert_eq!(curve.step, [0, 0]);
+
+    let curve2 = &raw.free_forms[2];
+    assert_eq!(
+        curve2.geometry,
+        Geometry::Curve2 {
+            control_points: vec![0, 1],
+        }
+    );
+    assert_eq!(curve2.basis, Basis::BSpline);
+    assert_eq!(curve2.parameters, [vec![0.0, 0.0, 1.0, 1.0], vec![]]);
+
+    let surface = &raw.free_forms[3];
+    assert_eq!(
+        *surface,
+        FreeForm {
+            geometry: Geometry::Surface {
+                s: (0.0, 1.0),
+                t: (0.0, 1.0),
+                control_points: vec![
+                    (0, Some(0), Some(0)),
+                    (1, None, Some(0)),
+                    (2, Some(0), None),
+                    (3, None, None),
+                ],
+            },
+            rational: true,
+            basis: Basis::BSpline,
+            degree: [1, 1],
+            basis_matrix: [vec![], vec![]],
+            step: [1, 1],
+            technique: None,
+            curve_technique: None,
+            material: String::new(),
+            parameters: [vec![0.0, 0.0, 1.0, 1.0], vec![0.0, 0.0, 1.0, 1.0]],
+            // Trimming curves refer to the indices in `free_forms`
+            trims: vec![vec![TrimCurve {
+                start: 0.0,
+                end: 4.0,
+                curve: 1,
+            }]],
+            holes: vec![vec![TrimCurve {
+                start: 0.0,
+                end: 1.0,
+                curve: 2,
+            }]],
+            special_curves: vec![vec![
+                TrimCurve {
+                    start: 0.0,
+                    end: 1.0,
+                    curve: 2,
+                },
+                TrimCurve {
+                    start: 0.0,
+                    end: 0.5,
+                    curve: 1,
+                },
+            ]],
+            special_points: vec![4],
+        }
+    );
+
+    // Free forms aren't elements of any group
+    assert!(raw.groups.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn basis_matrix() -> TestResult {
+    let input = r#"
+v 0 0 0
+v 1 0 0
+cstype bmatrix
+deg 1
+bmat u 1 -1 0 1
+step 1
+curv 0 1 1 2
+end
+"#;
+    let raw = parse_obj(input.as_bytes())?;
+    let curve = &raw.free_forms[0];
+    assert_eq!(curve.basis, Basis::BasisMatrix);
+    assert_eq!(curve.basis_matrix, [vec![1.0, -1.0, 0.0, 1.0], vec![]]);
+    assert_eq!(curve.step, [1, 0]);
+
+    Ok(())
+}
+
+#[test]
+fn missing_end() -> TestResult {
+    let input = b"vp 0\nvp 1\ncstype bezier\ndeg 1\ncurv2 1 2\nend\ncurv2 1 2\nparm u 0 1";
+    match parse_obj(&input[..]) {
+        Err(ObjError::Load(e)) => {
+            assert_eq!(e.kind(), &LoadErrorKind::UnexpectedStatement);
+            assert_eq!(e.location().line, Some(7));
+            assert_eq!(e.location().statement.as_deref(), Some("curv2"));
+        }
+        _ => panic!("A missing `end` should be reported"),
+    }
+
+    // The unfinished curve is skipped in lenient mode
+    let raw = parse_obj_with(&input[..], &ParseOptions::new().lenient(true))?;
+    assert_eq!(raw.free_forms.len(), 1);
+    assert_eq!(raw.warnings.len(), 1);
+    assert_eq!(raw.warnings[0].line, 7);
+    assert_eq!(raw.warnings[0].statement, "curv2");
+
+    Ok(())
+}
+
+/// Records the curves of `trim`, `hole` and `scrv` statements, and `con` statements.
+#[derive(Default)]
+struct Loops {
+    curves: Vec<(f32, f32, usize)>,
+    directions: Vec<Direction>,
+    connections: Vec<Connection>,
+}
+
+impl Loops {
+    fn add(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        let curves = curves.iter().map(|c| (c.start, c.end, c.curve));
+        self.curves.extend(curves);
+        Ok(())
+    }
+}
+
+impl ObjVisitor for Loops {
+    fn parameters(&mut self, direction: Direction, _: &[f32]) -> ObjResult<()> {
+        self.directions.push(direction);
+        Ok(())
+    }
+
+    fn trim(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        self.add(curves)
+    }
+
+    fn hole(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        self.add(curves)
+    }
+
+    fn special_curve(&mut self, curves: &[TrimCurve]) -> ObjResult<()> {
+        self.add(curves)
+    }
+
+    fn connection(&mut self, connection: &Connection) -> ObjResult<()> {
+        self.connections.push(*connection);
+        Ok(())
+    }
+}
+
+#[test]
+fn visit() -> TestResult {
+    let mut loops = Loops::default();
+    visit_obj(SURFACE.as_bytes(), &mut loops)?;
+
+    // Visitors receive the indices among the `curv2` statements
+    assert_eq!(
+        loops.curves,
+        [(0.0, 4.0, 0), (0.0, 1.0, 1), (0.0, 1.0, 1), (0.0, 0.5, 0)]
+    );
+    assert_eq!(
+        loops.directions,
+        [Direction::U, Direction::U, Direction::U, Direction::V]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn errors() {
+    let curve2 = "vp 0\nvp 1\ncstype bezier\ndeg 1\ncurv2 1 2\n";
+    let test_cases = [
+        ("parm u 0 1".to_string(), LoadErrorKind::UnexpectedStatement),
+        ("end".to_string(), LoadErrorKind::UnexpectedStatement),
+        (
+            "vp 0\nvp 1\ncurv2 1 2".to_string(),
+            LoadErrorKind::UnexpectedStatement,
+        ),
+        (
+            format!("{}curv2 1 2", curve2),
+            LoadErrorKind::UnexpectedStatement,
+        ),
+        // The curve has to be ended before it trims anything
+        (
+            format!("{}trim 0 1 1", curve2),
+            LoadErrorKind::IndexOutOfRange,
+        ),
+        (
+            format!("{}end\ntrim 0 1 2", curve2),
+            LoadErrorKind::IndexOutOfRange,
+        ),
+        (
+            "cstype bezier\ndeg 1\ncurv2 1 2".to_string(),
+            LoadErrorKind::IndexOutOfRange,
+        ),
+        (
+            "trim 0 1".to_string(),
+            LoadErrorKind::WrongNumberOfArguments,
+        ),
+        ("trim".to_string(), LoadErrorKind::WrongNumberOfArguments),
+        (
+            "curv 0 1 1".to_string(),
+            LoadErrorKind::WrongNumberOfArguments,
+        ),
+        ("curv2 1".to_string(), LoadErrorKind::WrongNumberOfArguments),
+        (
+            "surf 0 1 0 1".to_string(),
+            LoadErrorKind::WrongNumberOfArguments,
+        ),
+        (
+            "parm u 0".to_string(),
+            LoadErrorKind::WrongNumberOfArguments,
+        ),
+        ("sp".to_string(), LoadErrorKind::WrongNumberOfArguments),
+        (
+            format!("{}end\ncon 1 0 1 1 1 0 1", curve2),
+            LoadErrorKind::WrongNumberOfArguments,
+        ),
+        // Both surfaces have to be ended before they're connected
+        (
+            format!("{}end\ncon 1 0 1 1 1 0 1 1", curve2),
+            LoadErrorKind::IndexOutOfRange,
+        ),
+        (
+            format!(
+                "{}end\nv 0 0 0\ndeg 1 1\nsurf 0 1 0 1 1 1 1 1\ncon 1 0 1 1 1 0 1 1",
+                curve2
+            ),
+            LoadErrorKind::IndexOutOfRange,
+        ),
+        ("end 1".to_string(), LoadErrorKind::WrongNumberOfArguments),
+        (
+            "step 1 2 3".to_string(),
+            LoadErrorKind::WrongNumberOfArguments,
+        ),
+        ("bmat w 1".to_string(), LoadErrorKind::WrongTypeOfArguments),
+        (
+            "v 0 0 0\nsurf 0 1 0 1 1/1/1/1".to_string(),
+            LoadErrorKind::WrongTypeOfArguments,
+        ),
+    ];
+
+    for (input, expected) in &test_cases {
+        match parse_obj(input.as_bytes()) {
+            Err(ObjError::Load(e)) => assert_eq!(e.kind(), expected, "{}", input),
+            _ => panic!("Expected an error from {:?}", input),
+        }
+    }
+}
+
+#[test]
+fn lenient() -> TestResult {
+    let input = r#"
+vp 0
+vp 1
+cstype bezier
+curv2 1 2
+deg 1
+curv2 1 2
+parm u 0
+trim 0 1 1
+end
+end
+"#;
+    let raw = parse_obj_with(input.as_bytes(), &ParseOptions::new().lenient(true))?;
+    assert_eq!(raw.free_forms.len(), 1);
+    assert!(raw.free_forms[0].trims.is_empty());
+
+    let warnings: Vec<_> = raw.warnings.iter().map(|w| w.line).collect();
+    assert_eq!(warnings, [5, 8, 9, 11]);
+
+    Ok(())
+}
+
+#[test]
+fn write() -> TestResult {
+    let raw = parse_obj(SURFACE.as_bytes())?;
+    let mut output = Vec::new();
+    write_obj(&raw, &mut output)?;
+    assert_eq!(parse_obj(&output[..])?, raw);
+
+    let input = "v 0 0 0\nv 1 0 0\nvp 0 0\nvp 1 0\n\
+                 cstype rat bspline\ndeg 1\ncurv2 1 2\nparm u 0 0 1 1\nend\n\
+                 deg 1 1\nbmat v 1 0 0 1\nstep 2 2\nsurf 0 1 0 1 1 2 2 1\n\
+                 trim 0 1 1 1 0 1\nsp 2\nend\n";
+    let raw = parse_obj(input.as_bytes())?;
+    let mut output = Vec::new();
+    write_obj(&raw, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, input);
+
+    Ok(())
+}
+
+#[test]
+fn connections() -> TestResult {
+    let input = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvp 0 0\nvp 1 0\n\
+                 cstype bspline\ndeg 1\ncurv2 1 2\nparm u 0 0 1 1\nend\n\
+                 deg 1 1\nsurf 0 1 0 1 1 2 3 1\nend\n\
+                 deg 1\ncurv2 2 1\nparm u 0 0 1 1\nend\n\
+                 deg 1 1\nsurf 0 1 0 1 2 3 1 2\nend\n\
+                 con 1 0 1 1 2 1 0 2\n";
+    let raw = parse_obj(input.as_bytes())?;
+    let expected = Connection {
+        surfaces: [1, 3],
+        curves: [
+            TrimCurve {
+                start: 0.0,
+                end: 1.0,
+                curve: 0,
+            },
+            TrimCurve {
+                start: 1.0,
+                end: 0.0,
+                curve: 2,
+            },
+        ],
+    };
+    assert_eq!(raw.connections, [expected]);
+
+    // Relative indices count `surf` and `curv2` statements backwards
+    let relative = input.replace("con 1 0 1 1 2 1 0 2", "con -2 0 1 -2 -1 1 0 -1");
+    assert_eq!(parse_obj(relative.as_bytes())?, raw);
+
+    // Visitors receive the indices among the `surf` and `curv2` statements
+    let mut loops = Loops::default();
+    visit_obj(input.as_bytes(), &mut loops)?;
+    let mut visited = expected;
+    visited.surfaces = [0, 1];
+    visited.curves[1].curve = 1;
+    assert_eq!(loops.connections, [visited]);
+
+    let mut output = Vec::new();
+    write_obj(&raw, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, input);
+
+    Ok(())
+}
+
+#[test]
+fn technique_and_material() -> TestResult {
+    let input = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvp 0 0\nvp 1 0\nf 1 2 3\n\
+                 cstype bezier\ndeg 1\ncurv2 1 2\nend\n\
+                 usemtl red\nf 3 2 1\nstech cparma 2 3\ncurv 0 1 1 2\nend\n\
+                 usemtl blue\nstech curv 0.1 5\ncurv 0 1 2 1\nend\n";
+    let raw = parse_obj(input.as_bytes())?;
+
+    let attributes: Vec<_> = raw
+        .free_forms
+        .iter()
+        .map(|free_form| (free_form.material.as_str(), free_form.technique))
+        .collect();
+    assert_eq!(
+        attributes,
+        [
+            ("", None),
+            (
+                "red",
+                Some(SurfaceTechnique::ParametricA { u: 2.0, v: 3.0 })
+            ),
+            (
+                "blue",
+                Some(SurfaceTechnique::Curvature {
+                    distance: 0.1,
+                    angle: 5.0
+                })
+            ),
+        ]
+    );
+
+    // Free forms are written after the elements with the same kind of material
+    let mut output = Vec::new();
+    write_obj(&raw, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, input);
+
+    for (input, expected) in &[
+        ("stech cparmb", LoadErrorKind::WrongNumberOfArguments),
+        ("stech curv 1", LoadErrorKind::WrongNumberOfArguments),
+        ("stech cspace 1 2", LoadErrorKind::WrongNumberOfArguments),
+        ("stech cparm 1", LoadErrorKind::WrongTypeOfArguments),
+    ] {
+        match parse_obj(input.as_bytes()) {
+            Err(ObjError::Load(e)) => assert_eq!(e.kind(), expected, "{}", input),
+            _ => panic!("Expected an error from {:?}", input),
+        }
+    }
+
+    Ok(())
+}
@@ -64,3 +64,59 @@ fn dup_groupnames() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn adjacent_ranges() -> TestResult {
+    // Restarting a group right after it has been left starts a new range, even if nothing comes
+    // between them
+    let input = b"v 0 0 0\nf 1 1 1\ng a\nf 1 1 1\ng b\ng a\nf 1 1 1\ng b\nf 1 1 1\ng a\nf 1 1 1";
+    let raw = parse_obj(&input[..])?;
+
+    test! {
+        raw.groups.len(),                   3
+        raw.groups["default"].polygons,     vec![Range { start: 0, end: 1 }]
+        raw.groups["a"].polygons,           vec![Range { start: 1, end: 2 }, Range { start: 2, end: 3 }, Range { start: 4, end: 5 }]
+        raw.groups["b"].polygons,           vec![Range { start: 3, end: 4 }]
+    }
+
+    Ok(())
+}
+
+#[test]
+fn multiple_names() -> TestResult {
+    // Elements belong to every group named by `g`, and `g` alone starts the default group
+    let input = b"v 0 0 0\ng wall left\nf 1 1 1\ng left floor\nf 1 1 1\nl 1 1\ng\np 1\ng a a\np 1";
+    let raw = parse_obj(&input[..])?;
+
+    test! {
+        raw.groups.len(),                   5
+        raw.groups["wall"].polygons,        vec![Range { start: 0, end: 1 }]
+        raw.groups["left"].polygons,        vec![Range { start: 0, end: 2 }]
+        raw.groups["left"].lines,           vec![Range { start: 0, end: 1 }]
+        raw.groups["floor"].polygons,       vec![Range { start: 1, end: 2 }]
+        raw.groups["floor"].lines,          vec![Range { start: 0, end: 1 }]
+        raw.groups["default"].points,       vec![Range { start: 0, end: 1 }]
+        raw.groups["default"].polygons,     vec![]
+        raw.groups["a"].points,             vec![Range { start: 1, end: 2 }]
+    }
+
+    Ok(())
+}
+
+#[test]
+fn first_element_order() -> TestResult {
+    // Groups are kept in the order of their first element, even if they were declared before
+    let input = b"v 0 0 0\ng empty\ng z\nusemtl y\ns 9\np 1\ng a\nusemtl x\ns 2\np 1\ng empty z\nusemtl y\ns 9\np 1";
+    let raw = parse_obj(&input[..])?;
+
+    let groups: Vec<_> = raw.groups.keys().map(String::as_str).collect();
+    let meshes: Vec<_> = raw.meshes.keys().map(String::as_str).collect();
+    let smoothing_groups: Vec<_> = raw.smoothing_groups.keys().copied().collect();
+    test! {
+        groups,             vec!["z", "a", "empty"]
+        meshes,             vec!["y", "x"]
+        smoothing_groups,   vec![9, 2]
+    }
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use obj::raw::object::Polygon;
+use obj::raw::{parse_mtl_with, parse_obj_with, ParseOptions};
+use obj::{LoadErrorKind, ObjError};
+use std::io::Cursor;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const OBJ: &str = r#"
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+#MRGB ffffffff
+vx 1 2 3
+v 1 x 1
+f 1 2 3
+f 1 3 9
+f 1 3 4
+"#;
+
+#[test]
+fn strict() {
+    match parse_obj_with(Cursor::new(OBJ), &ParseOptions::new()) {
+        Err(ObjError::Load(e)) => {
+            assert_eq!(*e.kind(), LoadErrorKind::UnexpectedStatement);
+            assert_eq!(e.location().line, Some(7));
+        }
+        _ => panic!("Strict mode should stop at the unknown statement"),
+    }
+}
+
+#[test]
+fn lenient_obj() -> TestResult {
+    let raw = parse_obj_with(Cursor::new(OBJ), &ParseOptions::new().lenient(true))?;
+
+    assert_eq!(raw.positions.len(), 4);
+    assert_eq!(
+        raw.polygons,
+        vec![Polygon::P(vec![0, 1, 2]), Polygon::P(vec![0, 2, 3])]
+    );
+
+    let warnings: Vec<_> = raw
+        .warnings
+        .iter()
+        .map(|w| (w.line, w.statement.as_str(), w.token.as_deref(), w.kind))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (7, "vx", None, Some(LoadErrorKind::UnexpectedStatement)),
+            (8, "v", Some("x"), None),
+            (10, "f", Some("9"), Some(LoadErrorKind::IndexOutOfRange)),
+        ]
+    );
+    assert_eq!(
+        raw.warnings[2].to_string(),
+        "Skipped statement: Received index value out of range: Too big index value \
+         (line 10, statement `f`, token `9`)"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn lenient_io_error() {
+    // Errors which aren't caused by a single statement still fail the parse
+    let result = parse_obj_with(
+        Cursor::new("v 0 0 0\nf 1 1 \\"),
+        &ParseOptions::new().lenient(true),
+    );
+    match result {
+        Err(ObjError::Load(e)) => assert_eq!(*e.kind(), LoadErrorKind::BackslashAtEOF),
+        _ => panic!("Shouldn't success"),
+    }
+}
+
+#[test]
+fn lenient_mtl() -> TestResult {
+    let input = r#"
+newmtl first
+Kd 1 0 0
+Pr 0.5
+Pm 0.1
+Ns abc
+
+newmtl
+Kd 0 1 0
+
+newmtl second
+d 0.5
+"#;
+    let mtl = parse_mtl_with(Cursor::new(input), &ParseOptions::new().lenient(true))?;
+
+    assert_eq!(mtl.materials.len(), 2);
+    assert_eq!(mtl.materials["first"].specular_exponent, None);
+    assert_eq!(mtl.materials["second"].diffuse, None);
+    assert_eq!(mtl.materials["second"].dissolve, Some(0.5));
+
+    // The property after the malformed `newmtl` is skipped with a warning as well
+    let lines: Vec<_> = mtl.warnings.iter().map(|w| w.line).collect();
+    assert_eq!(lines, vec![4, 5, 6, 8, 9]);
+    assert_eq!(mtl.warnings[4].statement, "Kd");
+    assert_eq!(
+        mtl.warnings[4].kind,
+        Some(LoadErrorKind::UnexpectedStatement)
+    );
+
+    Ok(())
+}
@@ -1,4 +1,8 @@
-use obj::raw::material::{parse_mtl, Material, MtlColor, MtlTextureMap, RawMtl};
+#![allow(clippy::excessive_precision)]
+
+use obj::raw::material::{
+    parse_mtl, write_material, write_mtl, Material, MtlColor, MtlTextureMap, RawMtl,
+};
 use obj::ObjResult;
 use std::error::Error;
 
@@ -85,3 +89,136 @@ fn untitled() -> TestResult {
 
     Ok(())
 }
+
+fn round_trip(mtl: &RawMtl) -> Result<(String, RawMtl), Box<dyn Error>> {
+    let mut output = Vec::new();
+    write_mtl(mtl, &mut output)?;
+    let parsed = parse_mtl(&output[..])?;
+    Ok((String::from_utf8(output)?, parsed))
+}
+
+#[test]
+fn write_fixtures() -> TestResult {
+    for name in &["cube.mtl", "untitled.mtl"] {
+        let mtl = fixture(name)?;
+        let (_, parsed) = round_trip(&mtl)?;
+        assert_eq!(parsed, mtl, "{} isn't written losslessly", name);
+    }
+
+    // Materials are written in the order of declaration
+    let (output, _) = round_trip(&fixture("untitled.mtl")?)?;
+    assert_eq!(
+        output,
+        "newmtl Material
+Ka 0 0 0
+Kd 0.64 0.64 0.64
+Ks 0.5 0.5 0.5
+Ns 96.07843
+Ni 1
+d 1
+illum 2
+
+newmtl None
+Ka 0 0 0
+Kd 0.8 0.8 0.8
+Ks 0.8 0.8 0.8
+Ns 0
+d 1
+illum 2
+"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_every_field() -> TestResult {
+    let map = |file: &str| {
+        Some(MtlTextureMap {
+            file: file.to_string(),
+        })
+    };
+    let material = Material {
+        ambient: Some(MtlColor::Rgb(0.1, 0.2, 0.3)),
+        diffuse: Some(MtlColor::Xyz(0.4, 0.5, 0.6)),
+        specular: Some(MtlColor::Spectral("metal.rfl".to_string(), 1.0)),
+        emissive: Some(MtlColor::Spectral("glow.rfl".to_string(), 0.5)),
+        transmission_filter: Some(MtlColor::Rgb(1.0, 1.0, 1.0)),
+        illumination_model: Some(4),
+        dissolve: Some(0.25),
+        specular_exponent: Some(10.0),
+        optical_density: Some(1.5),
+        ambient_map: map("ambient.png"),
+        diffuse_map: map("diffuse.png"),
+        specular_map: map("specular.png"),
+        emissive_map: map("emissive.png"),
+        dissolve_map: map("dissolve.png"),
+        bump_map: map("bump.png"),
+    };
+
+    let mut output = Vec::new();
+    write_material("everything", &material, &mut output)?;
+    assert_eq!(
+        String::from_utf8(output.clone())?,
+        "newmtl everything
+Ka 0.1 0.2 0.3
+Kd xyz 0.4 0.5 0.6
+Ks spectral metal.rfl 1
+Ke spectral glow.rfl 0.5
+Tf 1 1 1
+Ns 10
+Ni 1.5
+d 0.25
+illum 4
+map_Ka ambient.png
+map_Kd diffuse.png
+map_Ks specular.png
+map_Ke emissive.png
+map_d dissolve.png
+bump bump.png
+"
+    );
+
+    let parsed = parse_mtl(&output[..])?;
+    assert_eq!(parsed.materials.len(), 1);
+    assert_eq!(parsed.materials["everything"], material);
+
+    Ok(())
+}
+
+#[test]
+fn properties_before_newmtl() -> TestResult {
+    // Properties before the first `newmtl` belong to the first material
+    let mtl = parse_mtl(&b"Kd 1 0 0\nnewmtl red\nd 0.5\nnewmtl blue\nKd 0 0 1\n"[..])?;
+
+    assert_eq!(mtl.materials.len(), 2);
+    assert_eq!(
+        mtl.materials["red"].diffuse,
+        Some(MtlColor::Rgb(1.0, 0.0, 0.0))
+    );
+    assert_eq!(mtl.materials["red"].dissolve, Some(0.5));
+    assert_eq!(
+        mtl.materials["blue"].diffuse,
+        Some(MtlColor::Rgb(0.0, 0.0, 1.0))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn declaration_order() -> TestResult {
+    let input = b"newmtl zinc\nKd 1 1 1\nnewmtl iron\nKd 0 0 0\nnewmtl copper\nKd 1 0 0\n";
+    let mtl = parse_mtl(&input[..])?;
+
+    let names: Vec<_> = mtl.materials.keys().map(String::as_str).collect();
+    assert_eq!(names, ["zinc", "iron", "copper"]);
+
+    let (output, parsed) = round_trip(&mtl)?;
+    assert_eq!(
+        output,
+        "newmtl zinc\nKd 1 1 1\n\nnewmtl iron\nKd 0 0 0\n\nnewmtl copper\nKd 1 0 0\n"
+    );
+    assert!(parsed.materials.keys().eq(mtl.materials.keys()));
+
+    Ok(())
+}
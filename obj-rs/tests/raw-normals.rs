@@ -0,0 +1,133 @@
+use obj::raw::object::Polygon;
+use obj::raw::{parse_obj, NormalGeneration, RawObj};
+use obj::{
+    load_obj, load_obj_with, LoadErrorKind, LoadOptions, Obj, ObjError, TexturedVertex,
+    Triangulation, Vertex,
+};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Two triangles which meet at a right angle along the edge between vertex 1 and 2.
+fn folded(smoothing: &str) -> String {
+    format!(
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 -1\n{}\nf 1 2 3\n{}\nf 2 1 4\n",
+        smoothing.split('|').next().unwrap(),
+        smoothing.split('|').nth(1).unwrap(),
+    )
+}
+
+fn normal_of(raw: &RawObj, polygon: usize, corner: usize) -> [f32; 3] {
+    let n = match raw.polygons[polygon] {
+        Polygon::PN(ref vec) => raw.normals[vec[corner].1],
+        ref polygon => panic!("Unexpected polygon {:?}", polygon),
+    };
+    [n.0, n.1, n.2]
+}
+
+fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+    for axis in 0..3 {
+        assert!(
+            (actual[axis] - expected[axis]).abs() < 1e-5,
+            "{:?} should be {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn flat() -> TestResult {
+    let mut raw = parse_obj(Cursor::new(folded("s 1|s 1")))?;
+    raw.generate_normals(NormalGeneration::Flat);
+
+    assert_eq!(raw.normals.len(), 2);
+    for corner in 0..3 {
+        assert_close(normal_of(&raw, 0, corner), [0.0, 0.0, 1.0]);
+        assert_close(normal_of(&raw, 1, corner), [0.0, -1.0, 0.0]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn smooth_same_group() -> TestResult {
+    let mut raw = parse_obj(Cursor::new(folded("s 1|s 1")))?;
+    raw.generate_normals(NormalGeneration::Smooth);
+
+    // Vertices on the shared edge get the average, others keep the face normal
+    let h = std::f32::consts::FRAC_1_SQRT_2;
+    assert_close(normal_of(&raw, 0, 0), [0.0, -h, h]);
+    assert_close(normal_of(&raw, 0, 1), [0.0, -h, h]);
+    assert_close(normal_of(&raw, 0, 2), [0.0, 0.0, 1.0]);
+    assert_close(normal_of(&raw, 1, 0), [0.0, -h, h]);
+    assert_close(normal_of(&raw, 1, 1), [0.0, -h, h]);
+    assert_close(normal_of(&raw, 1, 2), [0.0, -1.0, 0.0]);
+
+    // Shared vertices share normal vectors
+    assert_eq!(raw.normals.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn smooth_hard_edges() -> TestResult {
+    for smoothing in &["s 1|s 2", "s off|s off", "s 1|s off"] {
+        let mut raw = parse_obj(Cursor::new(folded(smoothing)))?;
+        raw.generate_normals(NormalGeneration::Smooth);
+
+        for corner in 0..3 {
+            assert_close(normal_of(&raw, 0, corner), [0.0, 0.0, 1.0]);
+            assert_close(normal_of(&raw, 1, corner), [0.0, -1.0, 0.0]);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn keep_existing_normals() -> TestResult {
+    let mut raw = parse_obj(Cursor::new(
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 1 0 0\nf 1//1 2//1 3//1",
+    ))?;
+    raw.generate_normals(NormalGeneration::Smooth);
+
+    assert_eq!(raw.normals, vec![(1.0, 0.0, 0.0)]);
+    assert_eq!(raw.polygons[0], Polygon::PN(vec![(0, 0), (1, 0), (2, 0)]));
+
+    Ok(())
+}
+
+#[test]
+fn load() -> TestResult {
+    let fixture = |name| -> std::io::Result<_> {
+        Ok(BufReader::new(File::open(format!(
+            "tests/fixtures/{}",
+            name
+        ))?))
+    };
+
+    match load_obj::<Vertex, _, u16>(fixture("dome.obj")?) {
+        Err(ObjError::Load(e)) => assert_eq!(*e.kind(), LoadErrorKind::InsufficientData),
+        _ => panic!("Normals shouldn't be loaded from a model without them"),
+    }
+
+    let options = LoadOptions::new().generate_normals(NormalGeneration::Smooth);
+    let dome: Obj<Vertex> = load_obj_with(fixture("dome.obj")?, &options)?;
+    assert_eq!(dome.indices.len(), 62 * 3);
+    for vertex in &dome.vertices {
+        let [x, y, z] = vertex.normal;
+        assert!(((x * x + y * y + z * z).sqrt() - 1.0).abs() < 1e-5);
+    }
+
+    // cube.obj has texture coordinates but no normals, and consists of quads
+    let options = LoadOptions::new()
+        .triangulate(Triangulation::Fan)
+        .generate_normals(NormalGeneration::Flat);
+    let cube: Obj<TexturedVertex> = load_obj_with(fixture("cube.obj")?, &options)?;
+    assert_eq!(cube.indices.len(), 36);
+    assert_eq!(cube.vertices[0].normal, [0.0, -1.0, 0.0]);
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use obj::raw::object::{write_obj, Polygon, Range};
+use obj::raw::{parse_obj, Triangulation};
+use obj::{load_objects, LoadOptions, Position};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const INPUT: &str = r#"
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vn 0 0 1
+p 1
+o first
+g side
+f 1//1 2//1 3//1 4//1
+p 2
+o second
+l 1 3
+f 1 3 4
+g default
+f 2 3 4
+o
+p 4
+o first
+f 1 2 3
+"#;
+
+#[test]
+fn ranges() -> TestResult {
+    let raw = parse_obj(INPUT.as_bytes())?;
+
+    assert_eq!(raw.name.as_deref(), Some("first"));
+    assert_eq!(raw.objects.len(), 2);
+    assert_eq!(
+        raw.objects["first"].points,
+        vec![Range { start: 1, end: 2 }]
+    );
+    assert_eq!(raw.objects["first"].lines, vec![]);
+    assert_eq!(
+        raw.objects["first"].polygons,
+        vec![Range { start: 0, end: 1 }, Range { start: 3, end: 4 }]
+    );
+    assert_eq!(raw.objects["second"].points, vec![]);
+    assert_eq!(
+        raw.objects["second"].lines,
+        vec![Range { start: 0, end: 1 }]
+    );
+    assert_eq!(
+        raw.objects["second"].polygons,
+        vec![Range { start: 1, end: 3 }]
+    );
+
+    // Objects follow polygons split by triangulation
+    let mut triangulated = raw.clone();
+    triangulated.triangulate(Triangulation::Fan);
+    assert_eq!(
+        triangulated.objects["first"].polygons,
+        vec![Range { start: 0, end: 2 }, Range { start: 4, end: 5 }]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn extract() -> TestResult {
+    let raw = parse_obj(INPUT.as_bytes())?;
+    assert!(raw.object("third").is_none());
+
+    let second = raw.object("second").unwrap();
+    assert_eq!(second.name.as_deref(), Some("second"));
+    assert_eq!(second.positions.len(), 4);
+    assert_eq!(second.normals.len(), 0);
+    assert_eq!(second.points.len(), 0);
+    assert_eq!(second.lines.len(), 1);
+    assert_eq!(
+        second.polygons,
+        [Polygon::P(vec![0, 1, 2]), Polygon::P(vec![3, 1, 2])]
+    );
+    assert_eq!(second.groups.len(), 2);
+    assert_eq!(
+        second.groups["side"].polygons,
+        vec![Range { start: 0, end: 1 }]
+    );
+    assert_eq!(
+        second.groups["default"].polygons,
+        vec![Range { start: 1, end: 2 }]
+    );
+    assert_eq!(second.objects.len(), 1);
+
+    let first = raw.object("first").unwrap();
+    assert_eq!(first.positions.len(), 4);
+    assert_eq!(first.normals.len(), 1);
+    // Vertex data are renumbered in the order of the first reference
+    assert_eq!(first.points, [0]);
+    assert_eq!(first.positions[0], (1.0, 0.0, 0.0, 1.0));
+    assert_eq!(first.polygons[1], Polygon::P(vec![1, 0, 2]));
+    assert_eq!(
+        first.groups["side"].points,
+        vec![Range { start: 0, end: 1 }]
+    );
+    assert_eq!(
+        first.groups["side"].polygons,
+        vec![Range { start: 0, end: 1 }]
+    );
+    assert_eq!(
+        first.groups["default"].polygons,
+        vec![Range { start: 1, end: 2 }]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn round_trip() -> TestResult {
+    for input in &[
+        INPUT,
+        "v 0 0 0\np 1\no last\n",
+        "o a\nv 0 0 0\np 1\no\nl 1 1\no b\n",
+        "v 0 0 0\no a\nf 1 1 1\no b\np 1\no\n",
+    ] {
+        let raw = parse_obj(input.as_bytes())?;
+        let mut output = Vec::new();
+        write_obj(&raw, &mut output)?;
+        assert_eq!(
+            parse_obj(&output[..])?,
+            raw,
+            "{}",
+            String::from_utf8(output)?
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn load() -> TestResult {
+    // Quads have to be triangulated
+    assert!(load_objects::<Position, _, u16>(INPUT.as_bytes(), &LoadOptions::new()).is_err());
+    let options = LoadOptions::new().triangulate(Triangulation::Fan);
+    let objects = load_objects::<Position, _, u16>(INPUT.as_bytes(), &options)?;
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects["first"].indices.len(), 3 * 3);
+
+    let second = &objects["second"];
+    assert_eq!(second.vertices.len(), 4);
+    assert_eq!(second.indices, [0, 1, 2, 3, 1, 2]);
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "rayon")]
+
+use obj::raw::ParseOptions;
+use obj::raw::{parse_obj, parse_obj_parallel, parse_obj_parallel_with, parse_obj_with};
+use std::fs;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+#[test]
+fn sponza() -> TestResult {
+    let input = fs::read("tests/fixtures/sponza.obj")?;
+    assert_eq!(parse_obj_parallel(&input)?, parse_obj(&input[..])?);
+    Ok(())
+}
+
+#[test]
+fn large_lenient() -> TestResult {
+    // Big enough to be split into chunks, with negative indices and groups which span chunks
+    let mut input = String::from("o large\nmtllib a.mtl\n");
+    for i in 0..20000 {
+        input.push_str("v 0 0 0\nv 1 0 0 \\\n\nv 1 1 0\n");
+        if i % 7 == 0 {
+            input.push_str(&format!("g group{}\nusemtl material{}\n", i % 3, i % 5));
+        }
+        if i % 11 == 0 {
+            input.push_str("#MRGB ffffffff\nv 1 x 1\n");
+        }
+        input.push_str("f -1 -2 -3\ns 1\nf 1 2 -1\ns off\n");
+    }
+
+    let options = ParseOptions::new().lenient(true);
+    let parallel = parse_obj_parallel_with(input.as_bytes(), &options)?;
+    assert_eq!(parallel, parse_obj_with(input.as_bytes(), &options)?);
+    assert_eq!(parallel.warnings.len(), 20000 / 11 + 1);
+
+    assert_eq!(
+        parse_obj_parallel(input.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        parse_obj(input.as_bytes()).unwrap_err().to_string()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,340 @@
+use obj::raw::freeform::{Geometry, SurfaceTechnique};
+use obj::raw::object::{Polygon, Range};
+use obj::raw::{parse_obj, RawObj, Tessellation};
+use obj::{load_obj_with, LoadErrorKind, LoadOptions, Obj, ObjError, Vertex};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Quarter of a cylinder of radius 1 and height 1 around the `z` axis, as a NURBS surface.
+const CYLINDER: &str = r#"
+v 1 0 0
+v 1 1 0 0.70710677
+v 0 1 0
+v 1 0 1
+v 1 1 1 0.70710677
+v 0 1 1
+cstype rat bspline
+deg 2 1
+surf 0 1 0 1 1 2 3 4 5 6
+parm u 0 0 0 1 1 1
+parm v 0 0 1 1
+end
+"#;
+
+/// Bilinear patch over the unit square, which is lifted at one corner.
+const PATCH: &str = r#"
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 1
+cstype bspline
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+end
+"#;
+
+fn tessellated(input: &str, method: Tessellation) -> Result<RawObj, ObjError> {
+    let mut raw = parse_obj(input.as_bytes())?;
+    raw.tessellate(method)?;
+    Ok(raw)
+}
+
+fn triangles(raw: &RawObj) -> Vec<[(usize, usize, usize); 3]> {
+    raw.polygons
+        .iter()
+        .map(|polygon| match polygon {
+            Polygon::PTN(vec) => [vec[0], vec[1], vec[2]],
+            polygon => panic!("Unexpected polygon {:?}", polygon),
+        })
+        .collect()
+}
+
+#[test]
+fn bilinear() -> TestResult {
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricA { u: 2.0, v: 1.0 });
+    let raw = tessellated(PATCH, method)?;
+
+    // 2 segments in `u` and 1 in `v`, with 3 × 2 new vertices after the control points
+    assert!(raw.free_forms.is_empty());
+    assert_eq!(raw.positions.len(), 4 + 6);
+    assert_eq!(
+        raw.positions[4..],
+        [
+            (0.0, 0.0, 0.0, 1.0),
+            (0.5, 0.0, 0.0, 1.0),
+            (1.0, 0.0, 0.0, 1.0),
+            (0.0, 1.0, 0.0, 1.0),
+            (0.5, 1.0, 0.5, 1.0),
+            (1.0, 1.0, 1.0, 1.0),
+        ]
+    );
+    assert_eq!(
+        raw.tex_coords,
+        [
+            (0.0, 0.0, 0.0),
+            (0.5, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (0.0, 1.0, 0.0),
+            (0.5, 1.0, 0.0),
+            (1.0, 1.0, 0.0),
+        ]
+    );
+
+    let triangles = triangles(&raw);
+    assert_eq!(triangles.len(), 4);
+    assert_eq!(triangles[0], [(4, 0, 0), (5, 1, 1), (8, 4, 4)]);
+    assert_eq!(triangles[1], [(4, 0, 0), (8, 4, 4), (7, 3, 3)]);
+
+    // The patch faces `+z` where it's flat
+    let (x, y, z) = raw.normals[0];
+    assert!(x.abs() < 1e-6 && y.abs() < 1e-6 && (z - 1.0).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn rational() -> TestResult {
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricA { u: 4.0, v: 1.0 });
+    let raw = tessellated(CYLINDER, method)?;
+
+    // 4 × 2 segments in `u`, since the degree is 2
+    assert_eq!(raw.positions.len(), 6 + 9 * 2);
+    assert_eq!(raw.polygons.len(), 8 * 2);
+
+    for (position, normal) in raw.positions[6..].iter().zip(&raw.normals) {
+        let radius = position.0.hypot(position.1);
+        assert!((radius - 1.0).abs() < 1e-5, "{:?}", position);
+
+        // Normal vectors point away from the axis
+        let (x, y, z) = *normal;
+        let length = (x * x + y * y + z * z).sqrt();
+        assert!((length - 1.0).abs() < 1e-5, "{:?}", normal);
+        assert!(z.abs() < 1e-5, "{:?}", normal);
+        assert!(
+            (x * position.0 + y * position.1).abs() > 1.0 - 1e-5,
+            "{:?}",
+            normal
+        );
+    }
+
+    for &(u, v, _) in &raw.tex_coords {
+        assert!((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v));
+    }
+
+    Ok(())
+}
+
+/// Returns the vertices along the bottom edge of the tessellated cylinder.
+fn bottom(raw: &RawObj) -> Vec<(f32, f32)> {
+    raw.positions[6..]
+        .iter()
+        .filter(|p| p.2 == 0.0)
+        .map(|p| (p.0, p.1))
+        .collect()
+}
+
+#[test]
+fn tolerance() -> TestResult {
+    let method = Tessellation::Override(SurfaceTechnique::Spatial(0.1));
+    let raw = tessellated(CYLINDER, method)?;
+    let points = bottom(&raw);
+    assert!(points.len() > 16);
+    for pair in points.windows(2) {
+        let length = (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1);
+        assert!(length <= 0.1, "{:?}", pair);
+    }
+
+    let method = Tessellation::Override(SurfaceTechnique::Curvature {
+        distance: 0.001,
+        angle: 90.0,
+    });
+    let raw = tessellated(CYLINDER, method)?;
+    let points = bottom(&raw);
+    for pair in points.windows(2) {
+        // Midpoints of the edges stay close to the surface
+        let middle = (0.5 * (pair[0].0 + pair[1].0), 0.5 * (pair[0].1 + pair[1].1));
+        assert!(middle.0.hypot(middle.1) > 1.0 - 0.001, "{:?}", pair);
+    }
+
+    // The straight direction of the cylinder isn't subdivided
+    assert_eq!(raw.positions.len(), 6 + points.len() * 2);
+
+    Ok(())
+}
+
+#[test]
+fn technique() -> TestResult {
+    let input = PATCH.replace("surf", "stech cparmb 3\nsurf");
+
+    // `stech` takes precedence over the default technique
+    let raw = tessellated(
+        &input,
+        Tessellation::Stech(SurfaceTechnique::ParametricB(1.0)),
+    )?;
+    assert_eq!(raw.polygons.len(), 3 * 3 * 2);
+    let raw = tessellated(
+        PATCH,
+        Tessellation::Stech(SurfaceTechnique::ParametricB(1.0)),
+    )?;
+    assert_eq!(raw.polygons.len(), 2);
+
+    // Unless it's overridden
+    let method = Tessellation::Override(SurfaceTechnique::ParametricB(1.0));
+    let raw = tessellated(&input, method)?;
+    assert_eq!(raw.polygons.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn knot_spans() -> TestResult {
+    // Two spans in `u`, each of which is subdivided separately
+    let input = r#"
+v 0 0 0
+v 1 0 0
+v 2 0 0
+v 0 1 0
+v 1 1 0
+v 2 1 0
+cstype bspline
+deg 1 1
+surf 0.5 2 0 1 1 2 3 4 5 6
+parm u 0 0 1 2 2
+parm v 0 0 1 1
+end
+"#;
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricB(2.0));
+    let raw = tessellated(input, method)?;
+
+    // The range of `surf` starts in the middle of the first span
+    let xs: Vec<_> = raw.positions[6..]
+        .iter()
+        .filter(|p| p.1 == 0.0)
+        .map(|p| p.0)
+        .collect();
+    assert_eq!(xs, [0.5, 0.75, 1.0, 1.5, 2.0]);
+
+    Ok(())
+}
+
+#[test]
+fn materials() -> TestResult {
+    let input = format!(
+        "usemtl blue\n{}\nusemtl red\n{}\nf 1 2 3\n",
+        CYLINDER,
+        CYLINDER.replace("surf", "stech cparmb 2\nsurf")
+    );
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricB(1.0));
+    let raw = tessellated(&input, method)?;
+
+    // 2 × 1 and 4 × 2 segments
+    assert_eq!(raw.polygons.len(), 1 + 4 + 16);
+    assert_eq!(
+        raw.meshes["red"].polygons,
+        [Range { start: 0, end: 1 }, Range { start: 5, end: 21 }]
+    );
+    assert_eq!(raw.meshes["blue"].polygons, [Range { start: 1, end: 5 }]);
+
+    // Tessellated surfaces don't belong to any group
+    assert_eq!(raw.groups["default"].polygons, [Range { start: 0, end: 1 }]);
+
+    Ok(())
+}
+
+#[test]
+fn kept() -> TestResult {
+    let input = format!(
+        "{}vp 0 0\nvp 1 0\ncstype bspline\ndeg 1\ncurv2 1 2\nparm u 0 0 1 1\nend\n\
+         cstype bezier\ndeg 1 1\nsurf 0 1 0 1 1 2 3 4\ntrim 0 1 1\nend\n",
+        PATCH
+    );
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricB(1.0));
+    let raw = tessellated(&input, method)?;
+
+    // Curves and Bézier surfaces are kept, and trimming curves are renumbered
+    assert_eq!(raw.free_forms.len(), 2);
+    assert!(raw.free_forms[0].is_curve2());
+    assert!(matches!(
+        raw.free_forms[1].geometry,
+        Geometry::Surface { .. }
+    ));
+    assert_eq!(raw.free_forms[1].trims[0][0].curve, 0);
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> TestResult {
+    let test_cases = [
+        // 3 control points in `u`, but knots for 2
+        PATCH.replace("surf 0 1 0 1 1 2 3 4", "surf 0 1 0 1 1 2 3 4 1 2"),
+        PATCH.replace("parm u 0 0 1 1\n", ""),
+        PATCH.replace("parm v 0 0 1 1", "parm v 0 1 0 1"),
+        PATCH.replace("parm v 0 0 1 1", "parm v 0 0 0 0"),
+        PATCH.replace("surf 0 1 0 1", "surf 2 3 0 1"),
+        PATCH.replace("surf 0 1 0 1", "surf 0 inf 0 1"),
+        CYLINDER.replace("0.70710677", "0"),
+    ];
+
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricB(1.0));
+    for input in &test_cases {
+        let mut raw = parse_obj(input.as_bytes())?;
+        let expected = raw.clone();
+        match raw.tessellate(method) {
+            Err(ObjError::Load(e)) => {
+                assert_eq!(e.kind(), &LoadErrorKind::InsufficientData, "{}", input)
+            }
+            _ => panic!("Expected an error from {:?}", input),
+        }
+        assert_eq!(raw, expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn not_a_number() -> TestResult {
+    // Comparisons with NaN are always false, so they would pass the checks of the order
+    let test_cases = [
+        PATCH.replace("parm u 0 0 1 1", "parm u NaN NaN 1 1"),
+        PATCH.replace("surf 0 1 0 1", "surf 0 1 NaN 1"),
+    ];
+
+    let method = Tessellation::Stech(SurfaceTechnique::ParametricB(1.0));
+    for input in &test_cases {
+        let mut raw = parse_obj(input.as_bytes())?;
+        match raw.tessellate(method) {
+            Err(ObjError::Load(e)) => {
+                assert_eq!(e.kind(), &LoadErrorKind::InsufficientData, "{}", input)
+            }
+            _ => panic!("Expected an error from {:?}", input),
+        }
+        // NaN isn't equal to itself, so the rest is compared instead
+        assert_eq!(raw.free_forms.len(), 1);
+        assert!(raw.polygons.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn load() -> TestResult {
+    let options =
+        LoadOptions::new().tessellate(Tessellation::Stech(SurfaceTechnique::ParametricB(2.0)));
+    let obj: Obj<Vertex, u16> = load_obj_with(CYLINDER.as_bytes(), &options)?;
+
+    assert_eq!(obj.vertices.len(), 5 * 3);
+    assert_eq!(obj.indices.len(), 4 * 2 * 2 * 3);
+    for vertex in &obj.vertices {
+        let [x, y, _] = vertex.position;
+        assert!((x.hypot(y) - 1.0).abs() < 1e-5);
+    }
+
+    // Without tessellation, there's nothing to load
+    let obj: Obj<Vertex, u16> = load_obj_with(CYLINDER.as_bytes(), &LoadOptions::new())?;
+    assert!(obj.indices.is_empty());
+
+    Ok(())
+}
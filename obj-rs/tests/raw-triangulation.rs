@@ -0,0 +1,93 @@
+use obj::raw::object::{Polygon, Range};
+use obj::raw::{parse_obj, Triangulation};
+use obj::{load_obj, load_obj_with, LoadErrorKind, LoadOptions, Obj, ObjError, Position};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+const INPUT: &str = r#"
+v 0 0 0
+v 4 0 0
+v 4 4 0
+v 2 1 0
+v 0 4 0
+vn 0 0 1
+
+g first
+usemtl a
+f 1//1 2//1 3//1
+f 1//1 2//1 3//1 5//1
+g second
+usemtl b
+f 1//1 2//1 3//1 4//1 5//1
+f 1//1 3//1 5//1
+"#;
+
+/// Signed area of a triangle on the XY plane.
+fn area(positions: &[(f32, f32, f32, f32)], polygon: &Polygon) -> f32 {
+    let p: Vec<_> = match polygon {
+        Polygon::PN(vec) => vec.iter().map(|&(p, _)| positions[p]).collect(),
+        _ => panic!("Unexpected polygon {:?}", polygon),
+    };
+    assert_eq!(p.len(), 3);
+    ((p[1].0 - p[0].0) * (p[2].1 - p[0].1) - (p[2].0 - p[0].0) * (p[1].1 - p[0].1)) / 2.0
+}
+
+#[test]
+fn ranges() -> TestResult {
+    for &method in &[Triangulation::Fan, Triangulation::EarClipping] {
+        let mut raw = parse_obj(Cursor::new(INPUT))?;
+        raw.triangulate(method);
+
+        // 1 + 2 + 3 + 1 triangles
+        assert_eq!(raw.polygons.len(), 7);
+        assert_eq!(raw.polygons[0], Polygon::PN(vec![(0, 0), (1, 0), (2, 0)]));
+        assert_eq!(raw.polygons[6], Polygon::PN(vec![(0, 0), (2, 0), (4, 0)]));
+
+        assert_eq!(raw.groups["first"].polygons, [Range { start: 0, end: 3 }]);
+        assert_eq!(raw.groups["second"].polygons, [Range { start: 3, end: 7 }]);
+        assert_eq!(raw.meshes["a"].polygons, [Range { start: 0, end: 3 }]);
+        assert_eq!(raw.meshes["b"].polygons, [Range { start: 3, end: 7 }]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn concave() -> TestResult {
+    let mut raw = parse_obj(Cursor::new(INPUT))?;
+    raw.triangulate(Triangulation::EarClipping);
+
+    // The concave pentagon is split into counter-clockwise triangles which cover its area
+    let total: f32 = raw.polygons[3..6]
+        .iter()
+        .map(|polygon| {
+            let area = area(&raw.positions, polygon);
+            assert!(area > 0.0, "{:?} is flipped", polygon);
+            area
+        })
+        .sum();
+    assert!((total - 10.0).abs() < 1e-6);
+
+    Ok(())
+}
+
+#[test]
+fn load() -> TestResult {
+    let input =
+        || -> std::io::Result<_> { Ok(BufReader::new(File::open("tests/fixtures/cube.obj")?)) };
+
+    match load_obj::<Position, _, u16>(input()?) {
+        Err(ObjError::Load(e)) => assert_eq!(*e.kind(), LoadErrorKind::UntriangulatedModel),
+        _ => panic!("Quads shouldn't be loaded without triangulation"),
+    }
+
+    let options = LoadOptions::new().triangulate(Triangulation::EarClipping);
+    let cube: Obj<Position> = load_obj_with(input()?, &options)?;
+    assert_eq!(cube.vertices.len(), 8);
+    assert_eq!(cube.indices.len(), 36);
+    assert_eq!(&cube.indices[..6], &[0, 1, 2, 0, 2, 3]);
+
+    Ok(())
+}
@@ -0,0 +1,293 @@
+use obj::raw::freeform::SurfaceTechnique;
+use obj::raw::object::Polygon;
+use obj::raw::{parse_obj, RawObj, Tessellation};
+use obj::{LoadErrorKind, ObjError};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Flat patch over the unit square, where positions are the same as parameters.
+const PATCH: &str = r#"
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+cstype bspline
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+parm u 0 0 1 1
+parm v 0 0 1 1
+"#;
+
+/// Square loops of `curv2` curves, which are the 1st and the 2nd `curv2` curves.
+const SQUARES: &str = r#"
+vp 0.25 0.25
+vp 0.75 0.25
+vp 0.75 0.75
+vp 0.25 0.75
+vp 0.4 0.4
+vp 0.6 0.4
+vp 0.6 0.6
+vp 0.4 0.6
+cstype bezier
+deg 1
+curv2 1 2 3 4 1
+parm u 0 1 2 3 4
+end
+curv2 5 6 7 8 5
+parm u 0 1 2 3 4
+end
+"#;
+
+/// Circle of radius 0.4 around the center of the unit square, which is the 1st `curv2` curve.
+const CIRCLE: &str = r#"
+vp 0.9 0.5 1
+vp 0.9 0.9 0.70710677
+vp 0.5 0.9 1
+vp 0.1 0.9 0.70710677
+vp 0.1 0.5 1
+vp 0.1 0.1 0.70710677
+vp 0.5 0.1 1
+vp 0.9 0.1 0.70710677
+cstype rat bspline
+deg 2
+curv2 1 2 3 4 5 6 7 8 1
+parm u 0 0 0 1 1 2 2 3 3 4 4 4
+end
+"#;
+
+fn tessellated(curves: &str, body: &str, resolution: f32) -> Result<RawObj, ObjError> {
+    let input = format!("{}{}{}\nend\n", curves, PATCH, body);
+    let mut raw = parse_obj(input.as_bytes())?;
+    raw.tessellate(Tessellation::Stech(SurfaceTechnique::ParametricB(
+        resolution,
+    )))?;
+    Ok(raw)
+}
+
+fn triangles(raw: &RawObj) -> Vec<[[f32; 2]; 3]> {
+    raw.polygons
+        .iter()
+        .map(|polygon| match polygon {
+            Polygon::PTN(vec) => {
+                let corner = |i: usize| {
+                    let (x, y, _, _) = raw.positions[vec[i].0];
+                    [x, y]
+                };
+                [corner(0), corner(1), corner(2)]
+            }
+            polygon => panic!("Unexpected polygon {:?}", polygon),
+        })
+        .collect()
+}
+
+/// Signed area of the triangle, which is positive if the corners go counterclockwise.
+fn area([a, b, c]: [[f32; 2]; 3]) -> f32 {
+    0.5 * ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]))
+}
+
+fn total_area(raw: &RawObj) -> f32 {
+    triangles(raw).into_iter().map(area).sum()
+}
+
+#[test]
+fn trim() -> TestResult {
+    let raw = tessellated(SQUARES, "trim 0 4 1", 4.0)?;
+
+    // Only the inside of the outer loop is left, without slivers
+    assert!((total_area(&raw) - 0.25).abs() < 1e-5);
+    for triangle in triangles(&raw) {
+        assert!(area(triangle) > 1e-4, "{:?}", triangle);
+        for [x, y] in triangle.iter() {
+            assert!((0.25..=0.75).contains(x) && (0.25..=0.75).contains(y));
+        }
+    }
+
+    // Texture coordinates are the normalized parameters
+    for polygon in &raw.polygons {
+        if let Polygon::PTN(vec) = polygon {
+            for &(p, t, _) in vec {
+                let (x, y, _, _) = raw.positions[p];
+                let (u, v, _) = raw.tex_coords[t];
+                assert!((x - u).abs() < 1e-6 && (y - v).abs() < 1e-6);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn hole() -> TestResult {
+    let raw = tessellated(SQUARES, "trim 0 4 1\nhole 0 4 2", 4.0)?;
+    assert!((total_area(&raw) - (0.25 - 0.04)).abs() < 1e-5);
+    for triangle in triangles(&raw) {
+        let [x, y] = [0, 1].map(|k| triangle.iter().map(|p| p[k]).sum::<f32>() / 3.0);
+        assert!(!((0.4..0.6).contains(&x) && (0.4..0.6).contains(&y)));
+    }
+
+    // Without `trim`, the hole is cut out of the whole surface
+    let raw = tessellated(SQUARES, "hole 0 4 2", 4.0)?;
+    assert!((total_area(&raw) - (1.0 - 0.04)).abs() < 1e-5);
+
+    Ok(())
+}
+
+#[test]
+fn pieces() -> TestResult {
+    // The same square, with the 3rd side from a curve which goes backwards
+    let curves = "vp 0.25 0.25\nvp 0.75 0.25\nvp 0.75 0.75\nvp 0.25 0.75\n\
+                  cstype bezier\ndeg 1\n\
+                  curv2 1 2 3\nparm u 0 1 2\nend\n\
+                  curv2 3 4\nparm u 0 1\nend\n\
+                  curv2 1 4\nparm u 0 1\nend\n";
+    let raw = tessellated(curves, "trim 0 2 1 0 1 2 1 0 3", 4.0)?;
+    assert!((total_area(&raw) - 0.25).abs() < 1e-5);
+
+    Ok(())
+}
+
+#[test]
+fn rational() -> TestResult {
+    let raw = tessellated(CIRCLE, "trim 0 4 1", 16.0)?;
+
+    let expected = std::f32::consts::PI * 0.4 * 0.4;
+    assert!((total_area(&raw) - expected).abs() < 0.01 * expected);
+    for triangle in triangles(&raw) {
+        assert!(area(triangle) > 0.0);
+        for [x, y] in triangle.iter() {
+            assert!((x - 0.5).hypot(y - 0.5) < 0.4 + 1e-5);
+        }
+    }
+
+    // An override with a coarser technique approximates the circle more coarsely
+    let coarse = tessellated(CIRCLE, "trim 0 4 1", 1.0)?;
+    assert!(coarse.positions.len() < raw.positions.len());
+
+    Ok(())
+}
+
+#[test]
+fn technique() -> TestResult {
+    // `ctech` of the trimming curve picks its own resolution
+    let curves = CIRCLE.replace("curv2", "ctech cparm 1\ncurv2");
+    let raw = tessellated(&curves, "trim 0 4 1", 16.0)?;
+    let fine = tessellated(CIRCLE, "trim 0 4 1", 16.0)?;
+
+    let boundary = |raw: &RawObj| {
+        let positions = raw.positions[4..].iter();
+        positions
+            .filter(|p| ((p.0 - 0.5).hypot(p.1 - 0.5) - 0.4).abs() < 1e-5)
+            .count()
+    };
+    // 4 spans of degree 2
+    assert_eq!(boundary(&raw), 8);
+    assert!(boundary(&fine) > 8);
+
+    Ok(())
+}
+
+#[test]
+fn special_curve() -> TestResult {
+    let curves = "vp 0 0.5\nvp 1 0.5\ncstype bezier\ndeg 1\ncurv2 1 2\nparm u 0 1\nend\n";
+    let raw = tessellated(curves, "scrv 0 1 1", 3.0)?;
+
+    // No triangle crosses the special curve
+    assert!((total_area(&raw) - 1.0).abs() < 1e-5);
+    for triangle in triangles(&raw) {
+        let above = triangle.iter().any(|p| p[1] > 0.5 + 1e-6);
+        let below = triangle.iter().any(|p| p[1] < 0.5 - 1e-6);
+        assert!(!(above && below), "{:?}", triangle);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn special_point() -> TestResult {
+    let raw = tessellated("vp 0.3 0.7\n", "sp 1", 2.0)?;
+    assert!((total_area(&raw) - 1.0).abs() < 1e-5);
+    assert!(raw.positions[4..]
+        .iter()
+        .any(|p| (p.0 - 0.3).abs() < 1e-6 && (p.1 - 0.7).abs() < 1e-6));
+
+    Ok(())
+}
+
+#[test]
+fn crossing() -> TestResult {
+    // A special curve which crosses the outer loop, which are both kept as edges
+    let curves = format!(
+        "{}vp 0 0.5\nvp 1 0.5\ncstype bezier\ndeg 1\ncurv2 9 10\nparm u 0 1\nend\n",
+        SQUARES
+    );
+    let raw = tessellated(&curves, "trim 0 4 1\nscrv 0 1 3", 4.0)?;
+    assert!((total_area(&raw) - 0.25).abs() < 1e-5);
+    for triangle in triangles(&raw) {
+        let above = triangle.iter().any(|p| p[1] > 0.5 + 1e-6);
+        let below = triangle.iter().any(|p| p[1] < 0.5 - 1e-6);
+        assert!(!(above && below), "{:?}", triangle);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn curved() -> TestResult {
+    // Quarter of a cylinder, trimmed to a disk in its parameter space
+    let input = format!(
+        "{}v 1 0 0\nv 1 1 0 0.70710677\nv 0 1 0\nv 1 0 1\nv 1 1 1 0.70710677\nv 0 1 1\n\
+         cstype rat bspline\ndeg 2 1\nsurf 0 1 0 1 1 2 3 4 5 6\n\
+         parm u 0 0 0 1 1 1\nparm v 0 0 1 1\ntrim 0 4 1\nend\n",
+        CIRCLE
+    );
+    let mut raw = parse_obj(input.as_bytes())?;
+    raw.tessellate(Tessellation::Stech(SurfaceTechnique::ParametricB(8.0)))?;
+
+    assert!(!raw.polygons.is_empty());
+    for (position, normal) in raw.positions[6..].iter().zip(&raw.normals) {
+        assert!((position.0.hypot(position.1) - 1.0).abs() < 1e-5);
+        let (x, y, z) = *normal;
+        assert!(((x * x + y * y + z * z).sqrt() - 1.0).abs() < 1e-5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn errors() -> TestResult {
+    let test_cases = [
+        // The trimming curve doesn't have enough parameters
+        (
+            SQUARES.replace("parm u 0 1 2 3 4\nend\ncurv2", "parm u 0 1\nend\ncurv2"),
+            "trim 0 4 1",
+            LoadErrorKind::InsufficientData,
+        ),
+        (
+            "vp 0 0\n".to_string(),
+            "sp 2",
+            LoadErrorKind::IndexOutOfRange,
+        ),
+    ];
+
+    for (curves, body, expected) in &test_cases {
+        let input = format!("{}{}{}\nend\n", curves, PATCH, body);
+        let mut raw = match parse_obj(input.as_bytes()) {
+            Ok(raw) => raw,
+            // Special points out of range are already caught by the parser
+            Err(ObjError::Load(e)) => {
+                assert_eq!(e.kind(), expected);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let unchanged = raw.clone();
+        let method = Tessellation::Stech(SurfaceTechnique::ParametricB(1.0));
+        match raw.tessellate(method) {
+            Err(ObjError::Load(e)) => assert_eq!(e.kind(), expected, "{}", input),
+            _ => panic!("Expected an error from {:?}", input),
+        }
+        assert_eq!(raw, unchanged);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use obj::raw::{parse_mtl, parse_obj};
+use obj::{LoadErrorKind, ObjError};
+use std::io::Cursor;
+
+fn kind(result: Result<(), ObjError>) -> Option<LoadErrorKind> {
+    match result {
+        Err(ObjError::Load(e)) => Some(*e.kind()),
+        _ => None,
+    }
+}
+
+#[test]
+fn unsupported_obj_statements() {
+    let test_cases: [&[u8]; 5] = [
+        b"bevel on",
+        b"c_interp off",
+        b"d_interp off",
+        b"lod 10",
+        b"shadow_obj shadow.obj",
+    ];
+
+    for data in test_cases {
+        let result = parse_obj(Cursor::new(data)).map(|_| ());
+        assert_eq!(
+            kind(result),
+            Some(LoadErrorKind::UnsupportedStatement),
+            "{}",
+            String::from_utf8_lossy(data)
+        );
+    }
+}
+
+#[test]
+fn malformed_freeform_statements() {
+    let result = parse_obj(Cursor::new(b"cstype nurbs")).map(|_| ());
+    assert_eq!(kind(result), Some(LoadErrorKind::WrongTypeOfArguments));
+
+    let result = parse_obj(Cursor::new(b"deg 1 2 3")).map(|_| ());
+    assert_eq!(kind(result), Some(LoadErrorKind::WrongNumberOfArguments));
+}
+
+#[test]
+fn unsupported_mtl_statements() {
+    let test_cases: [&[u8]; 5] = [
+        b"newmtl a\nKm 0.5",
+        b"newmtl a\nmap_aat on",
+        b"newmtl a\nmap_refl refl.png",
+        b"newmtl a\ndisp disp.png",
+        b"newmtl a\nrefl -type sphere refl.png",
+    ];
+
+    for data in test_cases {
+        let result = parse_mtl(Cursor::new(data)).map(|_| ());
+        assert_eq!(
+            kind(result),
+            Some(LoadErrorKind::UnsupportedStatement),
+            "{}",
+            String::from_utf8_lossy(data)
+        );
+    }
+}
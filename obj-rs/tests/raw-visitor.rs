@@ -0,0 +1,210 @@
+use obj::raw::object::{Line, Point, Polygon};
+use obj::raw::{parse_obj, visit_obj, visit_obj_with, ObjVisitor, ParseOptions};
+use obj::{LoadError, LoadErrorKind, ObjError, ObjResult};
+use std::fs::File;
+use std::io::BufReader;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Records every statement as a string.
+#[derive(Default)]
+struct Recorder(Vec<String>);
+
+impl ObjVisitor for Recorder {
+    fn position(&mut self, x: f32, y: f32, z: f32, w: f32) -> ObjResult<()> {
+        self.0.push(format!("v {} {} {} {}", x, y, z, w));
+        Ok(())
+    }
+
+    fn tex_coord(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        self.0.push(format!("vt {} {} {}", u, v, w));
+        Ok(())
+    }
+
+    fn normal(&mut self, x: f32, y: f32, z: f32) -> ObjResult<()> {
+        self.0.push(format!("vn {} {} {}", x, y, z));
+        Ok(())
+    }
+
+    fn param_vertex(&mut self, u: f32, v: f32, w: f32) -> ObjResult<()> {
+        self.0.push(format!("vp {} {} {}", u, v, w));
+        Ok(())
+    }
+
+    fn point(&mut self, point: Point) -> ObjResult<()> {
+        self.0.push(format!("p {}", point));
+        Ok(())
+    }
+
+    fn line(&mut self, line: Line) -> ObjResult<()> {
+        self.0.push(format!("l {:?}", line));
+        Ok(())
+    }
+
+    fn face(&mut self, polygon: Polygon) -> ObjResult<()> {
+        self.0.push(format!("f {:?}", polygon));
+        Ok(())
+    }
+
+    fn group(&mut self, names: &[&str]) -> ObjResult<()> {
+        self.0.push(format!("g {}", names.join(" ")));
+        Ok(())
+    }
+
+    fn usemtl(&mut self, material: &str) -> ObjResult<()> {
+        self.0.push(format!("usemtl {}", material));
+        Ok(())
+    }
+
+    fn mtllib(&mut self, path: &str) -> ObjResult<()> {
+        self.0.push(format!("mtllib {}", path));
+        Ok(())
+    }
+
+    fn smoothing(&mut self, group: Option<usize>) -> ObjResult<()> {
+        self.0.push(format!("s {:?}", group));
+        Ok(())
+    }
+
+    fn merging(&mut self, group: Option<usize>) -> ObjResult<()> {
+        self.0.push(format!("mg {:?}", group));
+        Ok(())
+    }
+
+    fn object(&mut self, name: Option<&str>) -> ObjResult<()> {
+        self.0.push(format!("o {:?}", name));
+        Ok(())
+    }
+
+    fn comment(&mut self, comment: &str) -> ObjResult<()> {
+        self.0.push(format!("#{}", comment));
+        Ok(())
+    }
+}
+
+#[test]
+fn every_statement() -> TestResult {
+    let input = r#"# Header
+o cube # trailing
+mtllib a.mtl b.mtl
+v 1 2 3
+v 4 5 6 0.5
+vt 0.5
+vn 0 0 1
+vp 0.5 0.5
+g side top
+usemtl red
+s 1
+mg off
+p 1 -1
+l 1/1 2/1
+f 1//1 2//1 \
+  -1//1 # joined
+g
+o
+"#;
+    let mut recorder = Recorder::default();
+    visit_obj(input.as_bytes(), &mut recorder)?;
+
+    assert_eq!(
+        recorder.0,
+        [
+            "# Header",
+            "o Some(\"cube\")",
+            "# trailing",
+            "mtllib a.mtl",
+            "mtllib b.mtl",
+            "v 1 2 3 1",
+            "v 4 5 6 0.5",
+            "vt 0.5 0 0",
+            "vn 0 0 1",
+            "vp 0.5 0.5 1",
+            "g side top",
+            "usemtl red",
+            "s Some(1)",
+            "mg None",
+            "p 0",
+            "p 1",
+            "l PT([(0, 0), (1, 0)])",
+            "f PN([(0, 0), (1, 0), (1, 0)])",
+            "# joined",
+            "g default",
+            "o None",
+        ]
+    );
+
+    Ok(())
+}
+
+/// Counts elements without keeping them.
+#[derive(Default)]
+struct Counts {
+    positions: usize,
+    polygons: usize,
+}
+
+impl ObjVisitor for Counts {
+    fn position(&mut self, _: f32, _: f32, _: f32, _: f32) -> ObjResult<()> {
+        self.positions += 1;
+        Ok(())
+    }
+
+    fn face(&mut self, _: Polygon) -> ObjResult<()> {
+        self.polygons += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn same_as_parse_obj() -> TestResult {
+    for name in &["cube.obj", "dome.obj", "sponza.obj", "untitled.obj"] {
+        let path = format!("tests/fixtures/{}", name);
+        let raw = parse_obj(BufReader::new(File::open(&path)?))?;
+
+        let mut counts = Counts::default();
+        visit_obj(BufReader::new(File::open(&path)?), &mut counts)?;
+        assert_eq!(counts.positions, raw.positions.len());
+        assert_eq!(counts.polygons, raw.polygons.len());
+    }
+
+    Ok(())
+}
+
+/// Rejects faces with more than 3 vertices.
+struct TrianglesOnly;
+
+impl ObjVisitor for TrianglesOnly {
+    fn face(&mut self, polygon: Polygon) -> ObjResult<()> {
+        match polygon {
+            Polygon::P(ref vec) if vec.len() == 3 => Ok(()),
+            _ => Err(ObjError::Load(LoadError::new(
+                LoadErrorKind::UntriangulatedModel,
+                "Only triangles are allowed",
+            ))),
+        }
+    }
+}
+
+#[test]
+fn visitor_errors() -> TestResult {
+    let input = "v 0 0 0\nf 1 1 1\nf 1 1 1 1\nf 1 1 1\n";
+
+    match visit_obj(input.as_bytes(), &mut TrianglesOnly) {
+        Err(ObjError::Load(ref e)) => {
+            assert_eq!(*e.kind(), LoadErrorKind::UntriangulatedModel);
+            assert_eq!(e.location().line, Some(3));
+            assert_eq!(e.location().statement.as_deref(), Some("f"));
+        }
+        _ => panic!("Errors of the visitor should stop the parser"),
+    }
+
+    let warnings = visit_obj_with(
+        input.as_bytes(),
+        &ParseOptions::new().lenient(true),
+        &mut TrianglesOnly,
+    )?;
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].line, 3);
+
+    Ok(())
+}
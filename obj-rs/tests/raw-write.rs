@@ -0,0 +1,176 @@
+use obj::raw::object::{write_obj, Line, Polygon, Range};
+use obj::raw::{parse_obj, RawObj};
+use std::fs::File;
+use std::io::BufReader;
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Writes and parses `raw`, and returns the output and the parsed `RawObj`.
+fn round_trip(raw: &RawObj) -> Result<(String, RawObj), Box<dyn std::error::Error>> {
+    let mut output = Vec::new();
+    write_obj(raw, &mut output)?;
+    let parsed = parse_obj(&output[..])?;
+    Ok((String::from_utf8(output)?, parsed))
+}
+
+#[test]
+fn fixtures() -> TestResult {
+    for name in &[
+        "cube.obj",
+        "dome.obj",
+        "empty.obj",
+        "group.obj",
+        "lines_points.obj",
+        "normal-cone.obj",
+        "sponza.obj",
+        "textured-cube.obj",
+        "untitled.obj",
+    ] {
+        let input = BufReader::new(File::open(format!("tests/fixtures/{}", name))?);
+        let raw = parse_obj(input)?;
+
+        let (_, parsed) = round_trip(&raw)?;
+        assert_eq!(parsed, raw, "{} isn't written losslessly", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn all_elements() -> TestResult {
+    let input = r#"
+o everything
+mtllib a.mtl b.mtl
+v 1 2 3
+v 0.5 -0.25 1e-7 2
+v 4 5 6
+vt 0.1
+vt 0.1 0.2
+vt 0.1 0.2 0.3
+vn 0 0 1
+vp 0.5
+vp 0.5 0.5 0.5
+
+p 1
+f 1 2 3
+s 1
+mg 2
+usemtl red
+l 1 2 3
+f 1/1 2/2 3/3
+g second
+p -1 -2
+l 1/1 2/2
+s off
+f 1//1 2//1 3//1
+mg off
+usemtl blue
+f 1/1/1 2/2/1 3/3/1
+"#;
+    let raw = parse_obj(input.as_bytes())?;
+    assert_eq!(raw.lines[1], Line::PT(vec![(0, 0), (1, 1)]));
+    assert_eq!(raw.polygons[2], Polygon::PN(vec![(0, 0), (1, 0), (2, 0)]));
+
+    // Elements without material are written before the others, kind by kind, so `default`
+    // is left between its polygons and started again
+    let (output, parsed) = round_trip(&raw)?;
+    let mut expected = raw.clone();
+    expected.groups["default"].polygons =
+        vec![Range { start: 0, end: 1 }, Range { start: 1, end: 2 }];
+    assert_eq!(raw.groups["default"].polygons, [Range { start: 0, end: 2 }]);
+    assert_eq!(parsed, expected);
+    assert_eq!(
+        output,
+        r#"o everything
+mtllib a.mtl b.mtl
+v 1 2 3
+v 0.5 -0.25 0.0000001 2
+v 4 5 6
+vt 0.1 0
+vt 0.1 0.2
+vt 0.1 0.2 0.3
+vn 0 0 1
+vp 0.5 0
+vp 0.5 0.5 0.5
+p 1
+f 1 2 3
+g second
+usemtl red
+s 1
+mg 2
+p 3
+p 2
+g default
+l 1 2 3
+g second
+l 1/1 2/2
+g default
+f 1/1 2/2 3/3
+g second
+s off
+f 1//1 2//1 3//1
+usemtl blue
+mg off
+f 1/1/1 2/2/1 3/3/1
+"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unnamed_material() -> TestResult {
+    // Elements without material are written before the ones with material
+    let raw = parse_obj(&b"v 0 0 0\nl 1 1\nusemtl red\np 1\nf 1 1 1\n"[..])?;
+    assert_eq!(raw.meshes[""].polygons, []);
+    assert_eq!(raw.meshes[""].lines, [Range { start: 0, end: 1 }]);
+
+    let (output, parsed) = round_trip(&raw)?;
+    assert_eq!(output, "v 0 0 0\nl 1 1\nusemtl red\np 1\nf 1 1 1\n");
+    assert_eq!(parsed, raw);
+
+    let raw = parse_obj(&b"v 0 0 0\np 1\nusemtl red\nl 1 1\nf 1 1 1\n"[..])?;
+    let (output, parsed) = round_trip(&raw)?;
+    assert_eq!(output, "v 0 0 0\np 1\nusemtl red\nl 1 1\nf 1 1 1\n");
+    assert_eq!(parsed, raw);
+
+    Ok(())
+}
+
+#[test]
+fn adjacent_ranges() -> TestResult {
+    // A group which is left and started again keeps both ranges, so it's ended and started again
+    let raw = parse_obj(&b"v 0 0 0\ng a\np 1\ng b\ng a\np 1\n"[..])?;
+    assert_eq!(
+        raw.groups["a"].points,
+        [Range { start: 0, end: 1 }, Range { start: 1, end: 2 }]
+    );
+
+    let (output, parsed) = round_trip(&raw)?;
+    assert_eq!(output, "v 0 0 0\ng a\np 1\ng default\ng a\np 1\n");
+    assert_eq!(parsed, raw);
+
+    // The same goes for every kind of group
+    let input = "o x\nv 0 0 0\ng a b\nusemtl m\ns 1\nmg 2\np 1\no y\no x\ng b\ng a b\nusemtl n\nusemtl m\ns 3\ns 1\nmg 3\nmg 2\np 1\n";
+    let raw = parse_obj(input.as_bytes())?;
+    assert_eq!(raw.meshes["m"].points.len(), 2);
+
+    let (output, parsed) = round_trip(&raw)?;
+    assert_eq!(
+        output,
+        "o x\nv 0 0 0\ng a b\nusemtl m\ns 1\nmg 2\np 1\no\no x\ng b\ng a b\nusemtl default\nusemtl m\ns off\ns 1\nmg off\nmg 2\np 1\n"
+    );
+    assert_eq!(parsed, raw);
+
+    Ok(())
+}
+
+#[test]
+fn multiple_groups() -> TestResult {
+    let raw = parse_obj(&b"v 0 0 0\ng b a\np 1\ng a\np 1\ng\np 1\n"[..])?;
+    let (output, parsed) = round_trip(&raw)?;
+    assert_eq!(output, "v 0 0 0\ng b a\np 1\ng a\np 1\ng default\np 1\n");
+    assert_eq!(parsed, raw);
+
+    Ok(())
+}
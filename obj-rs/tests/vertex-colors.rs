@@ -0,0 +1,124 @@
+use obj::raw::object::write_obj;
+use obj::raw::{parse_obj, parse_obj_with, ParseOptions};
+use obj::{
+    load_obj, ColoredVertex, LoadErrorKind, LoadOptions, NormalGeneration, Obj, ObjError, ObjResult,
+};
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+#[test]
+fn parse() -> TestResult {
+    let input = b"v 0 0 0\nv 1 0 0 0.5 0.25 0\nv 0 1 0 2 0 1 0\nv 1 1 0\n";
+    let raw = parse_obj(&input[..])?;
+
+    assert_eq!(
+        raw.positions,
+        [
+            (0.0, 0.0, 0.0, 1.0),
+            (1.0, 0.0, 0.0, 1.0),
+            (0.0, 1.0, 0.0, 2.0),
+            (1.0, 1.0, 0.0, 1.0),
+        ]
+    );
+    // Positions without color are white
+    assert_eq!(
+        raw.colors,
+        [
+            (1.0, 1.0, 1.0),
+            (0.5, 0.25, 0.0),
+            (0.0, 1.0, 0.0),
+            (1.0, 1.0, 1.0),
+        ]
+    );
+
+    // Models without vertex color don't have any color
+    let raw = parse_obj(&b"v 0 0 0\nv 1 1 1 1\n"[..])?;
+    assert_eq!(raw.colors, []);
+
+    // Other numbers of components are still rejected
+    let options = ParseOptions::new().lenient(true);
+    let raw = parse_obj_with(&b"v 0 0 0 1 1\nv 0 0 0 1 1 1 1 1\nv 1 1 1\n"[..], &options)?;
+    assert_eq!(raw.positions, [(1.0, 1.0, 1.0, 1.0)]);
+    assert_eq!(raw.colors, []);
+    assert_eq!(raw.warnings.len(), 2);
+    assert_eq!(
+        raw.warnings[0].kind,
+        Some(LoadErrorKind::WrongNumberOfArguments)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write() -> TestResult {
+    let input = "v 0 0 0 1 0 0\nv 1 0 0 2 0 1 0\nv 0 1 0 1 1 1\np 1\n";
+    let raw = parse_obj(input.as_bytes())?;
+
+    let mut output = Vec::new();
+    write_obj(&raw, &mut output)?;
+    assert_eq!(String::from_utf8(output)?, input);
+
+    Ok(())
+}
+
+#[test]
+fn object() -> TestResult {
+    let input = b"v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\no a\np 3\np 1\n";
+    let raw = parse_obj(&input[..])?;
+
+    let object = raw.object("a").unwrap();
+    assert_eq!(object.colors, [(0.0, 0.0, 1.0), (1.0, 0.0, 0.0)]);
+
+    Ok(())
+}
+
+#[test]
+fn load() -> TestResult {
+    let input = b"v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nv 1 1 0\nf 1 2 3\nf 2 4 3\n";
+    let options = LoadOptions::new().generate_normals(NormalGeneration::Flat);
+    let model: Obj<ColoredVertex> = obj::load_obj_with(&input[..], &options)?;
+
+    assert_eq!(model.indices, [0, 1, 2, 3, 4, 5]);
+    let colors: Vec<_> = model.vertices.iter().map(|v| v.color).collect();
+    assert_eq!(
+        colors,
+        [
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.0, 0.0, 1.0],
+        ]
+    );
+    assert_eq!(model.vertices[0].normal, [0.0, 0.0, 1.0]);
+
+    // Colors are written back
+    let raw = model.to_raw()?;
+    assert_eq!(raw.colors.len(), 4);
+    let mut output = Vec::new();
+    model.write_obj(&mut output)?;
+    let reloaded: Obj<ColoredVertex> = load_obj(&output[..])?;
+    // Equal vertices are merged, since the generated normals are stored only once
+    let corners = |model: &Obj<ColoredVertex>| -> Vec<ColoredVertex> {
+        model
+            .indices
+            .iter()
+            .map(|&i| model.vertices[i as usize])
+            .collect()
+    };
+    assert_eq!(reloaded.vertices.len(), 4);
+    assert_eq!(corners(&reloaded), corners(&model));
+
+    Ok(())
+}
+
+#[test]
+fn missing_colors() {
+    let input = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n";
+    let result: ObjResult<Obj<ColoredVertex>> = load_obj(&input[..]);
+    match result {
+        Err(ObjError::Load(e)) => assert_eq!(*e.kind(), LoadErrorKind::InsufficientData),
+        _ => panic!("Expected InsufficientData, got {:?}", result),
+    }
+}